@@ -0,0 +1,919 @@
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use futures::future::{BoxFuture, FutureExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use crate::audit::{AuditEntry, AuditLog};
+use crate::branch::{branch_ref_name, extract_ahead_behind_counts, parse_branches, BranchInfo, BranchState, PullPushCountInfo};
+use crate::diff::{DiffFile, DiffResult};
+use crate::diff_options::{DiffOptions, WhitespaceMode};
+use crate::merge::MergeOptions;
+use crate::history::{parse_log, parse_show, parse_show_files, Commit, ShowInfo, LOG_FIELD_SEPARATOR};
+use crate::patch::{build_partial_patch, HunkSelection, PatchDirection};
+use crate::platform::CommandCreationFlags;
+use crate::progress::{parse_progress_line, ProgressCallback, ProgressLineSplitter};
+use crate::status::{parse_status_porcelain_v2, split_staged_unstaged, StatusInfo, StatusResult};
+use crate::temp_index::TempIndex;
+
+// 読み取りのみのコマンドがindexの更新 (index.lockの作成) をしないようにする
+// (`git status`は変更の確認のついでにindexのstat情報を書き戻すため、書き込み中の操作とぶつかる)
+const OPTIONAL_LOCKS_ENV: &str = "GIT_OPTIONAL_LOCKS";
+
+// gitに渡す引数・環境変数・標準入力
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitRequest {
+    pub args: Vec<String>,
+    pub envs: Vec<(String, String)>,
+    pub stdin: Option<Vec<u8>>,
+}
+
+impl GitRequest {
+    pub fn new<I, S>(args: I) -> Self
+        where I: IntoIterator<Item=S>, S: Into<String> {
+        GitRequest {
+            args: args.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+        where I: IntoIterator<Item=S>, S: Into<String> {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn stdin<B: Into<Vec<u8>>>(mut self, stdin: B) -> Self {
+        self.stdin = Some(stdin.into());
+        self
+    }
+}
+
+// `git log`の取得条件
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogQuery {
+    pub is_show_all: bool,
+    pub branch_name: String,
+    pub is_first_parent: bool,
+    pub date_format: String,
+}
+
+// gitの操作をまとめたもの
+// execのみ実装すればgitコマンドを実行するものとして動作する。別の実装(libgit2など)に置き換える場合は各操作を上書きする。
+// 返されるFutureをdropすると実行中のコマンドは中断される
+pub trait GitBackend: Send + Sync {
+    // gitを実行して、成功した場合は標準出力、失敗した場合は標準エラー出力を返す
+    fn exec<'a>(&'a self, repo_path: &'a Path, request: GitRequest) -> BoxFuture<'a, Result<String, String>>;
+
+    // `--progress`を付けたコマンド用。標準エラー出力の進捗を逐次on_progressに渡す
+    // (既定の実装は進捗を通知せずに実行する)
+    fn exec_with_progress<'a>(&'a self,
+                              repo_path: &'a Path,
+                              request: GitRequest,
+                              on_progress: ProgressCallback<'a>) -> BoxFuture<'a, Result<String, String>> {
+        let _ = on_progress;
+        self.exec(repo_path, request)
+    }
+
+    // 標準出力を文字列に変換せずに返す (文字コードや改行コードをそのまま扱う必要がある場合に使う)
+    // (既定の実装はexecの結果を使うので、UTF-8でない内容は置き換えられる)
+    fn exec_bytes<'a>(&'a self, repo_path: &'a Path, request: GitRequest) -> BoxFuture<'a, Result<Vec<u8>, String>> {
+        self.exec(repo_path, request).map(|v| v.map(String::into_bytes)).boxed()
+    }
+
+    fn status<'a>(&'a self, repo_path: &'a Path) -> BoxFuture<'a, Result<StatusResult, String>> {
+        async move {
+            let request = GitRequest::new(["status", "--porcelain=v2", "-z", "-uall"]).env(OPTIONAL_LOCKS_ENV, "0");
+            let output = self.exec(repo_path, request).await?;
+            Ok(split_staged_unstaged(parse_status_porcelain_v2(&output)))
+        }.boxed()
+    }
+
+    // 未追跡ファイルかどうか
+    fn is_untracked<'a>(&'a self, repo_path: &'a Path, file: &'a str) -> BoxFuture<'a, bool> {
+        async move {
+            let request = GitRequest::new(["ls-files", "--others", "--exclude-standard", "--", file]);
+            match self.exec(repo_path, request).await {
+                Ok(output) => !output.is_empty(),
+                Err(_) => false,
+            }
+        }.boxed()
+    }
+
+    // `.git`内のファイルのパス (リポジトリからの相対パスの場合がある)
+    fn git_path<'a>(&'a self, repo_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<PathBuf, String>> {
+        async move {
+            let output = self.exec(repo_path, GitRequest::new(["rev-parse", "--git-path", name])).await?;
+            Ok(PathBuf::from(output.trim()))
+        }.boxed()
+    }
+
+    // index_fileを指定した場合はそのindexとの差分を取る
+    fn diff<'a>(&'a self,
+                repo_path: &'a Path,
+                file: &'a str,
+                staged: bool,
+                options: &'a DiffOptions,
+                index_file: Option<&'a Path>) -> BoxFuture<'a, Result<DiffResult, String>> {
+        async move {
+            let output = self.exec(repo_path, diff_request(file, staged, options, index_file)).await?;
+            Ok(to_diff_result(&output, options))
+        }.boxed()
+    }
+
+    // 削除されたファイルもstagingできるように`-A`を付ける
+    fn stage<'a>(&'a self, repo_path: &'a Path, files: Vec<String>) -> BoxFuture<'a, Result<String, String>> {
+        self.exec(repo_path, GitRequest::new(["add", "-A", "--"]).args(files))
+    }
+
+    // `git restore --staged`は初回コミット前(HEADがない状態)だと失敗するので`git reset`を使う
+    fn unstage<'a>(&'a self, repo_path: &'a Path, infos: Vec<StatusInfo>) -> BoxFuture<'a, Result<String, String>> {
+        let mut request = GitRequest::new(["reset", "-q", "--"]);
+        for v in infos {
+            // リネームの場合は元のパスも戻さないと削除扱いがstagingに残る
+            if let Some(orig_filename) = v.orig_filename {
+                request = request.arg(orig_filename);
+            }
+            request = request.arg(v.filename);
+        }
+        self.exec(repo_path, request)
+    }
+
+    // パッチをindexに適用する (reverseの場合は取り除く)
+    fn apply_patch_to_index<'a>(&'a self, repo_path: &'a Path, patch: Vec<u8>, reverse: bool) -> BoxFuture<'a, Result<String, String>> {
+        let mut request = GitRequest::new(["apply", "--cached", "--recount"]);
+        if reverse {
+            request = request.arg("-R");
+        }
+        self.exec(repo_path, request.arg("-").stdin(patch))
+    }
+
+    // stagingされている内容のみをコミットする (メッセージが空の場合は直前のコミットに追加する)
+    fn commit<'a>(&'a self, repo_path: &'a Path, message: String) -> BoxFuture<'a, Result<String, String>> {
+        let request = if !message.is_empty() {
+            GitRequest::new(["commit", "-m"]).arg(message)
+        } else {
+            GitRequest::new(["commit", "--amend", "--no-edit"])
+        };
+        self.exec(repo_path, request)
+    }
+
+    // 未追跡ファイルを削除する
+    fn clean_files<'a>(&'a self, repo_path: &'a Path, files: Vec<String>) -> BoxFuture<'a, Result<String, String>> {
+        self.exec(repo_path, GitRequest::new(["clean", "-f", "--"]).args(files))
+    }
+
+    // 作業ツリーの変更を破棄する
+    fn checkout_files<'a>(&'a self, repo_path: &'a Path, files: Vec<String>) -> BoxFuture<'a, Result<String, String>> {
+        self.exec(repo_path, GitRequest::new(["checkout", "--"]).args(files))
+    }
+
+    fn push<'a>(&'a self,
+                repo_path: &'a Path,
+                remote: String,
+                set_upstream: bool,
+                on_progress: ProgressCallback<'a>) -> BoxFuture<'a, Result<String, String>> {
+        let mut request = GitRequest::new(["push", "--progress"]);
+        if set_upstream {
+            request = request.arg("-u");
+        }
+        self.exec_with_progress(repo_path, request.arg(remote).arg("HEAD"), on_progress)
+    }
+
+    fn pull<'a>(&'a self, repo_path: &'a Path, prune: bool, on_progress: ProgressCallback<'a>) -> BoxFuture<'a, Result<String, String>> {
+        let mut request = GitRequest::new(["pull", "--progress"]);
+        if prune {
+            request = request.arg("--prune");
+        }
+        self.exec_with_progress(repo_path, request, on_progress)
+    }
+
+    fn fetch<'a>(&'a self, repo_path: &'a Path, prune: bool, on_progress: ProgressCallback<'a>) -> BoxFuture<'a, Result<String, String>> {
+        let mut request = GitRequest::new(["fetch", "--progress"]);
+        if prune {
+            request = request.arg("--prune");
+        }
+        self.exec_with_progress(repo_path, request, on_progress)
+    }
+
+    // parent_dirの下にdirectory_nameのフォルダを作成してcloneする
+    fn clone_repository<'a>(&'a self,
+                            parent_dir: &'a Path,
+                            url: String,
+                            directory_name: String,
+                            on_progress: ProgressCallback<'a>) -> BoxFuture<'a, Result<String, String>> {
+        let request = GitRequest::new(["clone", "--progress", "--"]).arg(url).arg(directory_name);
+        self.exec_with_progress(parent_dir, request, on_progress)
+    }
+
+    fn pull_push_count<'a>(&'a self, repo_path: &'a Path) -> BoxFuture<'a, Result<PullPushCountInfo, String>> {
+        async move {
+            let request = GitRequest::new(["status", "-sb"]).env(OPTIONAL_LOCKS_ENV, "0");
+            let output = self.exec(repo_path, request).await?;
+            let (ahead_count, behind_count) = extract_ahead_behind_counts(output)?;
+            Ok(PullPushCountInfo { push_count: ahead_count, pull_count: behind_count })
+        }.boxed()
+    }
+
+    fn log<'a>(&'a self, repo_path: &'a Path, query: LogQuery) -> BoxFuture<'a, Result<Vec<Commit>, String>> {
+        async move {
+            let mut request = GitRequest::new(["log", "--graph", "--color", "--oneline"])
+                .arg(format!("--date=format:{}", query.date_format))
+                .arg(["--format=", "%h", "%an", "%s", "%ad", "%C(auto)%d%C(reset)"].join(LOG_FIELD_SEPARATOR));
+            if query.is_show_all {
+                request = request.arg("--all");
+            } else {
+                if query.is_first_parent {
+                    request = request.arg("--first-parent");
+                }
+                if !query.branch_name.is_empty() {
+                    request = request.arg(query.branch_name);
+                    request = request.arg("--"); // ファイル/フォルダ名とブランチ名が同じ場合エラーが出るためブランチ名として明示する (https://qiita.com/hakuisan/items/d2e40bec6d2785202885)
+                }
+            }
+
+            let output = self.exec(repo_path, request).await?;
+            Ok(parse_log(&output))
+        }.boxed()
+    }
+
+    fn show<'a>(&'a self, repo_path: &'a Path, hash: String) -> BoxFuture<'a, Result<ShowInfo, String>> {
+        async move {
+            let request = GitRequest::new(["show", "--pretty=format:%H%n%an%n%ad%n%B", "--no-patch"]).arg(hash);
+            let output = self.exec(repo_path, request).await?;
+            Ok(parse_show(&output))
+        }.boxed()
+    }
+
+    fn show_files<'a>(&'a self, repo_path: &'a Path, hash: String) -> BoxFuture<'a, Result<Vec<StatusInfo>, String>> {
+        async move {
+            // 日本語などのパスが8進数でエスケープされないようにする
+            let request = GitRequest::new(["-c", "core.quotePath=false", "show", "--pretty=format:", "--name-status"]).arg(hash);
+            let output = self.exec(repo_path, request).await?;
+            Ok(parse_show_files(&output))
+        }.boxed()
+    }
+
+    // git show --pretty=format: <commit hash> -- <filename>
+    fn show_file_diff<'a>(&'a self, repo_path: &'a Path, hash: String, file: String, options: &'a DiffOptions) -> BoxFuture<'a, Result<DiffResult, String>> {
+        async move {
+            let request = GitRequest::new(options.config_args())
+                .args(["show", "--pretty=format:"])
+                .args(options.args())
+                .arg(hash)
+                .arg("--")
+                .arg(file);
+            let output = self.exec(repo_path, request).await?;
+            Ok(to_diff_result(&output, options))
+        }.boxed()
+    }
+
+    fn branches<'a>(&'a self, repo_path: &'a Path) -> BoxFuture<'a, Result<Vec<BranchInfo>, String>> {
+        async move {
+            let output = self.exec(repo_path, GitRequest::new(["branch", "-a"])).await?;
+            Ok(parse_branches(&output))
+        }.boxed()
+    }
+
+    // ブランチを作成してチェックアウトする
+    fn create_branch<'a>(&'a self, repo_path: &'a Path, branch_name: String) -> BoxFuture<'a, Result<String, String>> {
+        self.exec(repo_path, GitRequest::new(["checkout", "-b"]).arg(branch_name))
+    }
+
+    fn delete_branch<'a>(&'a self, repo_path: &'a Path, branch_name: String) -> BoxFuture<'a, Result<String, String>> {
+        self.exec(repo_path, GitRequest::new(["branch", "-d"]).arg(branch_name))
+    }
+
+    // ブランチまたはコミットをチェックアウトする
+    fn checkout<'a>(&'a self, repo_path: &'a Path, target: String) -> BoxFuture<'a, Result<String, String>> {
+        self.exec(repo_path, GitRequest::new(["checkout"]).arg(target))
+    }
+
+    // 結果の種類の判定はmerge::merge_branchで行う
+    fn merge<'a>(&'a self, repo_path: &'a Path, branch_name: String, options: &'a MergeOptions) -> BoxFuture<'a, Result<String, String>> {
+        self.exec(repo_path, GitRequest::new(["merge"]).args(options.args()).arg(branch_name))
+    }
+
+    // ブランチが存在するかどうか (HEADが特定のコミットを指している場合の判定に使う)
+    fn branch_exists<'a>(&'a self, repo_path: &'a Path, branch_name: String, branch_state: BranchState) -> BoxFuture<'a, bool> {
+        async move {
+            let request = GitRequest::new(["show-ref", "--verify"]).arg(branch_ref_name(&branch_name, branch_state));
+            self.exec(repo_path, request).await.is_ok()
+        }.boxed()
+    }
+}
+
+fn diff_request(file: &str, staged: bool, options: &DiffOptions, index_file: Option<&Path>) -> GitRequest {
+    let mut request = GitRequest::new(options.config_args())
+        .arg("diff")
+        .args(options.args());
+    if staged {
+        request = request.arg("--cached");
+    }
+    if let Some(index_file) = index_file {
+        request = request.env("GIT_INDEX_FILE", index_file.to_string_lossy());
+    }
+    request.args(["--", file])
+}
+
+pub(crate) fn to_diff_result(output: &str, options: &DiffOptions) -> DiffResult {
+    if options.color_moved {
+        DiffResult::from_colored_raw(output)
+    } else {
+        DiffResult::from_raw(output)
+    }
+}
+
+// 指定したリポジトリで実行するgitコマンドを作成する
+// (プロセスのカレントディレクトリは使わない)
+fn git_command(repo_path: &Path) -> Command {
+    let mut command = Command::new("git");
+    command.current_dir(repo_path);
+    command
+}
+
+// gitコマンドを実行する通常の実装
+#[derive(Clone, Default)]
+pub struct CliBackend {
+    // 実行したgitの記録先
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+impl CliBackend {
+    pub fn with_audit_log(audit_log: Arc<AuditLog>) -> Self {
+        CliBackend { audit_log: Some(audit_log) }
+    }
+
+    fn start_audit(&self, repo_path: &Path, request: &GitRequest) -> Option<AuditEntry> {
+        self.audit_log.as_ref().map(|v| v.start(repo_path, &request.args))
+    }
+
+    // gitを起動して標準入力を書き込む
+    async fn spawn(repo_path: &Path, request: &GitRequest) -> Result<Child, String> {
+        let mut command = git_command(repo_path);
+        command
+            .kill_on_drop(true)
+            .args(&request.args)
+            .envs(request.envs.iter().map(|(k, v)| (k, v)))
+            .stdin(if request.stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .set_creation_flags();
+
+        let mut child = command.spawn().map_err(|e| e.to_string())?;
+        if let (Some(input), Some(mut stdin)) = (&request.stdin, child.stdin.take()) {
+            stdin.write_all(input).await.map_err(|e| e.to_string())?;
+            // 閉じないとgitが入力の終わりを待ち続ける
+            drop(stdin);
+        }
+        Ok(child)
+    }
+
+    // 標準エラー出力の進捗を通知しながらgitを実行し、終了状態・標準出力・進捗以外の標準エラー出力を返す
+    async fn run_with_progress(repo_path: &Path,
+                               request: &GitRequest,
+                               on_progress: ProgressCallback<'_>) -> Result<(ExitStatus, String, String), String> {
+        let mut child = Self::spawn(repo_path, request).await?;
+        let (Some(mut stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return Err("Failed to read the output of git.".to_string());
+        };
+
+        let read_stdout = async {
+            let mut buffer = vec![];
+            stdout.read_to_end(&mut buffer).await.map(|_| buffer)
+        };
+
+        // 進捗以外の行 (エラーメッセージなど) は結果として返す
+        let read_stderr = async {
+            let mut splitter = ProgressLineSplitter::default();
+            let mut messages: Vec<String> = vec![];
+            let mut handle_line = |line: String| match parse_progress_line(&line) {
+                Some(progress) => on_progress(progress),
+                None => messages.push(line),
+            };
+
+            let mut buffer = [0; 4096];
+            loop {
+                let size = stderr.read(&mut buffer).await?;
+                if size == 0 {
+                    break;
+                }
+                splitter.push(&buffer[..size]).into_iter().for_each(&mut handle_line);
+            }
+            splitter.finish().into_iter().for_each(&mut handle_line);
+            Ok::<String, std::io::Error>(messages.join("\n"))
+        };
+
+        let (stdout, messages) = tokio::try_join!(read_stdout, read_stderr).map_err(|e| e.to_string())?;
+        let status = child.wait().await.map_err(|e| e.to_string())?;
+        Ok((status, String::from_utf8_lossy(&stdout).to_string(), messages))
+    }
+
+    // gitを実行して、成功した場合は標準出力をそのまま、失敗した場合は標準エラー出力を返す
+    async fn run(&self, repo_path: &Path, request: &GitRequest) -> Result<Vec<u8>, String> {
+        let audit = self.start_audit(repo_path, request);
+        let result = async {
+            let child = Self::spawn(repo_path, request).await?;
+            child.wait_with_output().await.map_err(|e| e.to_string())
+        }.await;
+        match &result {
+            Ok(output) => finish_audit(audit, output.status.code(), &String::from_utf8_lossy(&output.stderr)),
+            Err(e) => finish_audit(audit, None, e),
+        }
+
+        let output = result?;
+        if output.status.success() {
+            Ok(output.stdout)
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).to_string())
+        }
+    }
+}
+
+impl GitBackend for CliBackend {
+    fn exec<'a>(&'a self, repo_path: &'a Path, request: GitRequest) -> BoxFuture<'a, Result<String, String>> {
+        async move {
+            let stdout = self.run(repo_path, &request).await?;
+            Ok(String::from_utf8_lossy(&stdout).to_string())
+        }.boxed()
+    }
+
+    fn exec_bytes<'a>(&'a self, repo_path: &'a Path, request: GitRequest) -> BoxFuture<'a, Result<Vec<u8>, String>> {
+        async move { self.run(repo_path, &request).await }.boxed()
+    }
+
+    fn exec_with_progress<'a>(&'a self,
+                              repo_path: &'a Path,
+                              request: GitRequest,
+                              on_progress: ProgressCallback<'a>) -> BoxFuture<'a, Result<String, String>> {
+        async move {
+            let audit = self.start_audit(repo_path, &request);
+            let result = Self::run_with_progress(repo_path, &request, on_progress).await;
+            match &result {
+                Ok((status, _, messages)) => finish_audit(audit, status.code(), messages),
+                Err(e) => finish_audit(audit, None, e),
+            }
+
+            let (status, stdout, messages) = result?;
+            if status.success() {
+                // push/cloneの結果は標準エラー出力に出るので、標準出力がない場合はそちらを返す
+                Ok(if stdout.is_empty() { messages } else { stdout })
+            } else {
+                Err(messages)
+            }
+        }.boxed()
+    }
+}
+
+fn finish_audit(audit: Option<AuditEntry>, exit_code: Option<i32>, stderr: &str) {
+    if let Some(audit) = audit {
+        audit.finish(exit_code, stderr);
+    }
+}
+
+// 実行するgitのリクエストを通知するbackend (ジョブの記録などに使う)
+// 各操作はexecを経由して実行するので、innerが操作を独自に実装していてもそちらは使われない
+pub struct ObservedBackend<F> {
+    inner: Arc<dyn GitBackend>,
+    observer: F,
+}
+
+impl<F: Fn(&GitRequest) + Send + Sync> ObservedBackend<F> {
+    pub fn new(inner: Arc<dyn GitBackend>, observer: F) -> Self {
+        ObservedBackend { inner, observer }
+    }
+}
+
+impl<F: Fn(&GitRequest) + Send + Sync> GitBackend for ObservedBackend<F> {
+    fn exec<'a>(&'a self, repo_path: &'a Path, request: GitRequest) -> BoxFuture<'a, Result<String, String>> {
+        (self.observer)(&request);
+        self.inner.exec(repo_path, request)
+    }
+
+    fn exec_bytes<'a>(&'a self, repo_path: &'a Path, request: GitRequest) -> BoxFuture<'a, Result<Vec<u8>, String>> {
+        (self.observer)(&request);
+        self.inner.exec_bytes(repo_path, request)
+    }
+
+    fn exec_with_progress<'a>(&'a self,
+                              repo_path: &'a Path,
+                              request: GitRequest,
+                              on_progress: ProgressCallback<'a>) -> BoxFuture<'a, Result<String, String>> {
+        (self.observer)(&request);
+        self.inner.exec_with_progress(repo_path, request, on_progress)
+    }
+}
+
+// 未追跡ファイルのdiffを取るため、一時indexに`git add -N`したものを用意する
+// (ユーザーのindexには一切触れない)
+async fn prepare_untracked_index(backend: &dyn GitBackend, repo_path: &Path, file: &str) -> Option<TempIndex> {
+    if !backend.is_untracked(repo_path, file).await {
+        return None;
+    }
+
+    let temp_index = TempIndex::create(backend, repo_path).await.ok()?;
+    temp_index.add_intent_to_add(backend, file).await.ok()?;
+    Some(temp_index)
+}
+
+// 作業ツリー(stagedの場合はindex)のファイルのdiffを取る
+// 新規追加ファイルのdiffは一時indexを使って取る
+pub async fn diff_file(backend: &dyn GitBackend, repo_path: &Path, file: &str, staged: bool, options: &DiffOptions) -> Result<DiffResult, String> {
+    let temp_index = if staged { None } else { prepare_untracked_index(backend, repo_path, file).await };
+    backend.diff(repo_path, file, staged, options, temp_index.as_ref().map(|v| v.path())).await
+}
+
+// 選択されたhunk/行のみをindexに適用する (`git add -p`相当)
+// selectionsのhunk/行の番号は、optionsで表示したdiffのもの
+pub async fn apply_hunks(backend: &dyn GitBackend,
+                         repo_path: &Path,
+                         file: &str,
+                         selections: &[HunkSelection],
+                         direction: PatchDirection,
+                         options: &DiffOptions) -> Result<String, String> {
+    // 空白などを無視したdiffから作ったパッチはindexにそのまま適用できない
+    if options.whitespace != WhitespaceMode::None || options.ignore_blank_lines {
+        return Err("Lines cannot be partially staged while whitespace changes are ignored.".to_string());
+    }
+    // 番号が同じhunk/行を指すように、表示したときと同じ前後の行数とアルゴリズムでdiffを取り直す
+    // (未追跡ファイルの場合は新規ファイルのパッチとしてindexに適用される)
+    let options = DiffOptions {
+        context_lines: options.context_lines,
+        algorithm: options.algorithm,
+        ..Default::default()
+    };
+    let (staged, temp_index) = match direction {
+        PatchDirection::Stage => (false, prepare_untracked_index(backend, repo_path, file).await),
+        PatchDirection::Unstage => (true, None),
+    };
+    // 文字コードや改行コードを変えずにパッチを作るため、diffの出力はバイト列のまま使う
+    let request = diff_request(file, staged, &options, temp_index.as_ref().map(|v| v.path()));
+    let raw = backend.exec_bytes(repo_path, request).await?;
+    let diff = DiffResult::from_raw(&String::from_utf8_lossy(&raw));
+
+    let file: DiffFile = diff.files.into_iter().next().ok_or("No changes to apply.".to_string())?;
+    if file.is_binary {
+        return Err("Binary files cannot be partially staged.".to_string());
+    }
+    let partial_patch = build_partial_patch(&file, &raw, selections, direction)?.ok_or("No lines are selected.".to_string())?;
+
+    backend.apply_patch_to_index(repo_path, partial_patch, direction == PatchDirection::Unstage).await
+}
+
+// テスト用に、期待するgitの引数と返す結果を順番に登録しておくバックエンド
+#[cfg(test)]
+pub mod scripted {
+    use std::collections::VecDeque;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use futures::future::{BoxFuture, FutureExt};
+    use super::{GitBackend, GitRequest};
+
+    // 任意の引数に一致する
+    pub const ANY_ARG: &str = "<any>";
+
+    // 期待する引数と返す結果
+    type Step = (Vec<String>, Result<String, String>);
+
+    #[derive(Default)]
+    pub struct ScriptedBackend {
+        steps: Mutex<VecDeque<Step>>,
+        requests: Mutex<Vec<GitRequest>>,
+    }
+
+    impl ScriptedBackend {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn expect<S: AsRef<str>>(self, args: &[S], result: Result<&str, &str>) -> Self {
+            let args = args.iter().map(|v| v.as_ref().to_string()).collect();
+            let result = result.map(|v| v.to_string()).map_err(|v| v.to_string());
+            self.steps.lock().unwrap().push_back((args, result));
+            self
+        }
+
+        pub fn requests(&self) -> Vec<GitRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        // 登録した全てのコマンドが実行されたか
+        pub fn assert_finished(&self) {
+            let steps = self.steps.lock().unwrap();
+            assert!(steps.is_empty(), "git commands were not executed: {:?}", steps.iter().map(|v| &v.0).collect::<Vec<_>>());
+        }
+    }
+
+    impl GitBackend for ScriptedBackend {
+        fn exec<'a>(&'a self, _repo_path: &'a Path, request: GitRequest) -> BoxFuture<'a, Result<String, String>> {
+            let (args, result) = self.steps.lock().unwrap()
+                .pop_front()
+                .unwrap_or_else(|| panic!("unexpected git command: {:?}", request.args));
+            let matches = args.len() == request.args.len() &&
+                args.iter().zip(&request.args).all(|(expected, actual)| expected == ANY_ARG || expected == actual);
+            assert!(matches, "expected git {:?}, but got git {:?}", args, request.args);
+
+            self.requests.lock().unwrap().push(request);
+            async move { result }.boxed()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+    use super::scripted::{ScriptedBackend, ANY_ARG};
+    use super::*;
+    use crate::diff_options::DiffAlgorithm;
+    use crate::status::ChangeState;
+
+    const DIFF_ARGS: [&str; 1] = ["--no-color"];
+
+    fn repo_path() -> PathBuf {
+        env::temp_dir()
+    }
+
+    // 既定のオプションでのdiffの引数
+    fn diff_args(tail: &[&str]) -> Vec<String> {
+        ["diff"].iter().chain(&DIFF_ARGS).chain(tail).map(|v| v.to_string()).collect()
+    }
+
+    const NEW_FILE_DIFF: &str = "\
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..3bd1f0e
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++foo
++bar
+";
+
+    #[tokio::test]
+    async fn status_is_split_into_staged_and_unstaged() {
+        let output = "1 M. N... 100644 100644 100644 aaaa bbbb staged.txt\0\
+                      1 .M N... 100644 100644 100644 aaaa bbbb changed.txt\0\
+                      ? new.txt\0";
+        let backend = ScriptedBackend::new()
+            .expect(&["status", "--porcelain=v2", "-z", "-uall"], Ok(output));
+
+        let res = backend.status(&repo_path()).await.unwrap();
+        assert_eq!(res.staged.iter().map(|v| v.filename.as_str()).collect::<Vec<_>>(), ["staged.txt"]);
+        assert_eq!(res.unstaged.iter().map(|v| v.filename.as_str()).collect::<Vec<_>>(), ["changed.txt", "new.txt"]);
+        assert_eq!(res.unstaged[1].change_state, ChangeState::Add);
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn failed_command_returns_stderr() {
+        let backend = ScriptedBackend::new()
+            .expect(&["status", "--porcelain=v2", "-z", "-uall"], Err("fatal: not a git repository"));
+
+        let res = backend.status(&repo_path()).await;
+        assert_eq!(res.unwrap_err(), "fatal: not a git repository");
+    }
+
+    #[tokio::test]
+    async fn diff_of_untracked_file_uses_temporary_index() {
+        let backend = ScriptedBackend::new()
+            .expect(&["ls-files", "--others", "--exclude-standard", "--", "new.txt"], Ok("new.txt\n"))
+            .expect(&["rev-parse", "--git-path", "index"], Ok("zengit-test-missing/index\n"))
+            .expect(&["add", "-N", "--", "new.txt"], Ok(""))
+            .expect(&diff_args(&["--", "new.txt"]), Ok(NEW_FILE_DIFF));
+
+        let res = diff_file(&backend, &repo_path(), "new.txt", false, &DiffOptions::default()).await.unwrap();
+        assert_eq!(res.files.len(), 1);
+        assert_eq!(res.files[0].hunks[0].lines.len(), 2);
+
+        // `add -N`とdiffは同じ一時indexに対して行い、ユーザーのindexは使わない
+        let requests = backend.requests();
+        let index_of = |request: &GitRequest| request.envs.iter().find(|(k, _)| k == "GIT_INDEX_FILE").map(|(_, v)| v.clone());
+        let temp_index = index_of(&requests[2]).expect("add -N must use a temporary index");
+        assert_eq!(index_of(&requests[3]), Some(temp_index.clone()));
+        assert!(!temp_index.ends_with("zengit-test-missing/index"));
+        // diffの後に一時indexは削除される
+        assert!(!Path::new(&temp_index).exists());
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn staged_diff_does_not_check_untracked_files() {
+        let options = DiffOptions {
+            whitespace: WhitespaceMode::IgnoreAll,
+            ..Default::default()
+        };
+        let backend = ScriptedBackend::new()
+            .expect(&["diff", "-w", "--no-color", "--cached", "--", "a.txt"], Ok(""));
+
+        let res = diff_file(&backend, &repo_path(), "a.txt", true, &options).await.unwrap();
+        assert!(res.files.is_empty());
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn stage_hunks_applies_only_selected_lines() {
+        let backend = ScriptedBackend::new()
+            .expect(&["ls-files", "--others", "--exclude-standard", "--", "new.txt"], Ok("new.txt\n"))
+            .expect(&["rev-parse", "--git-path", "index"], Ok("zengit-test-missing/index\n"))
+            .expect(&["add", "-N", "--", "new.txt"], Ok(""))
+            .expect(&diff_args(&["--", "new.txt"]), Ok(NEW_FILE_DIFF))
+            .expect(&["apply", "--cached", "--recount", "-"], Ok(""));
+
+        let selections = vec![HunkSelection { hunk_index: 0, line_indices: Some(vec![1]) }];
+        apply_hunks(&backend, &repo_path(), "new.txt", &selections, PatchDirection::Stage, &DiffOptions::default()).await.unwrap();
+
+        let patch = String::from_utf8(backend.requests()[4].stdin.clone().unwrap()).unwrap();
+        assert!(patch.contains("@@ -0,0 +1,1 @@\n+bar\n"), "{}", patch);
+        assert!(!patch.contains("+foo"));
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn unstage_hunks_reverses_patch_from_index() {
+        let staged = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,2 @@
+-old
++new
+ same
+";
+        let backend = ScriptedBackend::new()
+            .expect(&diff_args(&["--cached", "--", "a.txt"]), Ok(staged))
+            .expect(&["apply", "--cached", "--recount", "-R", "-"], Ok(""));
+
+        let selections = vec![HunkSelection { hunk_index: 0, line_indices: None }];
+        apply_hunks(&backend, &repo_path(), "a.txt", &selections, PatchDirection::Unstage, &DiffOptions::default()).await.unwrap();
+        let patch = String::from_utf8(backend.requests()[1].stdin.clone().unwrap()).unwrap();
+        assert!(patch.contains("-old\n+new\n"), "{}", patch);
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn hunks_are_rebuilt_with_displayed_context_and_algorithm() {
+        let staged = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-old
++new
+";
+        // 色は付けずに、前後の行数とアルゴリズムだけを表示したときに合わせる
+        let options = DiffOptions {
+            context_lines: Some(0),
+            algorithm: DiffAlgorithm::Histogram,
+            color_moved: true,
+            ..Default::default()
+        };
+        let backend = ScriptedBackend::new()
+            .expect(&["diff", "-U0", "--diff-algorithm=histogram", "--no-color", "--cached", "--", "a.txt"], Ok(staged))
+            .expect(&["apply", "--cached", "--recount", "-R", "-"], Ok(""));
+
+        let selections = vec![HunkSelection { hunk_index: 0, line_indices: None }];
+        apply_hunks(&backend, &repo_path(), "a.txt", &selections, PatchDirection::Unstage, &options).await.unwrap();
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn hunks_cannot_be_applied_while_ignoring_whitespace() {
+        let backend = ScriptedBackend::new();
+        let selections = vec![HunkSelection { hunk_index: 0, line_indices: None }];
+        for options in [
+            DiffOptions { whitespace: WhitespaceMode::IgnoreChange, ..Default::default() },
+            DiffOptions { ignore_blank_lines: true, ..Default::default() },
+        ] {
+            let res = apply_hunks(&backend, &repo_path(), "a.txt", &selections, PatchDirection::Stage, &options).await;
+            assert!(res.unwrap_err().contains("whitespace"));
+        }
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn binary_files_cannot_be_partially_staged() {
+        let binary = "\
+diff --git a/img.png b/img.png
+index 1111111..2222222 100644
+Binary files a/img.png and b/img.png differ
+";
+        let backend = ScriptedBackend::new()
+            .expect(&["ls-files", "--others", "--exclude-standard", "--", "img.png"], Ok(""))
+            .expect(&diff_args(&["--", "img.png"]), Ok(binary));
+
+        let selections = vec![HunkSelection { hunk_index: 0, line_indices: None }];
+        let res = apply_hunks(&backend, &repo_path(), "img.png", &selections, PatchDirection::Stage, &DiffOptions::default()).await;
+        assert_eq!(res.unwrap_err(), "Binary files cannot be partially staged.");
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn unstage_also_resets_original_path_of_renames() {
+        let mut renamed = StatusInfo::with_state("new.txt".to_string(), ChangeState::Rename);
+        renamed.orig_filename = Some("old.txt".to_string());
+        let backend = ScriptedBackend::new()
+            .expect(&["reset", "-q", "--", "old.txt", "new.txt", "b.txt"], Ok(""));
+
+        backend.unstage(&repo_path(), vec![renamed, StatusInfo::with_state("b.txt".to_string(), ChangeState::Staging)]).await.unwrap();
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn empty_commit_message_amends() {
+        let backend = ScriptedBackend::new()
+            .expect(&["commit", "--amend", "--no-edit"], Ok(""))
+            .expect(&["commit", "-m", "message"], Ok(""));
+
+        backend.commit(&repo_path(), "".to_string()).await.unwrap();
+        backend.commit(&repo_path(), "message".to_string()).await.unwrap();
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn log_of_branch_is_parsed() {
+        let output = "* ___abc1234___Alice___Fix bug___2024/05/01 (Wed) 10:00___ (HEAD -> main)\n|\n";
+        let backend = ScriptedBackend::new()
+            .expect(&["log", "--graph", "--color", "--oneline", "--date=format:%Y", ANY_ARG, "--first-parent", "main", "--"], Ok(output));
+
+        let query = LogQuery {
+            is_show_all: false,
+            branch_name: "main".to_string(),
+            is_first_parent: true,
+            date_format: "%Y".to_string(),
+        };
+        let res = backend.log(&repo_path(), query).await.unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].hash, "abc1234");
+        assert_eq!(res[0].branch, " (HEAD -> main)");
+        assert_eq!(res[1].graph, "|");
+        assert!(res[1].hash.is_empty());
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn pull_push_count_is_read_from_status() {
+        let backend = ScriptedBackend::new()
+            .expect(&["status", "-sb"], Ok("## main...origin/main [ahead 2, behind 1]\n"));
+
+        let res = backend.pull_push_count(&repo_path()).await.unwrap();
+        assert_eq!(res, PullPushCountInfo { push_count: 2, pull_count: 1 });
+    }
+
+    #[tokio::test]
+    async fn branch_exists_checks_remote_refs() {
+        let backend = ScriptedBackend::new()
+            .expect(&["show-ref", "--verify", "refs/remotes/origin/main"], Ok("abcd refs/remotes/origin/main\n"))
+            .expect(&["show-ref", "--verify", "refs/heads/abcd"], Err("fatal: 'refs/heads/abcd' - not a valid ref"));
+
+        assert!(backend.branch_exists(&repo_path(), "remotes/origin/main".to_string(), BranchState::Remote).await);
+        assert!(!backend.branch_exists(&repo_path(), "abcd".to_string(), BranchState::Current).await);
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn branches_exclude_remote_head() {
+        let output = "* main\n  feature\n  remotes/origin/HEAD -> origin/main\n  remotes/origin/main\n";
+        let backend = ScriptedBackend::new()
+            .expect(&["branch", "-a"], Ok(output));
+
+        let res = backend.branches(&repo_path()).await.unwrap();
+        assert_eq!(res.iter().map(|v| (v.branch_name.as_str(), v.branch_state)).collect::<Vec<_>>(), [
+            ("main", BranchState::Current),
+            ("feature", BranchState::Default),
+            ("remotes/origin/main", BranchState::Remote),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn observed_backend_reports_each_request() {
+        let inner = ScriptedBackend::new()
+            .expect(&["status", "-sb"], Ok("## main...origin/main [behind 3]\n"))
+            .expect(&["fetch", "--progress", "--prune"], Ok(""));
+        let inner = Arc::new(inner);
+        let observed = std::sync::Mutex::new(vec![]);
+        let backend = ObservedBackend::new(inner.clone(), |request: &GitRequest| observed.lock().unwrap().push(request.args.clone()));
+
+        let res = backend.pull_push_count(&repo_path()).await.unwrap();
+        assert_eq!(res, PullPushCountInfo { push_count: 0, pull_count: 3 });
+        backend.fetch(&repo_path(), true, &|_| {}).await.unwrap();
+
+        assert_eq!(observed.into_inner().unwrap(), vec![
+            vec!["status", "-sb"],
+            vec!["fetch", "--progress", "--prune"],
+        ]);
+        inner.assert_finished();
+    }
+}
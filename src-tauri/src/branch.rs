@@ -0,0 +1,89 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum BranchState {
+    Unknown = 0,
+    Default = 1,
+    Current = 2,
+    Remote = 3,
+    All = 4,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BranchInfo {
+    pub branch_name: String,
+    pub branch_state: BranchState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PullPushCountInfo {
+    pub push_count: u16,
+    pub pull_count: u16,
+}
+
+// `git branch -a`の出力をパースする
+pub fn parse_branches(output: &str) -> Vec<BranchInfo> {
+    output
+        .lines()
+        .filter(|line| !line.contains("remotes/origin/HEAD")) // HEADは除外
+        .map(|line| {
+            let mut state: BranchState = BranchState::Default;
+            if line.starts_with("* ") {
+                state = BranchState::Current;
+            }
+
+            let branch_name = line[2..].to_string();
+
+            if branch_name.starts_with("remotes/") {
+                state = BranchState::Remote;
+            }
+
+            BranchInfo {
+                branch_name,
+                branch_state: state,
+            }
+        })
+        .collect()
+}
+
+// `show-ref --verify`で確認する参照名
+pub fn branch_ref_name(branch_name: &str, branch_state: BranchState) -> String {
+    if branch_state == BranchState::Remote {
+        format!("refs/{}", branch_name)
+    } else {
+        format!("refs/heads/{}", branch_name)
+    }
+}
+
+// `git status -sb`の先頭行 (`## main...origin/main [ahead 1, behind 2]`) から件数を取り出す
+// upstreamがない場合や`[gone]`の場合は0件とする
+// (&str と String を両方取れるようにする: https://qiita.com/yasuo-ozu/items/987b7c4a7e2ebab098a4)
+pub fn extract_ahead_behind_counts<'a, S: Into<Cow<'a, str>>>(s: S) -> Result<(u16, u16), &'static str> {
+    let s: Cow<'a, str> = s.into();
+    let status: &str = &s;
+    let header = status.lines().next().unwrap_or("");
+
+    let mut ahead_count = 0;
+    let mut behind_count = 0;
+
+    // ブランチ名に含まれる文字列と区別するため、末尾の[]の中だけを見る
+    let tracking = match (header.rfind('['), header.ends_with(']')) {
+        (Some(start), true) => &header[start + 1..header.len() - 1],
+        _ => return Ok((ahead_count, behind_count)),
+    };
+
+    for part in tracking.split(", ") {
+        if let Some(ahead) = part.strip_prefix("ahead ") {
+            ahead_count = u16::from_str(ahead).map_err(|_| "Failed to parse ahead count")?;
+        }
+        if let Some(behind) = part.strip_prefix("behind ") {
+            behind_count = u16::from_str(behind).map_err(|_| "Failed to parse behind count")?;
+        }
+    }
+
+    Ok((ahead_count, behind_count))
+}
@@ -0,0 +1,311 @@
+// ウィンドウを開かずに結果を出力するサブコマンド (CIやエディタとの連携用)
+// `zengit status --json` のように実行する。--jsonの場合はGUIに送るものと同じ構造をJSONで出力する
+// (Windowsのリリースビルドはコンソールを持たないため、出力はリダイレクト/パイプで受け取る)
+
+use std::ffi::OsString;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use serde::Serialize;
+use zengit_lib::backend::{GitBackend, LogQuery};
+use zengit_lib::branch::{BranchInfo, BranchState, PullPushCountInfo};
+use zengit_lib::history::Commit;
+use zengit_lib::status::{ChangeState, StatusInfo, StatusResult};
+
+pub const USAGE: &str = "\
+Usage: zengit [<path>]
+       zengit <command> [options]
+
+Commands:
+  status        Show staged and unstaged changes
+  log [<branch>] [--all] [--first-parent] [--date-format <format>]
+                Show the commit history
+  branches      List local and remote branches
+  ahead-behind  Show the number of commits to push and pull
+  help          Show this message
+
+Options:
+  -C <path>     Run in <path> instead of the current directory
+  --json        Print the result as JSON";
+
+// 引数の誤りの終了コード (gitの失敗は1)
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+    Status,
+    Log(LogQuery),
+    Branches,
+    AheadBehind,
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliArgs {
+    pub command: CliCommand,
+    pub repo_path: PathBuf,
+    pub json: bool,
+}
+
+// 最初の引数がサブコマンドでない場合はNone (これまでどおりフォルダを開くウィンドウを起動する)
+pub fn parse<I: IntoIterator<Item = OsString>>(args: I) -> Option<Result<CliArgs, String>> {
+    let raw_args = clap_lex::RawArgs::new(args);
+    let mut cursor = raw_args.cursor();
+    raw_args.next_os(&mut cursor); // index 0にはアプリ自体のパスが渡るため、1つ進める
+
+    let command = match raw_args.next(&mut cursor)?.to_value() {
+        Ok("status") => CliCommand::Status,
+        Ok("log") => CliCommand::Log(LogQuery::default()),
+        Ok("branches") => CliCommand::Branches,
+        Ok("ahead-behind") => CliCommand::AheadBehind,
+        Ok("help") | Ok("--help") | Ok("-h") => CliCommand::Help,
+        _ => return None,
+    };
+
+    Some(parse_options(&raw_args, &mut cursor, command))
+}
+
+// サブコマンドより後ろのオプションを解析する
+fn parse_options(raw_args: &clap_lex::RawArgs, cursor: &mut clap_lex::ArgCursor, mut command: CliCommand) -> Result<CliArgs, String> {
+    let mut res = CliArgs {
+        command: CliCommand::Help,
+        repo_path: PathBuf::from("."),
+        json: false,
+    };
+
+    let next_value = |cursor: &mut clap_lex::ArgCursor, name: &str| -> Result<String, String> {
+        raw_args.next_os(cursor)
+            .map(|v| v.to_string_lossy().to_string())
+            .ok_or(format!("{} requires a value", name))
+    };
+
+    while let Some(arg) = raw_args.next(cursor) {
+        let value = arg.to_value_os().to_string_lossy().to_string();
+        match (value.as_str(), &mut command) {
+            ("--json", _) => res.json = true,
+            ("-C", _) => res.repo_path = PathBuf::from(next_value(cursor, "-C")?),
+            ("-h" | "--help", _) => command = CliCommand::Help,
+            ("--all", CliCommand::Log(query)) => query.is_show_all = true,
+            ("--first-parent", CliCommand::Log(query)) => query.is_first_parent = true,
+            ("--date-format", CliCommand::Log(query)) => query.date_format = next_value(cursor, "--date-format")?,
+            (branch, CliCommand::Log(query)) if !branch.starts_with('-') && query.branch_name.is_empty() => {
+                query.branch_name = branch.to_string();
+            }
+            (other, _) => return Err(format!("unexpected argument '{}'", other)),
+        }
+    }
+
+    res.command = command;
+    Ok(res)
+}
+
+// サブコマンドを実行して終了コードを返す
+// date_formatはlogで--date-formatが指定されていない場合に使う
+pub async fn run(args: Result<CliArgs, String>, backend: &dyn GitBackend, date_format: &str) -> i32 {
+    let args = match args {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    let repo_path = &args.repo_path;
+    let res = match args.command {
+        CliCommand::Help => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        CliCommand::Status => backend.status(repo_path).await
+            .map(|v| output(&v, args.json, || format_status(&v))),
+        CliCommand::Log(mut query) => {
+            if query.date_format.is_empty() {
+                query.date_format = date_format.to_string();
+            }
+            // GUI用にグラフとブランチ名に色が付いているので、JSONや端末以外への出力では取り除く
+            let without_color = args.json || !io::stdout().is_terminal();
+            backend.log(repo_path, query).await
+                .map(|v| if without_color { strip_log_colors(v) } else { v })
+                .map(|v| output(&v, args.json, || format_log(&v)))
+        }
+        CliCommand::Branches => backend.branches(repo_path).await
+            .map(|v| output(&v, args.json, || format_branches(&v))),
+        CliCommand::AheadBehind => backend.pull_push_count(repo_path).await
+            .map(|v| output(&v, args.json, || format_ahead_behind(&v))),
+    };
+
+    match res {
+        Ok(text) => {
+            println!("{}", text);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e.trim_end());
+            1
+        }
+    }
+}
+
+fn output<T: Serialize, F: FnOnce() -> String>(value: &T, json: bool, format: F) -> String {
+    if json {
+        serde_json::to_string(value).unwrap_or_default()
+    } else {
+        format()
+    }
+}
+
+fn state_label(state: ChangeState) -> &'static str {
+    match state {
+        ChangeState::Change | ChangeState::Staging => "modified",
+        ChangeState::Delete => "deleted",
+        ChangeState::Add | ChangeState::Untracked => "added",
+        ChangeState::Rename => "renamed",
+        ChangeState::Copy => "copied",
+        ChangeState::TypeChange => "typechange",
+        ChangeState::Unmerged => "unmerged",
+        ChangeState::Ignored => "ignored",
+        ChangeState::SubmoduleChange => "submodule",
+        ChangeState::Unmodified | ChangeState::Unknown => "unknown",
+    }
+}
+
+fn format_status_info(info: &StatusInfo) -> String {
+    match &info.orig_filename {
+        Some(orig_filename) => format!("  {:<11}{} -> {}", state_label(info.change_state), orig_filename, info.filename),
+        None => format!("  {:<11}{}", state_label(info.change_state), info.filename),
+    }
+}
+
+fn format_status(status: &StatusResult) -> String {
+    let mut lines = vec![];
+    for (title, infos) in [("Staged:", &status.staged), ("Unstaged:", &status.unstaged)] {
+        if infos.is_empty() {
+            continue;
+        }
+        lines.push(title.to_string());
+        lines.extend(infos.iter().map(format_status_info));
+    }
+
+    if lines.is_empty() {
+        "No changes".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+// 色のエスケープシーケンス ("\x1b[1;31m"など) を取り除く
+fn strip_sgr(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("\x1b[") {
+        res.push_str(&rest[..pos]);
+        rest = &rest[pos + 2..];
+        let end = rest.find(|c: char| !c.is_ascii_digit() && c != ';').unwrap_or(rest.len());
+        match rest[end..].strip_prefix('m') {
+            Some(tail) => rest = tail,
+            // 色以外のシーケンスはそのまま残す
+            None => res.push_str("\x1b["),
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+fn strip_log_colors(commits: Vec<Commit>) -> Vec<Commit> {
+    commits.into_iter()
+        .map(|v| Commit {
+            graph: strip_sgr(&v.graph),
+            branch: strip_sgr(&v.branch),
+            ..v
+        })
+        .collect()
+}
+
+fn format_log(commits: &[Commit]) -> String {
+    commits.iter()
+        .map(|v| {
+            if v.hash.is_empty() {
+                // グラフのみの行
+                v.graph.clone()
+            } else {
+                format!("{}{} {} {} {}{}", v.graph, v.hash, v.date, v.author, v.message, v.branch)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn format_branches(branches: &[BranchInfo]) -> String {
+    branches.iter()
+        .map(|v| {
+            let mark = if v.branch_state == BranchState::Current { "*" } else { " " };
+            format!("{} {}", mark, v.branch_name)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn format_ahead_behind(count: &PullPushCountInfo) -> String {
+    format!("ahead {}, behind {}", count.push_count, count.pull_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Option<Result<CliArgs, String>> {
+        parse(["zengit"].iter().chain(args).map(OsString::from))
+    }
+
+    #[test]
+    fn path_argument_opens_window() {
+        assert_eq!(parse_args(&[]), None);
+        assert_eq!(parse_args(&["."]), None);
+        assert_eq!(parse_args(&["/path/to/repo"]), None);
+    }
+
+    #[test]
+    fn status_with_options() {
+        let args = parse_args(&["status", "--json", "-C", "/path/to/repo"]).unwrap().unwrap();
+        assert_eq!(args.command, CliCommand::Status);
+        assert_eq!(args.repo_path, PathBuf::from("/path/to/repo"));
+        assert!(args.json);
+    }
+
+    #[test]
+    fn log_with_branch_and_flags() {
+        let args = parse_args(&["log", "feature", "--first-parent", "--date-format", "%Y"]).unwrap().unwrap();
+        assert_eq!(args.command, CliCommand::Log(LogQuery {
+            is_show_all: false,
+            branch_name: "feature".to_string(),
+            is_first_parent: true,
+            date_format: "%Y".to_string(),
+        }));
+        assert!(!args.json);
+    }
+
+    #[test]
+    fn log_colors_are_stripped() {
+        let commit = Commit {
+            graph: "\x1b[31m|\x1b[m \x1b[1;32m*\x1b[m ".to_string(),
+            hash: "abc1234".to_string(),
+            author: "Test User".to_string(),
+            message: "message".to_string(),
+            date: "2024/01/01".to_string(),
+            branch: "\x1b[33m (\x1b[m\x1b[1;36mHEAD -> \x1b[m\x1b[1;32mmain\x1b[m\x1b[33m)\x1b[m".to_string(),
+        };
+        let stripped = strip_log_colors(vec![commit.clone()]);
+        assert_eq!(stripped[0].graph, "| * ");
+        assert_eq!(stripped[0].branch, " (HEAD -> main)");
+        assert_eq!(stripped[0].hash, commit.hash);
+        assert!(!serde_json::to_string(&stripped).unwrap().contains("\\u001b"));
+
+        // 色以外のシーケンスや途中で切れたものはそのまま
+        assert_eq!(strip_sgr("a\x1b[2Kb\x1b["), "a\x1b[2Kb\x1b[");
+    }
+
+    #[test]
+    fn unknown_option_is_an_error() {
+        assert!(parse_args(&["branches", "--all"]).unwrap().is_err());
+        assert!(parse_args(&["status", "-C"]).unwrap().is_err());
+    }
+}
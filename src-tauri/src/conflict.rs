@@ -0,0 +1,396 @@
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use crate::backend::{GitBackend, GitRequest};
+use crate::status::ConflictKind;
+
+// 標準の長さのコンフリクトマーカー
+const OURS_MARKER: &str = "<<<<<<<";
+const BASE_MARKER: &str = "|||||||";
+const SEPARATOR_MARKER: &str = "=======";
+const THEIRS_MARKER: &str = ">>>>>>>";
+
+// マージ中のindexに残っているファイルの各ステージ (blobのハッシュ、そのステージがない場合はNone)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ConflictEntry {
+    pub path: String,
+    pub kind: Option<ConflictKind>,
+    // ステージ1: 共通の祖先
+    pub base: Option<String>,
+    // ステージ2: 現在のブランチ
+    pub ours: Option<String>,
+    // ステージ3: マージするブランチ
+    pub theirs: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum ConflictRegionKind {
+    // 両方で同じ (または自動でマージされた) 部分
+    Common = 0,
+    Conflict = 1,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConflictRegion {
+    pub kind: ConflictRegionKind,
+    // Commonの場合の内容
+    pub text: String,
+    pub ours: String,
+    // diff3形式 (merge.conflictStyle=diff3/zdiff3) の場合のみ
+    pub base: Option<String>,
+    pub theirs: String,
+}
+
+impl ConflictRegion {
+    fn common(text: String) -> Self {
+        ConflictRegion {
+            kind: ConflictRegionKind::Common,
+            text,
+            ours: String::new(),
+            base: None,
+            theirs: String::new(),
+        }
+    }
+
+    fn conflict() -> Self {
+        ConflictRegion { kind: ConflictRegionKind::Conflict, ..Self::common(String::new()) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConflictDetail {
+    pub entry: ConflictEntry,
+    // "<<<<<<< HEAD" / ">>>>>>> feature" のラベル
+    pub ours_label: String,
+    pub theirs_label: String,
+    // 作業ツリーのファイルにマーカーがない場合 (削除と変更の衝突など) はindexの各ステージの内容を1つの衝突とする
+    pub from_markers: bool,
+    pub is_binary: bool,
+    pub regions: Vec<ConflictRegion>,
+}
+
+// 解消に使う側
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum ConflictSide {
+    Ours = 0,
+    Theirs = 1,
+}
+
+// `git ls-files -u -z` の出力 ("<mode> <hash> <stage>\t<path>\0") をファイルごとにまとめる
+pub fn parse_unmerged(output: &str) -> Vec<ConflictEntry> {
+    let mut res: Vec<ConflictEntry> = vec![];
+    for record in output.split('\0').filter(|v| !v.is_empty()) {
+        let Some((info, path)) = record.split_once('\t') else {
+            continue;
+        };
+        let fields: Vec<&str> = info.split(' ').collect();
+        if fields.len() != 3 {
+            continue;
+        }
+
+        let index = match res.iter().position(|v| v.path == path) {
+            Some(index) => index,
+            None => {
+                res.push(ConflictEntry { path: path.to_string(), ..Default::default() });
+                res.len() - 1
+            }
+        };
+        let entry = &mut res[index];
+        let hash = Some(fields[1].to_string());
+        match fields[2] {
+            "1" => entry.base = hash,
+            "2" => entry.ours = hash,
+            "3" => entry.theirs = hash,
+            _ => {}
+        }
+    }
+
+    for entry in &mut res {
+        entry.kind = ConflictKind::from_stages(entry.base.is_some(), entry.ours.is_some(), entry.theirs.is_some());
+    }
+    res
+}
+
+// マーカーの行かどうか (マーカーの後ろは空白とラベルのみ)
+fn marker_label<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    let rest = line.trim_end_matches(['\n', '\r']).strip_prefix(marker)?;
+    if rest.is_empty() {
+        Some("")
+    } else {
+        rest.strip_prefix(' ').map(str::trim)
+    }
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Common,
+    Ours,
+    Base,
+    Theirs,
+}
+
+// コンフリクトマーカーの付いたファイルを衝突部分とそれ以外に分ける
+// 閉じていないマーカーはそのまま共通の部分として扱う
+// 戻り値: (領域, oursのラベル, theirsのラベル)
+pub fn parse_conflict_markers(content: &str) -> (Vec<ConflictRegion>, String, String) {
+    let mut regions = vec![];
+    let (mut ours_label, mut theirs_label) = (String::new(), String::new());
+
+    let mut common = String::new();
+    // 衝突部分の途中 (閉じていない場合に共通の部分へ戻すため、元の行も保持する)
+    let mut pending = String::new();
+    let mut current = ConflictRegion::conflict();
+    let mut section = Section::Common;
+
+    for line in content.split_inclusive('\n') {
+        if section == Section::Common {
+            if let Some(label) = marker_label(line, OURS_MARKER) {
+                if ours_label.is_empty() {
+                    ours_label = label.to_string();
+                }
+                section = Section::Ours;
+                pending.push_str(line);
+            } else {
+                common.push_str(line);
+            }
+            continue;
+        }
+
+        pending.push_str(line);
+        match section {
+            Section::Ours | Section::Base if marker_label(line, SEPARATOR_MARKER) == Some("") => section = Section::Theirs,
+            Section::Ours if marker_label(line, BASE_MARKER).is_some() => {
+                section = Section::Base;
+                current.base = Some(String::new());
+            }
+            Section::Theirs if marker_label(line, THEIRS_MARKER).is_some() => {
+                if theirs_label.is_empty() {
+                    theirs_label = marker_label(line, THEIRS_MARKER).unwrap_or_default().to_string();
+                }
+                if !common.is_empty() {
+                    regions.push(ConflictRegion::common(std::mem::take(&mut common)));
+                }
+                regions.push(std::mem::replace(&mut current, ConflictRegion::conflict()));
+                pending.clear();
+                section = Section::Common;
+            }
+            Section::Ours => current.ours.push_str(line),
+            Section::Base => current.base.get_or_insert_with(String::new).push_str(line),
+            Section::Theirs => current.theirs.push_str(line),
+            Section::Common => {}
+        }
+    }
+
+    common.push_str(&pending);
+    if !common.is_empty() {
+        regions.push(ConflictRegion::common(common));
+    }
+    (regions, ours_label, theirs_label)
+}
+
+pub fn has_conflict_markers(content: &str) -> bool {
+    parse_conflict_markers(content).0.iter().any(|v| v.kind == ConflictRegionKind::Conflict)
+}
+
+// マージ中のファイル (解消していないもの) の一覧
+pub async fn list_conflicts(backend: &dyn GitBackend, repo_path: &Path) -> Result<Vec<ConflictEntry>, String> {
+    let output = backend.exec(repo_path, GitRequest::new(["ls-files", "-u", "-z"])).await?;
+    Ok(parse_unmerged(&output))
+}
+
+async fn find_conflict(backend: &dyn GitBackend, repo_path: &Path, path: &str) -> Result<ConflictEntry, String> {
+    list_conflicts(backend, repo_path).await?
+        .into_iter()
+        .find(|v| v.path == path)
+        .ok_or(format!("'{}' is not in conflict.", path))
+}
+
+// indexのステージの内容 (ステージがない場合は空)
+async fn stage_content(backend: &dyn GitBackend, repo_path: &Path, hash: &Option<String>) -> Result<String, String> {
+    match hash {
+        Some(hash) => backend.exec(repo_path, GitRequest::new(["cat-file", "blob"]).arg(hash)).await,
+        None => Ok(String::new()),
+    }
+}
+
+pub async fn conflict_detail(backend: &dyn GitBackend, repo_path: &Path, path: &str) -> Result<ConflictDetail, String> {
+    let entry = find_conflict(backend, repo_path, path).await?;
+    let content = fs::read(repo_path.join(path)).unwrap_or_default();
+    let mut res = ConflictDetail {
+        entry,
+        ours_label: "ours".to_string(),
+        theirs_label: "theirs".to_string(),
+        from_markers: false,
+        is_binary: content.contains(&0),
+        regions: vec![],
+    };
+    if res.is_binary {
+        return Ok(res);
+    }
+
+    let (regions, ours_label, theirs_label) = parse_conflict_markers(&String::from_utf8_lossy(&content));
+    if regions.iter().any(|v| v.kind == ConflictRegionKind::Conflict) {
+        res.from_markers = true;
+        res.regions = regions;
+        if !ours_label.is_empty() {
+            res.ours_label = ours_label;
+        }
+        if !theirs_label.is_empty() {
+            res.theirs_label = theirs_label;
+        }
+        return Ok(res);
+    }
+
+    let base = stage_content(backend, repo_path, &res.entry.base).await?;
+    let ours = stage_content(backend, repo_path, &res.entry.ours).await?;
+    let theirs = stage_content(backend, repo_path, &res.entry.theirs).await?;
+    if [&base, &ours, &theirs].iter().any(|v| v.contains('\0')) {
+        res.is_binary = true;
+        return Ok(res);
+    }
+    res.regions.push(ConflictRegion {
+        ours,
+        base: res.entry.base.as_ref().map(|_| base),
+        theirs,
+        ..ConflictRegion::conflict()
+    });
+    Ok(res)
+}
+
+// どちらかの内容で解消する (選んだ側で削除されている場合は削除する)
+pub async fn resolve_with_side(backend: &dyn GitBackend, repo_path: &Path, path: &str, side: ConflictSide) -> Result<(), String> {
+    let entry = find_conflict(backend, repo_path, path).await?;
+    let (stage, option) = match side {
+        ConflictSide::Ours => (&entry.ours, "--ours"),
+        ConflictSide::Theirs => (&entry.theirs, "--theirs"),
+    };
+
+    if stage.is_some() {
+        backend.exec(repo_path, GitRequest::new(["checkout", option, "--", path])).await?;
+        backend.exec(repo_path, GitRequest::new(["add", "--", path])).await?;
+    } else {
+        backend.exec(repo_path, GitRequest::new(["rm", "-q", "--ignore-unmatch", "--", path])).await?;
+    }
+    Ok(())
+}
+
+// 編集した内容で解消する
+pub async fn resolve_with_content(backend: &dyn GitBackend, repo_path: &Path, path: &str, content: &str) -> Result<(), String> {
+    find_conflict(backend, repo_path, path).await?;
+    fs::write(repo_path.join(path), content).map_err(|e| e.to_string())?;
+    backend.exec(repo_path, GitRequest::new(["add", "--", path])).await?;
+    Ok(())
+}
+
+// 作業ツリーの内容のまま解消済みにする (マーカーが残っている場合は失敗させる)
+pub async fn mark_resolved(backend: &dyn GitBackend, repo_path: &Path, files: &[String]) -> Result<(), String> {
+    // 空のままgitに渡すと全てのファイルがstagingされるので止める
+    if files.is_empty() {
+        return Err("No files are selected.".to_string());
+    }
+    // 衝突していないファイルの変更まで一緒にstagingしないようにする
+    let conflicts = list_conflicts(backend, repo_path).await?;
+    if let Some(file) = files.iter().find(|file| !conflicts.iter().any(|v| &v.path == *file)) {
+        return Err(format!("'{}' is not in conflict.", file));
+    }
+
+    for file in files {
+        let path = repo_path.join(file);
+        if let Ok(content) = fs::read_to_string(&path) {
+            if has_conflict_markers(&content) {
+                return Err(format!("'{}' still contains conflict markers.", file));
+            }
+        }
+    }
+
+    // 削除されたファイルもindexに反映されるように`-A`を付ける
+    backend.exec(repo_path, GitRequest::new(["add", "-A", "--"]).args(files)).await?;
+    Ok(())
+}
+
+pub async fn is_merge_in_progress(backend: &dyn GitBackend, repo_path: &Path) -> Result<bool, String> {
+    let merge_head = backend.git_path(repo_path, "MERGE_HEAD").await?;
+    Ok(repo_path.join(merge_head).exists())
+}
+
+// 全て解消した後にマージコミットを作成する (メッセージはgitが用意したものを使う)
+pub async fn continue_merge(backend: &dyn GitBackend, repo_path: &Path) -> Result<String, String> {
+    let remaining = list_conflicts(backend, repo_path).await?;
+    if !remaining.is_empty() {
+        let files: Vec<&str> = remaining.iter().map(|v| v.path.as_str()).collect();
+        return Err(format!("Resolve all conflicts first: {}", files.join(", ")));
+    }
+    backend.exec(repo_path, GitRequest::new(["merge", "--continue"]).env("GIT_EDITOR", "true")).await
+}
+
+pub async fn abort_merge(backend: &dyn GitBackend, repo_path: &Path) -> Result<String, String> {
+    backend.exec(repo_path, GitRequest::new(["merge", "--abort"])).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflict(ours: &str, base: Option<&str>, theirs: &str) -> ConflictRegion {
+        ConflictRegion {
+            ours: ours.to_string(),
+            base: base.map(str::to_string),
+            theirs: theirs.to_string(),
+            ..ConflictRegion::conflict()
+        }
+    }
+
+    #[test]
+    fn unmerged_stages_are_grouped_by_path() {
+        let output = ["100644 aaa 1\tboth.txt", "100644 bbb 2\tboth.txt", "100644 ccc 3\tboth.txt",
+                      "100644 ddd 1\tdeleted by them.txt", "100644 eee 2\tdeleted by them.txt", ""].join("\0");
+        let entries = parse_unmerged(&output);
+        assert_eq!(entries, vec![
+            ConflictEntry {
+                path: "both.txt".to_string(),
+                kind: Some(ConflictKind::BothModified),
+                base: Some("aaa".to_string()),
+                ours: Some("bbb".to_string()),
+                theirs: Some("ccc".to_string()),
+            },
+            ConflictEntry {
+                path: "deleted by them.txt".to_string(),
+                kind: Some(ConflictKind::DeletedByThem),
+                base: Some("ddd".to_string()),
+                ours: Some("eee".to_string()),
+                theirs: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn markers_are_split_into_regions() {
+        let content = "a\n<<<<<<< HEAD\nours\n=======\ntheirs 1\ntheirs 2\n>>>>>>> feature\nb\n";
+        let (regions, ours_label, theirs_label) = parse_conflict_markers(content);
+        assert_eq!(regions, vec![
+            ConflictRegion::common("a\n".to_string()),
+            conflict("ours\n", None, "theirs 1\ntheirs 2\n"),
+            ConflictRegion::common("b\n".to_string()),
+        ]);
+        assert_eq!((ours_label.as_str(), theirs_label.as_str()), ("HEAD", "feature"));
+    }
+
+    #[test]
+    fn diff3_markers_include_base() {
+        let content = "<<<<<<< HEAD\r\nours\r\n||||||| merged common ancestors\r\nbase\r\n=======\r\n>>>>>>> feature\r\n";
+        let (regions, _, _) = parse_conflict_markers(content);
+        assert_eq!(regions, vec![conflict("ours\r\n", Some("base\r\n"), "")]);
+    }
+
+    #[test]
+    fn unterminated_markers_are_common_text() {
+        let content = "a\n<<<<<<< HEAD\nours\n=======\n";
+        let (regions, _, _) = parse_conflict_markers(content);
+        assert_eq!(regions, vec![ConflictRegion::common(content.to_string())]);
+        assert!(!has_conflict_markers(content));
+        assert!(!has_conflict_markers("======= not a marker\n"));
+    }
+}
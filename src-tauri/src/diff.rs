@@ -0,0 +1,635 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use crate::diff_options::{SGR_NEW, SGR_OLD};
+use crate::word_diff::{annotate_word_diff, ChangeSpan};
+
+// Typescript側のenumがswitch-caseで正しく動作しなかったのでu8としてserialize
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DiffFileStatus {
+    Modified = 0,
+    Added = 1,
+    Deleted = 2,
+    Renamed = 3,
+    Copied = 4,
+}
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DiffLineKind {
+    Context = 0,
+    Added = 1,
+    Removed = 2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    // 先頭の"+", "-", " "を除いた内容
+    pub content: String,
+    pub old_line_number: Option<u32>,
+    pub new_line_number: Option<u32>,
+    // 直後に"\ No newline at end of file"が付いているか
+    pub no_newline_at_eof: bool,
+    // 対になる削除行/追加行と比較した行内の変更箇所
+    #[serde(default)]
+    pub changes: Vec<ChangeSpan>,
+    // `--color-moved`で移動されたと判定された行
+    #[serde(default)]
+    pub is_moved: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    // "@@ ... @@"の後ろに付く関数名などの文字列
+    pub section: String,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffFile {
+    // 新規追加の場合はold_path、削除の場合はnew_pathがNone
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub status: DiffFileStatus,
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    // リネーム/コピーの類似度 (%)
+    pub similarity: Option<u8>,
+    pub is_binary: bool,
+    // マージコミットの`diff --cc`形式
+    pub is_combined: bool,
+    // "diff --git"から"+++"までのヘッダー行 (部分stagingのパッチ作成に使う)
+    pub header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl DiffFile {
+    fn new() -> Self {
+        DiffFile {
+            old_path: None,
+            new_path: None,
+            status: DiffFileStatus::Modified,
+            old_mode: None,
+            new_mode: None,
+            similarity: None,
+            is_binary: false,
+            is_combined: false,
+            header: vec![],
+            hunks: vec![],
+        }
+    }
+}
+
+// フロントエンドに送るdiffの結果
+// (raw は既存の表示処理用に残している)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DiffResult {
+    pub raw: String,
+    pub files: Vec<DiffFile>,
+}
+
+impl DiffResult {
+    pub fn from_raw(raw: &str) -> Self {
+        let mut files = parse_diff(raw);
+        annotate_word_diff(&mut files);
+
+        DiffResult {
+            raw: raw.to_string(),
+            files,
+        }
+    }
+
+    // `--color=always --color-moved`付きの出力から作成する
+    pub fn from_colored_raw(colored: &str) -> Self {
+        let lines: Vec<(String, bool)> = colored.lines().map(strip_colors).collect();
+        let raw = lines.iter().map(|(line, _)| line.as_str()).collect::<Vec<&str>>().join("\n");
+
+        let mut files = parse_diff_lines(lines.iter().map(|(line, is_moved)| (line.as_str(), *is_moved)));
+        annotate_word_diff(&mut files);
+
+        DiffResult {
+            raw,
+            files,
+        }
+    }
+}
+
+// 行から色のエスケープシーケンスを取り除き、移動された行の色だったかどうかを返す
+fn strip_colors(line: &str) -> (String, bool) {
+    let mut res = String::with_capacity(line.len());
+    let mut first_sgr: Option<&str> = None;
+    let mut rest = line;
+
+    while let Some(pos) = rest.find("\x1b[") {
+        res.push_str(&rest[..pos]);
+        let sequence = &rest[pos..];
+        match sequence.find('m') {
+            Some(end) => {
+                if res.is_empty() && first_sgr.is_none() {
+                    first_sgr = Some(&sequence[..=end]);
+                }
+                rest = &sequence[end + 1..];
+            }
+            None => {
+                rest = "";
+            }
+        }
+    }
+    res.push_str(rest);
+
+    let is_change_line = res.starts_with('+') || res.starts_with('-');
+    let is_moved = is_change_line && matches!(first_sgr, Some(sgr) if sgr != SGR_OLD && sgr != SGR_NEW);
+
+    (res, is_moved)
+}
+
+// "@@ -1,2 +3,4 @@ section" をパースする
+pub fn parse_hunk_header(line: &str) -> Option<(u32, u32, u32, u32, String)> {
+    // マージコミットの場合は"@@@ -1,2 -1,2 +1,3 @@@"のように親の数+1個の"@"で囲まれる
+    let marker_len = line.chars().take_while(|c| *c == '@').count();
+    if marker_len < 2 {
+        return None;
+    }
+    let marker = &line[..marker_len];
+
+    let rest = line[marker_len..].trim_start();
+    let (ranges, section) = rest.split_once(&format!(" {}", marker))?;
+
+    let parse_range = |range: &str| -> Option<(u32, u32)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+
+    // 変更前は最初の親の範囲を使う
+    let mut old_range = None;
+    let mut new_range = None;
+    for range in ranges.split(' ') {
+        if let Some(v) = range.strip_prefix('-') {
+            if old_range.is_none() {
+                old_range = Some(parse_range(v)?);
+            }
+        } else if let Some(v) = range.strip_prefix('+') {
+            new_range = Some(parse_range(v)?);
+        }
+    }
+
+    let (old_start, old_count) = old_range?;
+    let (new_start, new_count) = new_range?;
+
+    Some((old_start, old_count, new_start, new_count, section.trim_start().to_string()))
+}
+
+// gitのCスタイルでクォートされたパス ("\343\201\202.txt" など) を元に戻す
+fn unquote_path(path: &str) -> String {
+    let Some(inner) = path.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return path.to_string();
+    };
+
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('a') => bytes.push(0x07),
+            Some('b') => bytes.push(0x08),
+            Some('f') => bytes.push(0x0c),
+            Some('v') => bytes.push(0x0b),
+            Some(d) if d.is_digit(8) => {
+                let mut value = d.to_digit(8).unwrap_or(0);
+                for _ in 0..2 {
+                    match chars.peek().and_then(|v| v.to_digit(8)) {
+                        Some(v) => {
+                            value = value * 8 + v;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => {}
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+// "--- a/path" などのパス部分を取り出す ("/dev/null"の場合はNone)
+fn parse_header_path(path: &str) -> Option<String> {
+    // パスにスペースが含まれる場合は末尾にタブが付く
+    let path = unquote_path(path.trim_end_matches('\t'));
+    if path == "/dev/null" {
+        return None;
+    }
+
+    match path.split_once('/') {
+        Some((prefix, rest)) if prefix.len() == 1 => Some(rest.to_string()),
+        _ => Some(path),
+    }
+}
+
+// "diff --git a/path b/path" からパスを取り出す
+// (バイナリファイルなど"---"/"+++"行がない場合に使う)
+fn parse_diff_git_line(line: &str) -> Option<(String, String)> {
+    let rest = line.strip_prefix("diff --git ").or_else(|| line.strip_prefix("diff --cc ").or_else(|| line.strip_prefix("diff --combined ")))?;
+
+    if let Some(quoted) = rest.strip_prefix('"') {
+        // クォートされている場合はクォートの終わりで区切る
+        let end = quoted.find("\" ").map(|v| v + 2)?;
+        let (a, b) = rest.split_at(end);
+        return Some((parse_header_path(a)?, parse_header_path(b.trim_start())?));
+    }
+
+    // 同じパスが2回並ぶので中央で区切る (リネームの場合は"rename from/to"行で上書きされる)
+    let half = rest.len() / 2;
+    if rest.is_char_boundary(half) && rest.as_bytes().get(half) == Some(&b' ') {
+        let (a, b) = rest.split_at(half);
+        return Some((parse_header_path(a)?, parse_header_path(b.trim_start())?));
+    }
+
+    // `diff --cc path` の場合
+    Some((rest.to_string(), rest.to_string()))
+}
+
+// `git diff` / `git show` の出力を解析する
+pub fn parse_diff(raw: &str) -> Vec<DiffFile> {
+    parse_diff_lines(raw.lines().map(|line| (line, false)))
+}
+
+fn parse_diff_lines<'a>(lines: impl Iterator<Item = (&'a str, bool)>) -> Vec<DiffFile> {
+    let mut files: Vec<DiffFile> = vec![];
+    let mut old_line = 0;
+    let mut new_line = 0;
+
+    for (line, is_moved) in lines {
+        if line.starts_with("diff --git ") || line.starts_with("diff --cc ") || line.starts_with("diff --combined ") {
+            let mut file = DiffFile::new();
+            file.is_combined = !line.starts_with("diff --git ");
+            if let Some((old_path, new_path)) = parse_diff_git_line(line) {
+                file.old_path = Some(old_path);
+                file.new_path = Some(new_path);
+            }
+            file.header.push(line.to_string());
+            files.push(file);
+            continue;
+        }
+
+        let Some(file) = files.last_mut() else {
+            // `git show`のコミット情報などdiff以前の行は無視
+            continue;
+        };
+
+        if let Some(hunk) = file.hunks.last_mut() {
+            // 先頭の記号の数 (マージコミットの場合は親の数)
+            let prefix_len = if file.is_combined { hunk.header.chars().take_while(|c| *c == '@').count() - 1 } else { 1 };
+
+            if line.starts_with('\\') {
+                if let Some(last) = hunk.lines.last_mut() {
+                    last.no_newline_at_eof = true;
+                }
+                continue;
+            }
+
+            let is_hunk_line = line.is_empty() || line.starts_with([' ', '+', '-']);
+            if is_hunk_line && !line.starts_with("@@") {
+                let prefix: String = line.chars().take(prefix_len).collect();
+                let content = line.get(prefix.len()..).unwrap_or("").to_string();
+
+                // 変更後に存在する行か、変更前(マージコミットの場合は最初の親)に存在する行か
+                let first = prefix.chars().next().unwrap_or(' ');
+                let in_new = !prefix.contains('-');
+                let in_old = if in_new { first == ' ' } else { first == '-' };
+
+                let kind = match (in_old, in_new) {
+                    (true, true) => DiffLineKind::Context,
+                    (_, true) => DiffLineKind::Added,
+                    _ => DiffLineKind::Removed,
+                };
+
+                let old_line_number = in_old.then(|| {
+                    old_line += 1;
+                    old_line - 1
+                });
+                let new_line_number = in_new.then(|| {
+                    new_line += 1;
+                    new_line - 1
+                });
+
+                hunk.lines.push(DiffLine {
+                    kind,
+                    content,
+                    old_line_number,
+                    new_line_number,
+                    no_newline_at_eof: false,
+                    changes: vec![],
+                    is_moved,
+                });
+                continue;
+            }
+        }
+
+        if line.starts_with("@@") {
+            if let Some((old_start, old_lines, new_start, new_lines, section)) = parse_hunk_header(line) {
+                old_line = old_start;
+                new_line = new_start;
+                file.hunks.push(Hunk {
+                    header: line.to_string(),
+                    old_start,
+                    old_lines,
+                    new_start,
+                    new_lines,
+                    section,
+                    lines: vec![],
+                });
+            }
+            continue;
+        }
+
+        // ここから下はヘッダー行
+        file.header.push(line.to_string());
+
+        if let Some(v) = line.strip_prefix("--- ") {
+            file.old_path = parse_header_path(v);
+        } else if let Some(v) = line.strip_prefix("+++ ") {
+            file.new_path = parse_header_path(v);
+        } else if let Some(v) = line.strip_prefix("old mode ") {
+            file.old_mode = Some(v.to_string());
+        } else if let Some(v) = line.strip_prefix("new mode ") {
+            file.new_mode = Some(v.to_string());
+        } else if let Some(v) = line.strip_prefix("new file mode ") {
+            file.status = DiffFileStatus::Added;
+            file.old_path = None;
+            file.new_mode = Some(v.to_string());
+        } else if let Some(v) = line.strip_prefix("deleted file mode ") {
+            file.status = DiffFileStatus::Deleted;
+            file.new_path = None;
+            file.old_mode = Some(v.to_string());
+        } else if let Some(v) = line.strip_prefix("rename from ") {
+            file.status = DiffFileStatus::Renamed;
+            file.old_path = Some(unquote_path(v));
+        } else if let Some(v) = line.strip_prefix("rename to ") {
+            file.status = DiffFileStatus::Renamed;
+            file.new_path = Some(unquote_path(v));
+        } else if let Some(v) = line.strip_prefix("copy from ") {
+            file.status = DiffFileStatus::Copied;
+            file.old_path = Some(unquote_path(v));
+        } else if let Some(v) = line.strip_prefix("copy to ") {
+            file.status = DiffFileStatus::Copied;
+            file.new_path = Some(unquote_path(v));
+        } else if let Some(v) = line.strip_prefix("similarity index ") {
+            file.similarity = v.trim_end_matches('%').parse().ok();
+        } else if let Some(v) = line.strip_prefix("index ") {
+            // "index abc..def 100644" の場合は末尾がモード
+            if let Some((_, mode)) = v.split_once(' ') {
+                if !mode.contains(',') {
+                    file.old_mode.get_or_insert_with(|| mode.to_string());
+                    file.new_mode.get_or_insert_with(|| mode.to_string());
+                }
+            }
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.is_binary = true;
+        }
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(hunk: &Hunk) -> Vec<DiffLineKind> {
+        hunk.lines.iter().map(|v| v.kind).collect()
+    }
+
+    #[test]
+    fn mode_change_without_hunks() {
+        let files = parse_diff("\
+diff --git a/mode.sh b/mode.sh
+old mode 100644
+new mode 100755
+");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].status, DiffFileStatus::Modified);
+        assert_eq!(files[0].old_path.as_deref(), Some("mode.sh"));
+        assert_eq!(files[0].new_path.as_deref(), Some("mode.sh"));
+        assert_eq!(files[0].old_mode.as_deref(), Some("100644"));
+        assert_eq!(files[0].new_mode.as_deref(), Some("100755"));
+        assert!(files[0].hunks.is_empty());
+    }
+
+    #[test]
+    fn rename_and_copy_keep_similarity() {
+        let files = parse_diff("\
+diff --git a/orig.txt b/renamed.txt
+similarity index 79%
+rename from orig.txt
+rename to renamed.txt
+index b2f931a..17eb8c9 100644
+--- a/orig.txt
++++ b/renamed.txt
+@@ -2,4 +2,4 @@ one
+ two
+ three
+ four
+-five
++FIVE
+diff --git a/orig.txt b/copy.txt
+similarity index 100%
+copy from orig.txt
+copy to copy.txt
+");
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].status, DiffFileStatus::Renamed);
+        assert_eq!(files[0].old_path.as_deref(), Some("orig.txt"));
+        assert_eq!(files[0].new_path.as_deref(), Some("renamed.txt"));
+        assert_eq!(files[0].similarity, Some(79));
+        assert_eq!(files[0].old_mode.as_deref(), Some("100644"));
+        assert_eq!(files[0].hunks[0].section, "one");
+
+        assert_eq!(files[1].status, DiffFileStatus::Copied);
+        assert_eq!(files[1].old_path.as_deref(), Some("orig.txt"));
+        assert_eq!(files[1].new_path.as_deref(), Some("copy.txt"));
+        assert_eq!(files[1].similarity, Some(100));
+        assert!(files[1].hunks.is_empty());
+    }
+
+    #[test]
+    fn binary_file_takes_paths_from_diff_line() {
+        let files = parse_diff("\
+diff --git a/bin.dat b/bin.dat
+index bdc955b..8835708 100644
+Binary files a/bin.dat and b/bin.dat differ
+diff --git a/new image.png b/new image.png
+new file mode 100644
+index 0000000..8835708
+GIT binary patch
+literal 2
+JcmZQ%0000C01E&B
+");
+        assert_eq!(files.len(), 2);
+        assert!(files[0].is_binary);
+        assert_eq!(files[0].new_path.as_deref(), Some("bin.dat"));
+        assert!(files[1].is_binary);
+        assert_eq!(files[1].status, DiffFileStatus::Added);
+        assert_eq!(files[1].old_path, None);
+        assert_eq!(files[1].new_path.as_deref(), Some("new image.png"));
+        assert!(files[1].hunks.is_empty());
+    }
+
+    #[test]
+    fn combined_diff_is_compared_with_first_parent() {
+        let files = parse_diff("\
+diff --cc f.txt
+index 7c75921,5a5fa03..0000000
+--- a/f.txt
++++ b/f.txt
+@@@ -1,2 -1,2 +1,3 @@@
+ +ours
++ theirs
+  same
+");
+        assert_eq!(files.len(), 1);
+        assert!(files[0].is_combined);
+        assert_eq!(files[0].new_path.as_deref(), Some("f.txt"));
+        let hunk = &files[0].hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines), (1, 2, 1, 3));
+        // 最初の親(HEAD)にある行はコンテキストとして扱う
+        assert_eq!(kinds(hunk), [DiffLineKind::Context, DiffLineKind::Added, DiffLineKind::Context]);
+        assert_eq!(hunk.lines.iter().map(|v| v.content.as_str()).collect::<Vec<_>>(), ["ours", "theirs", "same"]);
+        assert_eq!(hunk.lines[2].old_line_number, Some(2));
+        assert_eq!(hunk.lines[2].new_line_number, Some(3));
+    }
+
+    #[test]
+    fn no_newline_marker_is_attached_to_previous_line() {
+        let files = parse_diff("\
+diff --git a/nn.txt b/nn.txt
+index 1b32298..6e94b48 100644
+--- a/nn.txt
++++ b/nn.txt
+@@ -1,2 +1,2 @@
+ x
+-y
+\\ No newline at end of file
++z
+\\ No newline at end of file
+");
+        let lines = &files[0].hunks[0].lines;
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines.iter().map(|v| v.no_newline_at_eof).collect::<Vec<_>>(), [false, true, true]);
+        assert_eq!(lines[2].content, "z");
+    }
+
+    #[test]
+    fn line_numbers_restart_at_each_hunk() {
+        let files = parse_diff("\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,3 @@
+ a
++x
+ b
+@@ -10,3 +11,2 @@ fn main() {
+ j
+-k
+ l
+");
+        let hunks = &files[0].hunks;
+        assert_eq!(hunks.len(), 2);
+        let numbers = |hunk: &Hunk| hunk.lines.iter().map(|v| (v.old_line_number, v.new_line_number)).collect::<Vec<_>>();
+        assert_eq!(numbers(&hunks[0]), [(Some(1), Some(1)), (None, Some(2)), (Some(2), Some(3))]);
+        assert_eq!(numbers(&hunks[1]), [(Some(10), Some(11)), (Some(11), None), (Some(12), Some(12))]);
+        assert_eq!(hunks[1].section, "fn main() {");
+    }
+
+    // `git diff --color=always --color-moved=zebra`にDiffOptions::config_argsの色を指定した実際の出力
+    const MOVED_COLORED_DIFF: &str = "\
+\x1b[1mdiff --git a/m.txt b/m.txt\x1b[m
+\x1b[1mindex b515c9c..e122838 100644\x1b[m
+\x1b[1m--- a/m.txt\x1b[m
+\x1b[1m+++ b/m.txt\x1b[m
+\x1b[36m@@ -1,8 +1,8 @@\x1b[m
+ header\x1b[m
+\x1b[35m-the first moved line of text\x1b[m
+\x1b[35m-the second moved line of text\x1b[m
+ keep 1\x1b[m
+ keep 2\x1b[m
+ keep 3\x1b[m
+ keep 4\x1b[m
+\x1b[36m+\x1b[m\x1b[36mthe first moved line of text\x1b[m
+\x1b[36m+\x1b[m\x1b[36mthe second moved line of text\x1b[m
+ end\x1b[m
+";
+
+    const NOT_MOVED_COLORED_DIFF: &str = "\
+\x1b[1mdiff --git a/m.txt b/m.txt\x1b[m
+\x1b[1mindex e3cfc59..5ff0a38 100644\x1b[m
+\x1b[1m--- a/m.txt\x1b[m
+\x1b[1m+++ b/m.txt\x1b[m
+\x1b[36m@@ -1,3 +1,3 @@\x1b[m
+ header\x1b[m
+\x1b[31m-middle\x1b[m
+\x1b[32m+\x1b[m\x1b[32mmiddle x\x1b[m
+ end\x1b[m
+";
+
+    #[test]
+    fn moved_lines_are_detected_from_colors() {
+        let result = DiffResult::from_colored_raw(MOVED_COLORED_DIFF);
+        assert!(!result.raw.contains('\x1b'));
+        assert!(result.raw.starts_with("diff --git a/m.txt b/m.txt\nindex b515c9c..e122838 100644\n"));
+
+        let file = &result.files[0];
+        assert_eq!(file.new_path.as_deref(), Some("m.txt"));
+        assert_eq!(file.hunks[0].header, "@@ -1,8 +1,8 @@");
+        let lines = &file.hunks[0].lines;
+        assert_eq!(lines.iter().map(|v| v.is_moved).collect::<Vec<_>>(),
+                   [false, true, true, false, false, false, false, true, true, false]);
+        assert_eq!(lines[1].content, "the first moved line of text");
+        assert_eq!(lines[7].kind, DiffLineKind::Added);
+        assert_eq!(lines[7].content, "the first moved line of text");
+    }
+
+    #[test]
+    fn changed_lines_in_normal_colors_are_not_moved() {
+        let result = DiffResult::from_colored_raw(NOT_MOVED_COLORED_DIFF);
+        let lines = &result.files[0].hunks[0].lines;
+        assert_eq!(kinds(&result.files[0].hunks[0]),
+                   [DiffLineKind::Context, DiffLineKind::Removed, DiffLineKind::Added, DiffLineKind::Context]);
+        assert!(lines.iter().all(|v| !v.is_moved));
+        assert_eq!(lines[2].content, "middle x");
+        // 色を取り除いた内容で単語単位の差分を取る
+        assert_eq!(lines[2].changes, [ChangeSpan { start: 6, end: 8 }]);
+    }
+
+    #[test]
+    fn strip_colors_ignores_non_change_lines() {
+        assert_eq!(strip_colors("\x1b[36m@@ -1,3 +1,3 @@\x1b[m"), ("@@ -1,3 +1,3 @@".to_string(), false));
+        assert_eq!(strip_colors(" keep\x1b[m"), (" keep".to_string(), false));
+        assert_eq!(strip_colors("\x1b[31m-middle\x1b[m"), ("-middle".to_string(), false));
+        assert_eq!(strip_colors("\x1b[1;35m-moved\x1b[m"), ("-moved".to_string(), true));
+    }
+}
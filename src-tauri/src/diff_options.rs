@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+// 移動された行の判定に使う色
+// 通常の削除/追加行を赤/緑に固定し、それ以外の色で出力された行を移動された行とみなす
+const COLOR_OLD: &str = "red";
+const COLOR_NEW: &str = "green";
+pub const SGR_OLD: &str = "\x1b[31m";
+pub const SGR_NEW: &str = "\x1b[32m";
+const COLOR_OLD_MOVED: &str = "magenta";
+const COLOR_NEW_MOVED: &str = "cyan";
+const COLOR_OLD_MOVED_ALTERNATIVE: &str = "bold magenta";
+const COLOR_NEW_MOVED_ALTERNATIVE: &str = "bold cyan";
+
+// Typescript側のenumがswitch-caseで正しく動作しなかったのでu8としてserialize
+#[derive(Debug, Clone, Copy, Default, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum WhitespaceMode {
+    #[default]
+    None = 0,
+    IgnoreAll = 1,    // -w
+    IgnoreChange = 2, // -b
+    IgnoreAtEol = 3,  // --ignore-space-at-eol
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DiffAlgorithm {
+    // gitの設定 (diff.algorithm) に従う
+    #[default]
+    Default = 0,
+    Myers = 1,
+    Patience = 2,
+    Histogram = 3,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DiffOptions {
+    pub whitespace: WhitespaceMode,
+    pub ignore_blank_lines: bool,
+    // Noneの場合はgitの設定に従う (通常は3行)
+    pub context_lines: Option<u32>,
+    pub algorithm: DiffAlgorithm,
+    // リネーム/コピー検出の類似度の閾値 (%)。Noneの場合はgitの設定に従う
+    pub rename_threshold: Option<u8>,
+    pub copy_threshold: Option<u8>,
+    pub color_moved: bool,
+}
+
+impl DiffOptions {
+    // `git -c ... diff` のようにサブコマンドより前に付ける引数
+    pub fn config_args(&self) -> Vec<String> {
+        if !self.color_moved {
+            return vec![];
+        }
+
+        [
+            ("color.diff.old", COLOR_OLD),
+            ("color.diff.new", COLOR_NEW),
+            ("color.diff.oldMoved", COLOR_OLD_MOVED),
+            ("color.diff.newMoved", COLOR_NEW_MOVED),
+            ("color.diff.oldMovedAlternative", COLOR_OLD_MOVED_ALTERNATIVE),
+            ("color.diff.newMovedAlternative", COLOR_NEW_MOVED_ALTERNATIVE),
+        ]
+            .iter()
+            .flat_map(|(key, value)| ["-c".to_string(), format!("{}={}", key, value)])
+            .collect()
+    }
+
+    // サブコマンドの後ろに付ける引数
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![];
+
+        match self.whitespace {
+            WhitespaceMode::None => {}
+            WhitespaceMode::IgnoreAll => args.push("-w".to_string()),
+            WhitespaceMode::IgnoreChange => args.push("-b".to_string()),
+            WhitespaceMode::IgnoreAtEol => args.push("--ignore-space-at-eol".to_string()),
+        }
+
+        if self.ignore_blank_lines {
+            args.push("--ignore-blank-lines".to_string());
+        }
+
+        if let Some(context_lines) = self.context_lines {
+            args.push(format!("-U{}", context_lines));
+        }
+
+        match self.algorithm {
+            DiffAlgorithm::Default => {}
+            DiffAlgorithm::Myers => args.push("--diff-algorithm=myers".to_string()),
+            DiffAlgorithm::Patience => args.push("--diff-algorithm=patience".to_string()),
+            DiffAlgorithm::Histogram => args.push("--diff-algorithm=histogram".to_string()),
+        }
+
+        if let Some(threshold) = self.rename_threshold {
+            args.push(format!("-M{}%", threshold.min(100)));
+        }
+        if let Some(threshold) = self.copy_threshold {
+            args.push(format!("-C{}%", threshold.min(100)));
+        }
+
+        if self.color_moved {
+            args.push("--color=always".to_string());
+            args.push("--color-moved=zebra".to_string());
+        } else {
+            args.push("--no-color".to_string());
+        }
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algorithm_is_passed_only_when_chosen() {
+        assert_eq!(DiffOptions::default().args(), vec!["--no-color"]);
+        let options = DiffOptions { algorithm: DiffAlgorithm::Myers, ..Default::default() };
+        assert_eq!(options.args(), vec!["--diff-algorithm=myers", "--no-color"]);
+    }
+}
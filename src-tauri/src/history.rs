@@ -0,0 +1,103 @@
+use serde::Serialize;
+use crate::status::{ChangeState, StatusInfo};
+
+// `git log --format`で各項目を区切る文字列
+pub const LOG_FIELD_SEPARATOR: &str = "___";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Commit {
+    pub graph: String,
+    pub hash: String,
+    pub author: String,
+    pub message: String,
+    pub date: String,
+    pub branch: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ShowInfo {
+    pub hash: String,
+    pub author: String,
+    pub date: String,
+    pub message: String,
+}
+
+// `git log --graph --format=___%h___%an___%s___%ad___%d`の出力をパースする
+pub fn parse_log(output: &str) -> Vec<Commit> {
+    output
+        .lines()
+        .map(|line| {
+            let parts: Vec<&str> = line.split(LOG_FIELD_SEPARATOR).collect();
+            if parts.len() > 5 {
+                // コミットメッセージありの行
+                Commit {
+                    graph: parts[0].to_string(),
+                    hash: parts[1].to_string(),
+                    author: parts[2].to_string(),
+                    message: parts[3].to_string(),
+                    date: parts[4].to_string(),
+                    branch: parts[5].to_string(),
+                }
+            } else {
+                // コミットメッセージなしのブランチ表記のみの行
+                Commit {
+                    graph: parts[0].to_string(),
+                    hash: "".to_string(),
+                    author: "".to_string(),
+                    message: "".to_string(),
+                    date: "".to_string(),
+                    branch: "".to_string(),
+                }
+            }
+        })
+        .collect()
+}
+
+// `git show --pretty=format:%H%n%an%n%ad%n%B --no-patch`の出力をパースする
+pub fn parse_show(output: &str) -> ShowInfo {
+    let mut lines = output.lines();
+
+    // 最初の4行をパース
+    let hash = lines.next().unwrap_or("").to_string();
+    let author = lines.next().unwrap_or("").to_string();
+    let date = lines.next().unwrap_or("").to_string();
+    let message = lines.collect::<Vec<&str>>().join("\n");
+
+    ShowInfo {
+        hash,
+        author,
+        date,
+        message,
+    }
+}
+
+// `git show --pretty=format: --name-status`の出力をパースする
+// 各行は`<状態>\t<パス>`の形式で、リネーム/コピーの場合は`R100\t<元のパス>\t<パス>`となる
+pub fn parse_show_files(output: &str) -> Vec<StatusInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let state = fields.next()?;
+            let paths: Vec<&str> = fields.collect();
+            let (orig_filename, filename) = match paths.as_slice() {
+                [filename] => (None, filename.to_string()),
+                [orig_filename, filename] => (Some(orig_filename.to_string()), filename.to_string()),
+                _ => return None,
+            };
+
+            let change_state = match state.get(0..1).unwrap_or("") {
+                "M" => ChangeState::Change,
+                "D" => ChangeState::Delete,
+                "A" => ChangeState::Add,
+                "R" => ChangeState::Rename,
+                "C" => ChangeState::Copy,
+                "T" => ChangeState::TypeChange,
+                _ => ChangeState::Unknown,
+            };
+            let mut info = StatusInfo::with_state(filename, change_state);
+            info.orig_filename = orig_filename;
+            Some(info)
+        })
+        .collect()
+}
@@ -0,0 +1,329 @@
+// 対話的リベース
+// gitが開くエディタ (GIT_SEQUENCE_EDITOR / GIT_EDITOR) にzengit自体の補助モードを指定し、
+// アプリで編集した計画どおりにtodoとコミットメッセージを書き込む (端末のエディタは開かない)
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use crate::backend::{GitBackend, GitRequest};
+use crate::rebase::{rebase_state, run_rebase, RebaseState};
+
+// `zengit rebase-editor <sequence|message> <file>` として起動された場合に補助モードで動作する
+pub const REBASE_EDITOR_COMMAND: &str = "rebase-editor";
+const SEQUENCE_MODE: &str = "sequence";
+const MESSAGE_MODE: &str = "message";
+
+// 計画のファイルのパスを補助モードに渡す環境変数
+const PLAN_ENV: &str = "ZENGIT_REBASE_PLAN";
+const PLAN_FILE_NAME: &str = "zengit-rebase-plan.json";
+// リベース中に使うメッセージ (rebase-merge内に置き、リベースが終わるとgitが一緒に削除する)
+const MESSAGES_FILE_NAME: &str = "zengit-messages.json";
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TodoAction {
+    Pick = 0,
+    // メッセージを変更する
+    Reword = 1,
+    // 前のコミットとまとめ、メッセージも合わせる
+    Squash = 2,
+    // 前のコミットとまとめ、メッセージは前のコミットのものを使う
+    Fixup = 3,
+    Drop = 4,
+}
+
+impl TodoAction {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            TodoAction::Pick => "pick",
+            TodoAction::Reword => "reword",
+            TodoAction::Squash => "squash",
+            TodoAction::Fixup => "fixup",
+            TodoAction::Drop => "drop",
+        }
+    }
+
+    // todoの行の先頭の単語 (省略形を含む)
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "pick" | "p" => Some(TodoAction::Pick),
+            "reword" | "r" => Some(TodoAction::Reword),
+            "squash" | "s" => Some(TodoAction::Squash),
+            "fixup" | "f" => Some(TodoAction::Fixup),
+            "drop" | "d" => Some(TodoAction::Drop),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TodoEntry {
+    pub action: TodoAction,
+    pub hash: String,
+    pub short_hash: String,
+    pub subject: String,
+    // RewordとSquashで使う新しいメッセージ (Squashの場合はまとめた後のメッセージ)
+    // Noneの場合はgitが用意したものをそのまま使う
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+// 補助モードに渡す内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+struct RebasePlan {
+    todo: String,
+    // コミットのハッシュとメッセージ
+    messages: HashMap<String, String>,
+}
+
+// gitに指定するエディタ
+// programがない場合 (テストなど) は何も変更しないエディタ (`true`) を使う
+#[derive(Debug, Clone, Default)]
+pub struct RebaseEditor {
+    program: Option<PathBuf>,
+}
+
+impl RebaseEditor {
+    // programはzengitの実行ファイル
+    pub fn new(program: PathBuf) -> Self {
+        RebaseEditor { program: Some(program) }
+    }
+
+    // gitはエディタをshで実行するので、パスを引用符で囲む
+    fn command(&self, mode: &str) -> String {
+        match &self.program {
+            Some(program) => format!("{} {} {}", shell_quote(&program.to_string_lossy()), REBASE_EDITOR_COMMAND, mode),
+            None => "true".to_string(),
+        }
+    }
+
+    // リベースのgitにエディタを設定する
+    pub fn apply(&self, request: GitRequest) -> GitRequest {
+        request
+            .env("GIT_SEQUENCE_EDITOR", self.command(SEQUENCE_MODE))
+            .env("GIT_EDITOR", self.command(MESSAGE_MODE))
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+pub fn render_todo(entries: &[TodoEntry]) -> String {
+    entries.iter()
+        .map(|v| format!("{} {} {}\n", v.action.keyword(), v.hash, v.subject))
+        .collect()
+}
+
+// base (含まない) からHEADまでのコミットを、適用する順 (古い順) に取得する
+// (マージコミットは対話的リベースで一直線に並べ直されるため含めない)
+pub async fn plan_interactive_rebase(backend: &dyn GitBackend, repo_path: &Path, base: &str) -> Result<Vec<TodoEntry>, String> {
+    let request = GitRequest::new(["merge-base", "--is-ancestor"]).arg(base).arg("HEAD");
+    if backend.exec(repo_path, request).await.is_err() {
+        return Err(format!("'{}' is not an ancestor of the current branch.", base));
+    }
+
+    let request = GitRequest::new(["log", "--reverse", "--topo-order", "--no-merges", "--format=%H%x1f%h%x1f%s"])
+        .arg(format!("{}..HEAD", base))
+        .arg("--");
+    let output = backend.exec(repo_path, request).await?;
+    Ok(output.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\x1f');
+            Some(TodoEntry {
+                action: TodoAction::Pick,
+                hash: fields.next()?.to_string(),
+                short_hash: fields.next()?.to_string(),
+                subject: fields.next().unwrap_or_default().to_string(),
+                message: None,
+            })
+        })
+        .collect())
+}
+
+// 編集した計画が、現在のコミットを1回ずつ含んでいるかを確認する
+fn validate_plan(planned: &[TodoEntry], entries: &[TodoEntry]) -> Result<(), String> {
+    let mut expected: Vec<&str> = planned.iter().map(|v| v.hash.as_str()).collect();
+    let mut actual: Vec<&str> = entries.iter().map(|v| v.hash.as_str()).collect();
+    expected.sort_unstable();
+    actual.sort_unstable();
+    if expected != actual {
+        return Err("The commits have changed since the plan was made. Reload the plan and try again.".to_string());
+    }
+
+    let first = entries.iter().find(|v| v.action != TodoAction::Drop);
+    if let Some(first) = first.filter(|v| matches!(v.action, TodoAction::Squash | TodoAction::Fixup)) {
+        return Err(format!("{} can't be combined because there is no commit before it.", first.short_hash));
+    }
+    Ok(())
+}
+
+// 編集した計画どおりに対話的リベースを実行する
+// 衝突などで止まった場合はエラーにせず、リベース中の状態を返す
+pub async fn run_interactive_rebase(backend: &dyn GitBackend,
+                                    repo_path: &Path,
+                                    base: &str,
+                                    entries: &[TodoEntry],
+                                    editor: &RebaseEditor) -> Result<RebaseState, String> {
+    if rebase_state(backend, repo_path).await?.in_progress {
+        return Err("A rebase is already in progress.".to_string());
+    }
+    let planned = plan_interactive_rebase(backend, repo_path, base).await?;
+    validate_plan(&planned, entries)?;
+
+    let plan = RebasePlan {
+        todo: render_todo(entries),
+        messages: entries.iter()
+            .filter(|v| matches!(v.action, TodoAction::Reword | TodoAction::Squash))
+            .filter_map(|v| Some((v.hash.clone(), v.message.clone()?)))
+            .collect(),
+    };
+    let plan_path = repo_path.join(backend.git_path(repo_path, PLAN_FILE_NAME).await?);
+    let json = serde_json::to_string(&plan).map_err(|e| e.to_string())?;
+    fs::write(&plan_path, json).map_err(|e| e.to_string())?;
+
+    let request = editor.apply(GitRequest::new(["rebase", "-i"]).arg(base).arg("--"))
+        .env(PLAN_ENV, plan_path.to_string_lossy());
+    let result = run_rebase(backend, repo_path, request).await;
+    let _ = fs::remove_file(&plan_path);
+    result
+}
+
+// 補助モードとして起動された場合は処理して終了コードを返す (それ以外はNone)
+pub fn run_editor_helper<I: IntoIterator<Item=OsString>>(args: I) -> Option<i32> {
+    let args: Vec<OsString> = args.into_iter().skip(1).collect();
+    if args.first().map(|v| v.as_os_str()) != Some(REBASE_EDITOR_COMMAND.as_ref()) {
+        return None;
+    }
+
+    let (Some(mode), Some(file)) = (args.get(1).and_then(|v| v.to_str()), args.get(2)) else {
+        eprintln!("usage: zengit {} <{}|{}> <file>", REBASE_EDITOR_COMMAND, SEQUENCE_MODE, MESSAGE_MODE);
+        return Some(2);
+    };
+    let file = PathBuf::from(file);
+    let result = match mode {
+        SEQUENCE_MODE => write_todo(&file, std::env::var_os(PLAN_ENV).map(PathBuf::from)),
+        MESSAGE_MODE => write_message(&file),
+        _ => Err(format!("unknown mode '{}'", mode)),
+    };
+
+    match result {
+        Ok(_) => Some(0),
+        Err(e) => {
+            // 0以外で終了するとgitはリベースを中止する
+            eprintln!("zengit {}: {}", REBASE_EDITOR_COMMAND, e);
+            Some(1)
+        }
+    }
+}
+
+// GIT_SEQUENCE_EDITOR: todoを計画の内容に置き換え、メッセージをrebase-mergeに残す
+// (計画が渡されていない場合はgitが用意したtodoのまま進める)
+fn write_todo(todo_file: &Path, plan_path: Option<PathBuf>) -> Result<(), String> {
+    let Some(plan_path) = plan_path else {
+        return Ok(());
+    };
+    let json = fs::read_to_string(&plan_path).map_err(|e| e.to_string())?;
+    let plan: RebasePlan = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+
+    let rebase_dir = todo_file.parent().ok_or("The todo file has no parent directory.")?;
+    let messages = serde_json::to_string(&plan.messages).map_err(|e| e.to_string())?;
+    fs::write(rebase_dir.join(MESSAGES_FILE_NAME), messages).map_err(|e| e.to_string())?;
+    fs::write(todo_file, plan.todo).map_err(|e| e.to_string())
+}
+
+// GIT_EDITOR: 適用中のコミットに計画したメッセージがあれば書き込む (なければgitが用意したものを使う)
+fn write_message(message_file: &Path) -> Result<(), String> {
+    let git_dir = message_file.parent().ok_or("The message file has no parent directory.")?;
+    let rebase_dir = git_dir.join("rebase-merge");
+    let Ok(json) = fs::read_to_string(rebase_dir.join(MESSAGES_FILE_NAME)) else {
+        return Ok(());
+    };
+    let messages: HashMap<String, String> = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let done = fs::read_to_string(rebase_dir.join("done")).unwrap_or_default();
+
+    if let Some(message) = find_message(&done, &messages) {
+        fs::write(message_file, format!("{}\n", message.trim_end())).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 適用済みのtodo (done) の最後の行から、まとめているコミットをさかのぼってメッセージを探す
+// (squashの後にfixupが続く場合、エディタはfixupの行で開かれる)
+fn find_message<'a>(done: &str, messages: &'a HashMap<String, String>) -> Option<&'a String> {
+    for line in done.lines().rev() {
+        let mut words = line.split_whitespace();
+        let (Some(action), Some(hash)) = (words.next().and_then(TodoAction::from_keyword), words.next()) else {
+            continue;
+        };
+        if matches!(action, TodoAction::Reword | TodoAction::Squash) {
+            if let Some((_, message)) = messages.iter().find(|(k, _)| k.starts_with(hash)) {
+                return Some(message);
+            }
+        }
+        if !matches!(action, TodoAction::Squash | TodoAction::Fixup) {
+            break;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(action: TodoAction, hash: &str) -> TodoEntry {
+        TodoEntry {
+            action,
+            hash: hash.to_string(),
+            short_hash: hash[..3].to_string(),
+            subject: format!("subject {}", hash),
+            message: None,
+        }
+    }
+
+    #[test]
+    fn todo_is_rendered_in_order() {
+        let entries = vec![entry(TodoAction::Pick, "bbbb"), entry(TodoAction::Fixup, "aaaa"), entry(TodoAction::Drop, "cccc")];
+        assert_eq!(render_todo(&entries), "pick bbbb subject bbbb\nfixup aaaa subject aaaa\ndrop cccc subject cccc\n");
+    }
+
+    #[test]
+    fn plan_must_contain_each_commit_once() {
+        let planned = vec![entry(TodoAction::Pick, "aaaa"), entry(TodoAction::Pick, "bbbb")];
+        assert!(validate_plan(&planned, &[entry(TodoAction::Pick, "bbbb"), entry(TodoAction::Reword, "aaaa")]).is_ok());
+        assert!(validate_plan(&planned, &[entry(TodoAction::Pick, "aaaa")]).is_err());
+        assert!(validate_plan(&planned, &[entry(TodoAction::Pick, "aaaa"), entry(TodoAction::Pick, "aaaa")]).is_err());
+
+        // 最初に残すコミットはまとめられない
+        let err = validate_plan(&planned, &[entry(TodoAction::Drop, "aaaa"), entry(TodoAction::Squash, "bbbb")]).unwrap_err();
+        assert!(err.starts_with("bbb"), "{}", err);
+    }
+
+    #[test]
+    fn message_is_found_for_current_step() {
+        let messages = HashMap::from([
+            ("aaaa1111".to_string(), "reworded".to_string()),
+            ("cccc3333".to_string(), "squashed".to_string()),
+        ]);
+        assert_eq!(find_message("pick bbbb subject\nreword aaaa subject\n", &messages).map(String::as_str), Some("reworded"));
+        assert_eq!(find_message("pick bbbb subject\nsquash cccc subject\nf dddd subject\n", &messages).map(String::as_str), Some("squashed"));
+        assert_eq!(find_message("reword aaaa subject\npick bbbb subject\nsquash eeee subject\n", &messages), None);
+    }
+
+    #[test]
+    fn editor_command_quotes_program() {
+        let editor = RebaseEditor::new(PathBuf::from("/Apps/Zen Git's/zengit"));
+        assert_eq!(editor.command(SEQUENCE_MODE), "'/Apps/Zen Git'\\''s/zengit' rebase-editor sequence");
+        assert_eq!(RebaseEditor::default().command(MESSAGE_MODE), "true");
+    }
+
+    #[test]
+    fn helper_ignores_other_arguments() {
+        assert_eq!(run_editor_helper(["zengit", "status"].map(OsString::from)), None);
+        assert_eq!(run_editor_helper(["zengit", REBASE_EDITOR_COMMAND].map(OsString::from)), Some(2));
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use tokio_util::sync::CancellationToken;
+
+// 終了したジョブはこの件数まで保持する
+const MAX_JOB_HISTORY: usize = 100;
+
+pub const JOB_STARTED_EVENT: &str = "job-started";
+pub const JOB_FINISHED_EVENT: &str = "job-finished";
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum JobStatus {
+    Running = 0,
+    Succeeded = 1,
+    Failed = 2,
+    Cancelled = 3,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub git_command_label: &'static str,
+    pub window_label: String,
+    pub status: JobStatus,
+    // UNIX時間 (ミリ秒)
+    pub started_at: u64,
+    // 実行時間 (実行中の場合は一覧を取得した時点までの時間、開始の通知ではNone)
+    pub duration_ms: Option<u64>,
+    // 実行したgitの引数 (1つのジョブで複数回実行する場合がある)
+    pub git_args: Vec<Vec<String>>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobList {
+    pub active: Vec<JobInfo>,
+    // 新しい順
+    pub recent: Vec<JobInfo>,
+}
+
+struct Job {
+    info: JobInfo,
+    started: Instant,
+    token: CancellationToken,
+}
+
+#[derive(Default)]
+struct Jobs {
+    next_id: JobId,
+    active: Vec<Job>,
+    history: VecDeque<JobInfo>,
+}
+
+// ジョブの開始・終了の通知先 (イベント名, ジョブ)
+type JobListener = Box<dyn Fn(&'static str, &JobInfo) + Send + Sync>;
+
+// 実行中・終了したgitの操作を管理する
+// 同じウィンドウで同じコマンドを重複して実行しないように、is_runningで確認してから開始する
+#[derive(Default)]
+pub struct JobManager {
+    jobs: Mutex<Jobs>,
+    listener: OnceCell<JobListener>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|v| v.as_millis() as u64).unwrap_or(0)
+}
+
+impl JobManager {
+    pub fn set_listener<F: Fn(&'static str, &JobInfo) + Send + Sync + 'static>(&self, listener: F) {
+        let _ = self.listener.set(Box::new(listener));
+    }
+
+    fn notify(&self, event: &'static str, info: &JobInfo) {
+        if let Some(listener) = self.listener.get() {
+            listener(event, info);
+        }
+    }
+
+    pub fn is_running(&self, git_command_label: &str, window_label: &str) -> bool {
+        self.jobs.lock().unwrap().active.iter()
+            .any(|v| v.info.git_command_label == git_command_label && v.info.window_label == window_label)
+    }
+
+    // ジョブを開始して、idとキャンセル用のトークンを返す
+    pub fn start(&self, git_command_label: &'static str, window_label: &str) -> (JobId, CancellationToken) {
+        let (info, token) = {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.next_id += 1;
+            let job = Job {
+                info: JobInfo {
+                    id: jobs.next_id,
+                    git_command_label,
+                    window_label: window_label.to_string(),
+                    status: JobStatus::Running,
+                    started_at: now_millis(),
+                    duration_ms: None,
+                    git_args: vec![],
+                    error: None,
+                },
+                started: Instant::now(),
+                token: CancellationToken::new(),
+            };
+            let res = (job.info.clone(), job.token.clone());
+            jobs.active.push(job);
+            res
+        };
+
+        self.notify(JOB_STARTED_EVENT, &info);
+        (info.id, token)
+    }
+
+    pub fn record_git_args(&self, id: JobId, args: Vec<String>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.active.iter_mut().find(|v| v.info.id == id) {
+            job.info.git_args.push(args);
+        }
+    }
+
+    // ジョブを終了して履歴に移す (既に終了している場合は何もしない)
+    pub fn finish(&self, id: JobId, status: JobStatus, error: Option<String>) {
+        let info = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let Some(index) = jobs.active.iter().position(|v| v.info.id == id) else {
+                return;
+            };
+            let job = jobs.active.remove(index);
+            let mut info = job.info;
+            info.status = status;
+            info.duration_ms = Some(job.started.elapsed().as_millis() as u64);
+            info.error = error;
+
+            jobs.history.push_front(info.clone());
+            jobs.history.truncate(MAX_JOB_HISTORY);
+            info
+        };
+
+        self.notify(JOB_FINISHED_EVENT, &info);
+    }
+
+    // 実行中のジョブをキャンセルする (実行中のgitはトークンを受け取った側で中断される)
+    pub fn cancel(&self, id: JobId) -> Result<(), String> {
+        let token = self.jobs.lock().unwrap().active.iter()
+            .find(|v| v.info.id == id)
+            .map(|v| v.token.clone())
+            .ok_or(format!("Job {} is not running.", id))?;
+
+        token.cancel();
+        self.finish(id, JobStatus::Cancelled, None);
+        Ok(())
+    }
+
+    // ウィンドウで実行中の指定したコマンドを全てキャンセルする
+    pub fn cancel_command(&self, git_command_label: &str, window_label: &str) {
+        let ids: Vec<JobId> = self.jobs.lock().unwrap().active.iter()
+            .filter(|v| v.info.git_command_label == git_command_label && v.info.window_label == window_label)
+            .map(|v| v.info.id)
+            .collect();
+
+        for id in ids {
+            let _ = self.cancel(id);
+        }
+    }
+
+    pub fn list(&self) -> JobList {
+        let jobs = self.jobs.lock().unwrap();
+        let now = Instant::now();
+        JobList {
+            active: jobs.active.iter()
+                .map(|v| {
+                    // 実行中のものは経過時間を入れる
+                    let mut info = v.info.clone();
+                    info.duration_ms = Some(now.duration_since(v.started).as_millis() as u64);
+                    info
+                })
+                .collect(),
+            recent: jobs.history.iter().cloned().collect(),
+        }
+    }
+}
@@ -0,0 +1,547 @@
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use crate::backend::{GitBackend, GitRequest};
+
+pub const JOURNAL_FILE_NAME: &str = "operation_journal.json";
+// 破棄した変更の退避先 (エントリのidごとのフォルダ)
+const JOURNAL_FILES_DIR_NAME: &str = "journal_files";
+// リポジトリごとにこの件数まで保持する
+const MAX_ENTRIES_PER_REPOSITORY: usize = 20;
+
+// 記録する操作の種類
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum OperationKind {
+    Commit = 0,
+    Amend = 1,
+    Merge = 2,
+    Checkout = 3,
+    BranchCreate = 4,
+    BranchDelete = 5,
+    Discard = 6,
+    Pull = 7,
+    // 元に戻せないが、それより前の操作を戻さないようにするために記録する
+    Push = 8,
+    CherryPick = 9,
+    Revert = 10,
+    ResetSoft = 11,
+    ResetMixed = 12,
+    // 破棄したファイルを退避し、元に戻すときにブランチと一緒に戻す
+    ResetHard = 13,
+    // 止まらずに終わったものだけを記録する (rebase::run_journaled)
+    Rebase = 14,
+}
+
+impl OperationKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OperationKind::Commit => "commit",
+            OperationKind::Amend => "amend",
+            OperationKind::Merge => "merge",
+            OperationKind::Checkout => "checkout",
+            OperationKind::BranchCreate => "branch creation",
+            OperationKind::BranchDelete => "branch deletion",
+            OperationKind::Discard => "discard",
+            OperationKind::Pull => "pull",
+            OperationKind::Push => "push",
+            OperationKind::CherryPick => "cherry-pick",
+            OperationKind::Revert => "revert",
+            OperationKind::ResetSoft => "soft reset",
+            OperationKind::ResetMixed => "mixed reset",
+            OperationKind::ResetHard => "hard reset",
+            OperationKind::Rebase => "rebase",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RefTarget {
+    // "refs/heads/main"
+    pub name: String,
+    pub hash: String,
+}
+
+// 操作の前後のリポジトリの状態
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RepositorySnapshot {
+    // HEADが指すブランチ (detached HEADの場合はNone)
+    pub head_ref: Option<String>,
+    // HEADのコミット (初回コミット前はNone)
+    pub head: Option<String>,
+    // ローカルブランチの指すコミット
+    pub refs: Vec<RefTarget>,
+    // HEADのreflogの最新の記録 ("HEAD@{日時} ハッシュ 内容")
+    // (同じ状態に戻ってきた場合も、間に別の操作があったことが分かるようにする)
+    pub head_reflog_top: Option<String>,
+}
+
+impl RepositorySnapshot {
+    fn ref_hash(&self, name: &str) -> Option<&str> {
+        self.refs.iter().find(|v| v.name == name).map(|v| v.hash.as_str())
+    }
+}
+
+// 破棄したファイル
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiscardedFile {
+    pub path: String,
+    // 退避したファイル名 (破棄する前にファイルがなかった場合はNone)
+    pub backup: Option<String>,
+    // 破棄した後の内容のハッシュ (`git hash-object`、ファイルがない場合はNone)
+    pub after_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub repo_path: String,
+    pub kind: OperationKind,
+    // UNIX時間 (ミリ秒)
+    pub created_at: u64,
+    pub before: RepositorySnapshot,
+    pub after: RepositorySnapshot,
+    #[serde(default)]
+    pub discarded_files: Vec<DiscardedFile>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalEntries {
+    #[serde(default)]
+    entries: Vec<JournalEntry>,
+}
+
+fn now_nanos() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|v| v.as_nanos()).unwrap_or(0)
+}
+
+// リポジトリを変更する操作の記録 (元に戻すために使う)
+pub struct OperationJournal {
+    dir: PathBuf,
+    // 複数のウィンドウから同時に読み書きされないようにする
+    lock: Mutex<()>,
+}
+
+impl OperationJournal {
+    pub fn new(dir: PathBuf) -> Self {
+        OperationJournal { dir, lock: Mutex::new(()) }
+    }
+
+    fn load(&self) -> JournalEntries {
+        fs::read_to_string(self.dir.join(JOURNAL_FILE_NAME))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, entries: &JournalEntries) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+        fs::write(self.dir.join(JOURNAL_FILE_NAME), json).map_err(|e| e.to_string())
+    }
+
+    fn files_dir(&self, id: u64) -> PathBuf {
+        self.dir.join(JOURNAL_FILES_DIR_NAME).join(id.to_string())
+    }
+
+    pub fn record(&self, entry: JournalEntry) -> Result<(), String> {
+        let _lock = self.lock.lock().unwrap();
+        let mut entries = self.load();
+        entries.entries.push(entry.clone());
+
+        // 古いものを取り除く
+        let count = entries.entries.iter().filter(|v| v.repo_path == entry.repo_path).count();
+        let mut excess = count.saturating_sub(MAX_ENTRIES_PER_REPOSITORY);
+        let mut removed = vec![];
+        entries.entries.retain(|v| {
+            if excess > 0 && v.repo_path == entry.repo_path {
+                excess -= 1;
+                removed.push(v.id);
+                return false;
+            }
+            true
+        });
+        for id in removed {
+            let _ = fs::remove_dir_all(self.files_dir(id));
+        }
+
+        self.save(&entries)
+    }
+
+    // リポジトリで最後に記録された操作
+    pub fn last(&self, repo_path: &Path) -> Option<JournalEntry> {
+        let _lock = self.lock.lock().unwrap();
+        let repo_path = repo_path.to_string_lossy();
+        self.load().entries.into_iter().rev().find(|v| v.repo_path == repo_path)
+    }
+
+    pub fn remove(&self, id: u64) -> Result<(), String> {
+        let _lock = self.lock.lock().unwrap();
+        let mut entries = self.load();
+        entries.entries.retain(|v| v.id != id);
+        let _ = fs::remove_dir_all(self.files_dir(id));
+        self.save(&entries)
+    }
+}
+
+// 実行中の操作 (操作の前に作成し、成功したらfinishで記録する)
+pub struct PendingOperation {
+    id: u64,
+    repo_path: PathBuf,
+    kind: OperationKind,
+    before: RepositorySnapshot,
+    discarded_files: Vec<DiscardedFile>,
+}
+
+impl PendingOperation {
+    pub async fn begin(backend: &dyn GitBackend, repo_path: &Path, kind: OperationKind) -> Result<Self, String> {
+        Ok(PendingOperation {
+            id: now_nanos() as u64,
+            repo_path: repo_path.to_path_buf(),
+            kind,
+            before: snapshot(backend, repo_path).await?,
+            discarded_files: vec![],
+        })
+    }
+
+    // 破棄するファイルを退避する
+    pub fn backup_files(&mut self, journal: &OperationJournal, files: &[String]) -> Result<(), String> {
+        let dir = journal.files_dir(self.id);
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        for file in files {
+            let source = self.repo_path.join(file);
+            let backup = if source.is_file() {
+                let name = self.discarded_files.len().to_string();
+                fs::copy(&source, dir.join(&name)).map_err(|e| e.to_string())?;
+                Some(name)
+            } else {
+                None
+            };
+            self.discarded_files.push(DiscardedFile { path: file.clone(), backup, after_hash: None });
+        }
+        Ok(())
+    }
+
+    // 操作が失敗した場合は記録せず、退避したファイルを削除する
+    pub fn abandon(self, journal: &OperationJournal) {
+        let _ = fs::remove_dir_all(journal.files_dir(self.id));
+    }
+
+    pub async fn finish(mut self, backend: &dyn GitBackend, journal: &OperationJournal) -> Result<(), String> {
+        for file in &mut self.discarded_files {
+            file.after_hash = hash_file(backend, &self.repo_path, &file.path).await?;
+        }
+
+        // 何も変わらなかった操作 (既に最新だったマージなど) は戻すものがないので記録しない
+        // (pushはリモートを変更するので、戻せないことを伝えるために記録する)
+        let after = snapshot(backend, &self.repo_path).await?;
+        if self.kind != OperationKind::Push && self.discarded_files.is_empty() && after == self.before {
+            self.abandon(journal);
+            return Ok(());
+        }
+
+        journal.record(JournalEntry {
+            id: self.id,
+            repo_path: self.repo_path.to_string_lossy().to_string(),
+            kind: self.kind,
+            created_at: (now_nanos() / 1_000_000) as u64,
+            before: self.before,
+            after,
+            discarded_files: self.discarded_files,
+        })
+    }
+}
+
+// 操作の前後のリポジトリの状態を記録しながら実行する
+// discarded_filesは破棄するファイル (元に戻せるように退避しておく)
+// 状態を取得できない場合 (リポジトリでない場合など) は記録せずに実行する
+pub async fn run_journaled<T, F>(backend: &dyn GitBackend,
+                                 journal: &OperationJournal,
+                                 repo_path: &Path,
+                                 kind: OperationKind,
+                                 discarded_files: &[String],
+                                 future: F) -> Result<T, String> where F: Future<Output=Result<T, String>> {
+    run_journaled_if(backend, journal, repo_path, kind, discarded_files, future, |_| true).await
+}
+
+// run_journaledと同じだが、成功してもcompletedがfalseを返した場合 (途中で止まった場合など) は記録しない
+pub async fn run_journaled_if<T, F, P>(backend: &dyn GitBackend,
+                                       journal: &OperationJournal,
+                                       repo_path: &Path,
+                                       kind: OperationKind,
+                                       discarded_files: &[String],
+                                       future: F,
+                                       completed: P) -> Result<T, String>
+    where F: Future<Output=Result<T, String>>, P: FnOnce(&T) -> bool {
+    let pending = match PendingOperation::begin(backend, repo_path, kind).await {
+        Ok(mut pending) => {
+            // 退避できない場合は破棄しない
+            pending.backup_files(journal, discarded_files)?;
+            Some(pending)
+        }
+        Err(e) => {
+            debug!("Failed to take a snapshot before {}: {}", kind.label(), e);
+            None
+        }
+    };
+
+    let result = future.await;
+
+    if let Some(pending) = pending {
+        match &result {
+            Ok(value) if completed(value) => {
+                if let Err(e) = pending.finish(backend, journal).await {
+                    debug!("Failed to record {}: {}", kind.label(), e);
+                }
+            }
+            _ => pending.abandon(journal),
+        }
+    }
+    result
+}
+
+// 失敗しても状態の確認だけに使うgit (該当するものがない場合に終了コードが0以外になる)
+async fn try_exec(backend: &dyn GitBackend, repo_path: &Path, request: GitRequest) -> Option<String> {
+    backend.exec(repo_path, request).await.ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+pub async fn snapshot(backend: &dyn GitBackend, repo_path: &Path) -> Result<RepositorySnapshot, String> {
+    let head_ref = try_exec(backend, repo_path, GitRequest::new(["symbolic-ref", "-q", "HEAD"])).await;
+    let head = try_exec(backend, repo_path, GitRequest::new(["rev-parse", "-q", "--verify", "HEAD"])).await;
+
+    let output = backend.exec(repo_path, GitRequest::new(["for-each-ref", "--format=%(refname) %(objectname)", "refs/heads"])).await?;
+    let refs = output.lines()
+        .filter_map(|line| line.rsplit_once(' '))
+        .map(|(name, hash)| RefTarget { name: name.to_string(), hash: hash.to_string() })
+        .collect();
+
+    // reflog全体は長くなるので最新の1件だけを見る
+    let head_reflog_top = match head {
+        Some(_) => try_exec(backend, repo_path, GitRequest::new(["reflog", "show", "-1", "--date=unix", "--format=%gd %H %gs", "HEAD"])).await,
+        None => None,
+    };
+
+    Ok(RepositorySnapshot { head_ref, head, refs, head_reflog_top })
+}
+
+// snapshotで実行するgitを、HEADが指すコミットがheadのmainブランチとして登録する
+#[cfg(test)]
+pub(crate) fn expect_snapshot(backend: crate::backend::scripted::ScriptedBackend, head: &str) -> crate::backend::scripted::ScriptedBackend {
+    backend
+        .expect(&["symbolic-ref", "-q", "HEAD"], Ok("refs/heads/main\n"))
+        .expect(&["rev-parse", "-q", "--verify", "HEAD"], Ok(&format!("{}\n", head)))
+        .expect(&["for-each-ref", "--format=%(refname) %(objectname)", "refs/heads"], Ok(&format!("refs/heads/main {}\n", head)))
+        .expect(&["reflog", "show", "-1", "--date=unix", "--format=%gd %H %gs", "HEAD"], Ok(&format!("HEAD@{{1700000000}} {} commit: test\n", head)))
+}
+
+// ファイルの内容のハッシュ (改行コードの変換などをせずに比較する)
+async fn hash_file(backend: &dyn GitBackend, repo_path: &Path, file: &str) -> Result<Option<String>, String> {
+    if !repo_path.join(file).is_file() {
+        return Ok(None);
+    }
+    let output = backend.exec(repo_path, GitRequest::new(["hash-object", "--no-filters", "--"]).arg(file)).await?;
+    Ok(Some(output.trim().to_string()))
+}
+
+fn short_hash(hash: &str) -> &str {
+    hash.get(0..7).unwrap_or(hash)
+}
+
+// 記録されている最後の操作を元に戻し、戻した操作を返す
+pub async fn undo_last_operation(backend: &dyn GitBackend, journal: &OperationJournal, repo_path: &Path) -> Result<String, String> {
+    let entry = journal.last(repo_path).ok_or("There is no operation to undo.")?;
+    let label = entry.kind.label();
+
+    if entry.kind == OperationKind::Push {
+        return Err("The last operation was a push. It can't be undone because the commits are already on the remote.".to_string());
+    }
+
+    // 記録した後に別の操作 (アプリの外での操作を含む) があった場合は戻さない
+    let current = snapshot(backend, repo_path).await?;
+    if current != entry.after {
+        return Err(format!("The repository has changed since the last {}, so it can't be undone safely.", label));
+    }
+
+    match entry.kind {
+        OperationKind::Discard => {
+            check_discarded_files(backend, repo_path, &entry).await?;
+            restore_discarded_files(journal, repo_path, &entry)?;
+        }
+        OperationKind::ResetHard => {
+            // ブランチを戻すと作業ツリーが変わるので、先に確認しておく
+            check_discarded_files(backend, repo_path, &entry).await?;
+            restore_refs(backend, repo_path, &entry).await?;
+            restore_discarded_files(journal, repo_path, &entry)?;
+        }
+        _ => restore_refs(backend, repo_path, &entry).await?,
+    }
+
+    journal.remove(entry.id)?;
+    Ok(format!("Undid the last {}.", label))
+}
+
+async fn restore_refs(backend: &dyn GitBackend, repo_path: &Path, entry: &JournalEntry) -> Result<(), String> {
+    let (before, after) = (&entry.before, &entry.after);
+
+    // 同じブランチのままコミットが変わる操作 (commit, merge, pullなど)
+    let moves_head = before.head_ref.is_some() && before.head_ref == after.head_ref && before.head != after.head;
+
+    // ブランチが既にpushされている場合は戻さない
+    // (pullはリモートにあるものを取り込んだだけ、リセットは戻してもpushしたコミットがなくならないので除く)
+    let is_reset = matches!(entry.kind, OperationKind::ResetSoft | OperationKind::ResetMixed | OperationKind::ResetHard);
+    if moves_head && entry.kind != OperationKind::Pull && !is_reset {
+        if let (Some(head_ref), Some(head)) = (&after.head_ref, &after.head) {
+            let request = GitRequest::new(["for-each-ref", "--format=%(upstream)"]).arg(head_ref);
+            if let Some(upstream) = try_exec(backend, repo_path, request).await {
+                let request = GitRequest::new(["merge-base", "--is-ancestor"]).arg(head).arg(&upstream);
+                if backend.exec(repo_path, request).await.is_ok() {
+                    return Err(format!("The last {} can't be undone because {} has already been pushed to {}.",
+                                       entry.kind.label(), short_hash(head), upstream.trim_start_matches("refs/remotes/")));
+                }
+            }
+        }
+    }
+
+    if before.head_ref != after.head_ref || (before.head_ref.is_none() && before.head != after.head) {
+        // 元のブランチ・コミットに戻る (作成したブランチを削除できるように先に行う)
+        let request = match (&before.head_ref, &before.head) {
+            (Some(head_ref), _) => GitRequest::new(["checkout"]).arg(head_ref.trim_start_matches("refs/heads/")).arg("--"),
+            (None, Some(head)) => GitRequest::new(["checkout", "--detach"]).arg(head).arg("--"),
+            (None, None) => return Err("The previous HEAD is unknown.".to_string()),
+        };
+        backend.exec(repo_path, request).await?;
+    } else if moves_head {
+        let request = match (&before.head, entry.kind) {
+            // 初回コミットを戻す場合はブランチを削除する (indexはそのまま残る)
+            (None, _) => GitRequest::new(["update-ref", "-d", "HEAD"]),
+            // コミットした内容はstagingされた状態に戻す
+            (Some(head), OperationKind::Commit | OperationKind::Amend) => GitRequest::new(["reset", "--soft"]).arg(head),
+            // リセットは同じ方法で元のコミットに戻す (hardで破棄したファイルは後で戻す)
+            (Some(head), OperationKind::ResetSoft) => GitRequest::new(["reset", "--soft"]).arg(head),
+            (Some(head), OperationKind::ResetMixed) => GitRequest::new(["reset", "-q", "--mixed"]).arg(head),
+            // 作業ツリーの変更は残し、取り込んだ変更だけを戻す (ぶつかる場合はgitが失敗する)
+            (Some(head), _) => GitRequest::new(["reset", "--keep"]).arg(head),
+        };
+        backend.exec(repo_path, request).await?;
+    }
+
+    // HEAD以外のブランチ (作成・削除したもの) を元に戻す
+    let current_ref = after.head_ref.as_deref().filter(|_| moves_head);
+    let names = before.refs.iter().chain(&after.refs)
+        .map(|v| v.name.as_str())
+        .filter(|name| Some(*name) != current_ref);
+    let mut restored: Vec<&str> = vec![];
+    for name in names {
+        if restored.contains(&name) {
+            continue;
+        }
+        restored.push(name);
+
+        // update-refに変更前の値を渡し、他で変更されていた場合は失敗させる
+        let request = match (before.ref_hash(name), after.ref_hash(name)) {
+            (Some(old), Some(new)) if old != new => GitRequest::new(["update-ref", name, old, new]),
+            (Some(old), None) => GitRequest::new(["update-ref", name, old, ""]),
+            (None, Some(new)) => GitRequest::new(["update-ref", "-d", name, new]),
+            _ => continue,
+        };
+        backend.exec(repo_path, request).await?;
+    }
+    Ok(())
+}
+
+// 破棄した後に変更されたファイルがあれば、その変更を失わないように何もしない
+async fn check_discarded_files(backend: &dyn GitBackend, repo_path: &Path, entry: &JournalEntry) -> Result<(), String> {
+    for file in &entry.discarded_files {
+        if hash_file(backend, repo_path, &file.path).await? != file.after_hash {
+            return Err(format!("'{}' has been changed since the changes were discarded, so it can't be restored without losing those changes.", file.path));
+        }
+    }
+    Ok(())
+}
+
+fn restore_discarded_files(journal: &OperationJournal, repo_path: &Path, entry: &JournalEntry) -> Result<(), String> {
+    let dir = journal.files_dir(entry.id);
+    for file in &entry.discarded_files {
+        let path = repo_path.join(&file.path);
+        match &file.backup {
+            Some(backup) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::copy(dir.join(backup), &path).map_err(|e| e.to_string())?;
+            }
+            None => {
+                if path.exists() {
+                    fs::remove_file(&path).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use tempfile::TempDir;
+    use super::*;
+    use crate::backend::scripted::ScriptedBackend;
+
+    async fn commit(backend: &ScriptedBackend, repo_path: &Path) -> Result<String, String> {
+        backend.exec(repo_path, GitRequest::new(["commit", "-q", "-m", "message"])).await
+    }
+
+    #[tokio::test]
+    async fn run_journaled_records_state_before_and_after() {
+        let dir = TempDir::new().unwrap();
+        let journal = OperationJournal::new(dir.path().to_path_buf());
+        let repo_path = env::temp_dir();
+        let backend = expect_snapshot(ScriptedBackend::new(), "aaa")
+            .expect(&["commit", "-q", "-m", "message"], Ok(""));
+        let backend = expect_snapshot(backend, "bbb");
+
+        run_journaled(&backend, &journal, &repo_path, OperationKind::Commit, &[], commit(&backend, &repo_path)).await.unwrap();
+        backend.assert_finished();
+
+        let entry = journal.last(&repo_path).unwrap();
+        assert_eq!(entry.kind, OperationKind::Commit);
+        assert_eq!((entry.before.head.as_deref(), entry.after.head.as_deref()), (Some("aaa"), Some("bbb")));
+    }
+
+    #[tokio::test]
+    async fn failed_operation_is_not_recorded() {
+        let dir = TempDir::new().unwrap();
+        let journal = OperationJournal::new(dir.path().to_path_buf());
+        let repo_path = env::temp_dir();
+        let backend = expect_snapshot(ScriptedBackend::new(), "aaa")
+            .expect(&["commit", "-q", "-m", "message"], Err("nothing to commit"));
+
+        let res = run_journaled(&backend, &journal, &repo_path, OperationKind::Commit, &[], commit(&backend, &repo_path)).await;
+        assert_eq!(res.unwrap_err(), "nothing to commit");
+        backend.assert_finished();
+        assert!(journal.last(&repo_path).is_none());
+    }
+
+    #[tokio::test]
+    async fn operation_runs_without_record_when_snapshot_fails() {
+        let dir = TempDir::new().unwrap();
+        let journal = OperationJournal::new(dir.path().to_path_buf());
+        let repo_path = env::temp_dir();
+        let backend = ScriptedBackend::new()
+            .expect(&["symbolic-ref", "-q", "HEAD"], Err(""))
+            .expect(&["rev-parse", "-q", "--verify", "HEAD"], Err(""))
+            .expect(&["for-each-ref", "--format=%(refname) %(objectname)", "refs/heads"], Err("not a git repository"))
+            .expect(&["commit", "-q", "-m", "message"], Ok(""));
+
+        run_journaled(&backend, &journal, &repo_path, OperationKind::Commit, &[], commit(&backend, &repo_path)).await.unwrap();
+        backend.assert_finished();
+        assert!(journal.last(&repo_path).is_none());
+    }
+}
@@ -0,0 +1,24 @@
+// Tauriに依存しないgitの操作 (アプリ本体とテストから使う)
+pub mod audit;
+pub mod backend;
+pub mod branch;
+pub mod conflict;
+pub mod diff;
+pub mod diff_options;
+pub mod history;
+pub mod interactive_rebase;
+pub mod journal;
+pub mod merge;
+pub mod patch;
+pub mod platform;
+pub mod progress;
+pub mod rebase;
+pub mod recent;
+pub mod repo_lock;
+pub mod reset;
+pub mod sequencer;
+pub mod settings;
+pub mod stash;
+pub mod status;
+pub mod temp_index;
+pub mod word_diff;
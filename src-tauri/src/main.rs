@@ -2,171 +2,136 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 
-mod platform;
+mod cli;
+mod jobs;
+mod repository;
 
-use std::borrow::Cow;
 use std::env;
-use tauri::{AppHandle, command, Manager, WindowUrl};
+use tauri::{AppHandle, command, Manager, WindowEvent, WindowUrl};
 use std::process::{Output, Stdio};
-use std::str::FromStr;
 use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
-use tauri::regex::Regex;
-use crate::platform::CommandCreationFlags;
-use tauri::api::path::{app_config_dir, home_dir};
-use std::fs::{self, create_dir_all, File};
-use std::io::{self, Write};
+use zengit_lib::audit::{AuditLog, AuditQuery, AuditRecord, AUDIT_LOG_FILE_NAME};
+use zengit_lib::backend::{self, diff_file, CliBackend, GitBackend, GitRequest, LogQuery, ObservedBackend};
+use zengit_lib::branch::BranchState;
+use zengit_lib::conflict::{self, ConflictSide};
+use zengit_lib::diff_options::DiffOptions;
+use zengit_lib::interactive_rebase::{self, RebaseEditor, TodoEntry};
+use zengit_lib::journal::{self, OperationJournal, OperationKind};
+use zengit_lib::merge::{self, MergeOptions};
+use zengit_lib::patch::{HunkSelection, PatchDirection};
+use zengit_lib::progress::{clone_directory_name, GitProgress};
+use zengit_lib::rebase;
+use zengit_lib::sequencer::{self, PickOptions, SequencerCommand};
+use zengit_lib::stash::{self, StashOptions};
+use zengit_lib::repo_lock::{LockMode, RepositoryLocks};
+use zengit_lib::reset::{self, ResetMode};
+use crate::jobs::{JobId, JobList, JobManager, JobStatus};
+use zengit_lib::recent::{forget_repository, last_opened_repository, list_recent_repositories, pin_repository, record_opened_repository, rename_repository, RecentRepository};
+use crate::repository::{get_repository, remove_repository, repository_path, set_repository};
+use zengit_lib::settings::Settings;
+use zengit_lib::status::{ChangeState, StatusInfo};
+use tauri::api::path::app_config_dir;
+use std::fs::create_dir_all;
+use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 use log::{debug, info};
 use tokio::process::Command;
 use tokio_util::sync::CancellationToken;
 use futures::future::{err, FutureExt, ok};
 use log::Level::Error;
-use tokio::sync::Mutex;
 use once_cell::sync::Lazy;
 use tauri::http::header::CONTENT_SECURITY_POLICY_REPORT_ONLY;
 use tauri::regex::bytes::RegexSet;
 use window_shadows::set_shadow;
 
-static GLOBAL_CANCELLATION_TOKENS: Lazy<Arc<Mutex<Vec<TaskHandle>>>> = Lazy::new(|| {
-    Arc::new(Mutex::new(vec![]))
+// 実行したgitの記録 (設定フォルダのaudit.log)
+static AUDIT_LOG: Lazy<Arc<AuditLog>> = Lazy::new(|| Arc::new(AuditLog::new(get_app_config_dir().join(AUDIT_LOG_FILE_NAME))));
+
+// gitの操作の実装 (GitBackendを実装したものに差し替えられる)
+static GIT_BACKEND: Lazy<Arc<dyn GitBackend>> = Lazy::new(|| Arc::new(CliBackend::with_audit_log(AUDIT_LOG.clone())));
+
+// リポジトリを変更した操作の記録 (undo_last_operationで元に戻す)
+static JOURNAL: Lazy<OperationJournal> = Lazy::new(|| OperationJournal::new(get_app_config_dir()));
+
+// 対話的リベースでgitが開くエディタ (zengit自体の補助モード)
+static REBASE_EDITOR: Lazy<RebaseEditor> = Lazy::new(|| match env::current_exe() {
+    Ok(program) => RebaseEditor::new(program),
+    Err(_) => RebaseEditor::default(),
 });
 
-static CURRENT_PATH: Lazy<std::sync::Mutex<String>> = Lazy::new(|| std::sync::Mutex::new(String::new()));
+// 実行中・終了したgitの操作
+static JOBS: Lazy<JobManager> = Lazy::new(JobManager::default);
+
+// リポジトリごとの読み書きのロック (index.lockの競合を防ぐ)
+static REPOSITORY_LOCKS: Lazy<RepositoryLocks> = Lazy::new(RepositoryLocks::default);
 
+// 実行中のジョブ
 #[derive(Clone)]
 struct TaskHandle {
+    id: JobId,
     git_command_label: &'static str,
     window_label: String,
     token: CancellationToken,
-    is_running: bool,
+    // 操作するリポジトリと、そのリポジトリを変更するかどうか
+    repo_path: PathBuf,
+    lock_mode: LockMode,
 }
 
 impl TaskHandle {
-    fn new() -> Self {
-        TaskHandle {
-            git_command_label: "",
-            window_label: "".to_string(),
-            token: CancellationToken::new(),
-            is_running: false,
-        }
-    }
-
-    fn get_token(&self) -> CancellationToken {
-        self.token.clone()
-    }
-
-    fn run(&mut self) {
-        self.is_running = true;
-    }
-
-    fn done(&mut self) {
-        self.is_running = false;
-    }
-
-    fn cancel(&mut self) {
-        self.token.cancel();
-        self.done();
-
-        self.token = CancellationToken::new(); // 新しいトークンを生成
+    // 実行したgitの引数をジョブに記録するbackend
+    fn backend(&self) -> Arc<dyn GitBackend> {
+        let id = self.id;
+        Arc::new(ObservedBackend::new(GIT_BACKEND.clone(), move |request: &GitRequest| {
+            JOBS.record_git_args(id, request.args.clone());
+        }))
     }
 }
 
-async fn is_running_command(git_command_label: &'static str, window_label: String) -> bool {
-    let mut guard = GLOBAL_CANCELLATION_TOKENS.lock().await;
-
-    // 既存のトークンを検索
-    if let Some(task) = guard.iter().find(
-        |v| v.git_command_label == git_command_label && v.window_label == window_label)
-    {
-        if task.is_running {
-            debug!("Task is running: {} for window: {}", git_command_label, window_label);
-        }
-
-        return task.is_running;
+fn is_running_command(git_command_label: &'static str, window_label: String) -> bool {
+    let is_running = JOBS.is_running(git_command_label, &window_label);
+    if is_running {
+        debug!("Task is running: {} for window: {}", git_command_label, window_label);
     }
-
-    return false;
+    is_running
 }
 
-async fn create_task(git_command_label: &'static str, window_label: String) -> TaskHandle {
-    let mut guard = GLOBAL_CANCELLATION_TOKENS.lock().await;
-
-    // 既存のトークンを検索
-    if let Some(task) = guard.iter_mut().find(
-        |v| v.git_command_label == git_command_label && v.window_label == window_label)
-    {
-        debug!("Do task: {} for window: {}", git_command_label, window_label);
-
-        task.run();
-        return task.clone(); // 既存のトークンを返す
-    }
-
-    // 新しいトークンを生成
-    let mut new_task = TaskHandle::new();
-    new_task.git_command_label = git_command_label;
-    new_task.window_label = window_label.clone();
-
-    new_task.run();
-    let new_task_clone = new_task.clone();
-    guard.push(new_task);
-
-    debug!("Added task: {} for window: {}", git_command_label, window_label);
+fn create_task(git_command_label: &'static str, window_label: String, repo_path: &Path, lock_mode: LockMode) -> TaskHandle {
+    let (id, token) = JOBS.start(git_command_label, &window_label);
+    debug!("Added task: {} ({}) for window: {}", git_command_label, id, window_label);
 
-    new_task_clone
-}
-
-async fn done_task(git_command_label: &'static str, window_label: String) {
-    let mut guard = GLOBAL_CANCELLATION_TOKENS.lock().await;
-    if let Some(task) = guard.iter_mut().find(
-        |v| v.git_command_label == git_command_label && v.window_label == window_label)
-    {
-        task.done();
-        debug!("Done task: {} for window: {}", git_command_label, window_label);
-    } else {
-        debug!("Task not found: {} for window: {}", git_command_label, window_label);
+    TaskHandle {
+        id,
+        git_command_label,
+        window_label,
+        token,
+        repo_path: repo_path.to_path_buf(),
+        lock_mode,
     }
 }
 
-async fn cancel_task(git_command_label: &'static str, window_label: String) {
-    let mut guard = GLOBAL_CANCELLATION_TOKENS.lock().await;
-    if let Some(task) = guard.iter_mut().find(
-        |v| v.git_command_label == git_command_label && v.window_label == window_label)
-    {
-        task.cancel();
-        debug!("Cancelled task: {} for window: {}", git_command_label, window_label);
-    } else {
-        debug!("Task not found: {} for window: {}", git_command_label, window_label);
-    }
+fn cancel_task(git_command_label: &'static str, window_label: String) {
+    JOBS.cancel_command(git_command_label, &window_label);
+    debug!("Cancelled task: {} for window: {}", git_command_label, window_label);
 }
 
-// Typescript側のenumがswitch-caseで正しく動作しなかったのでu8としてserialize
-#[derive(Debug, Serialize_repr, Deserialize_repr, PartialEq)]
-#[repr(u8)]
-enum ChangeState {
-    Unknown = 0,
-    Change = 1,
-    Staging = 2,
-    Delete = 3,
-    Add = 4,
+// 実行中と最近終了したジョブの一覧
+#[command]
+fn list_jobs() -> JobList {
+    JOBS.list()
 }
 
-#[derive(Debug, Serialize_repr, Deserialize_repr, PartialEq)]
-#[repr(u8)]
-enum BranchState {
-    Unknown = 0,
-    Default = 1,
-    Current = 2,
-    Remote = 3,
-    All = 4,
+#[command]
+fn cancel_job(id: JobId) -> Result<(), String> {
+    JOBS.cancel(id)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct StatusInfo {
-    change_state: ChangeState,
-    filename: String,
+// 実行したgitの記録を新しい順に取得する
+#[command]
+fn query_audit_log(query: AuditQuery) -> Vec<AuditRecord> {
+    AUDIT_LOG.query(&query)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -175,139 +140,123 @@ struct EmitMessage<T> {
     result: T,
 }
 
-async fn run_git_command<F>(app_handle: AppHandle,
+// gitの操作を実行し、結果をウィンドウに通知する
+async fn run_git_task<T, F>(app_handle: AppHandle,
                             window_label: &str,
-                            mut task: TaskHandle,
-                            command: &mut Command,
-                            emit_event_name: &str,
-                            on_success: F)
-                            -> Result<String, String> where F: Fn(AppHandle, &str, &String) {
+                            task: TaskHandle,
+                            future: F,
+                            emit_event_name: &str)
+                            -> Result<T, String> where T: Serialize, F: Future<Output=Result<T, String>> {
     let cancellation_token = task.token.clone(); // キャンセルトークンをクローン
 
     // キャンセルトークンを使って非同期に待機
     let result = tokio::select! {
         _ = cancellation_token.cancelled() => {
-            // キャンセルされた場合、プロセスを強制終了
-            return Ok("Operation was cancelled.".to_string() + ": " + emit_event_name);
+            // キャンセルされた場合、futureをdropすることでプロセスを強制終了
+            JOBS.finish(task.id, JobStatus::Cancelled, None);
+            return Err("Operation was cancelled.".to_string() + ": " + emit_event_name);
         }
         // コマンド実行
+        // 書き込みは同じリポジトリで1つずつ、読み取りは書き込みが終わってから実行する
+        // (ロックを待っている間もキャンセルできるように、select!の中で取得する)
         result = async {
-            // tokio::time::sleep(std::time::Duration::from_secs(3)).await; // TODO: debug
-    
-            let output = command.set_creation_flags().output().await;
-            match output {
-                Ok(output) => {
-                    if output.status.success() {
-                        let output = String::from_utf8_lossy(&output.stdout).to_string();
-                        on_success(app_handle, window_label, &output);
-                        Ok(output)
-                    } else {
-                        let output = String::from_utf8_lossy(&output.stderr).to_string();
-                        let message = EmitMessage{
-                            is_ok:false,
-                            result: &output
-                        };
-                        post_git_command_result(app_handle, window_label, &message, emit_event_name);
-                        Err(output)
-                    }
-                }
-                Err(e) => {
-                    let message = EmitMessage{
-                        is_ok:false,
-                        result: &e.to_string()
-                    };
-                    post_git_command_result(app_handle, window_label, &message, emit_event_name);
-                    Err(e.to_string())
-                }
-            }
+            let _guard = REPOSITORY_LOCKS.acquire(&task.repo_path, task.lock_mode).await;
+            future.await
         } => {
             result
         }
     };
 
-    done_task(task.git_command_label, task.window_label).await;
+    match &result {
+        Ok(res) => {
+            let message = EmitMessage {
+                is_ok: true,
+                result: res,
+            };
+            post_git_command_result(app_handle, window_label, &message, emit_event_name);
+        }
+        Err(e) => {
+            let message = EmitMessage {
+                is_ok: false,
+                result: e,
+            };
+            post_git_command_result(app_handle, window_label, &message, emit_event_name);
+        }
+    }
+
+    match &result {
+        Ok(_) => JOBS.finish(task.id, JobStatus::Succeeded, None),
+        Err(e) => JOBS.finish(task.id, JobStatus::Failed, Some(e.clone())),
+    }
     result
 }
 
+// 操作の前後のリポジトリの状態をJOURNALに記録しながら実行する
+async fn run_journaled<T, F>(backend: &dyn GitBackend,
+                             repo_path: &Path,
+                             kind: OperationKind,
+                             discarded_files: &[String],
+                             future: F) -> Result<T, String> where F: Future<Output=Result<T, String>> {
+    journal::run_journaled(backend, &JOURNAL, repo_path, kind, discarded_files, future).await
+}
+
 fn post_git_command_result<T: Serialize>(app_handle: AppHandle, window_label: &str, result: &EmitMessage<T>, emit_event_name: &str) {
     app_handle.app_handle()
         .emit_to(window_label, emit_event_name, result)
         .unwrap();
 }
 
+const GIT_PROGRESS_EVENT: &str = "git-progress";
+
+// 実行中のタスクの進捗 (どのコマンドの進捗かをフロントエンドで判別できるようにする)
+#[derive(Debug, Clone, Serialize)]
+struct ProgressMessage {
+    job_id: JobId,
+    git_command_label: &'static str,
+    window_label: String,
+    progress: GitProgress,
+}
+
+// タスクの進捗をウィンドウに通知するコールバックを作成する
+fn progress_emitter(app_handle: AppHandle, task: &TaskHandle) -> impl Fn(GitProgress) + Send + Sync {
+    let job_id = task.id;
+    let git_command_label = task.git_command_label;
+    let window_label = task.window_label.clone();
+    move |progress| {
+        let message = ProgressMessage {
+            job_id,
+            git_command_label,
+            window_label: window_label.clone(),
+            progress,
+        };
+        let _ = app_handle.emit_to(&window_label, GIT_PROGRESS_EVENT, message);
+    }
+}
+
 const GIT_STATUS_COMMAND: &str = "git_status";
 
 #[command]
 async fn git_status(app_handle: AppHandle, window_label: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_STATUS_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_STATUS_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
-    // unstagingしておく
-    let _ = Command::new("git").arg("reset").set_creation_flags().output().await;
-
+    let repo_path = repository_path(&window_label)?;
 
     const RESULT_LABLE: &str = "post-git-status-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_STATUS_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_STATUS_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("status")
-            .arg("-s")
-            .arg("-uall");
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABLE,
-                              |h, wl, o| {
-                                  let lines: Vec<StatusInfo> = o
-                                      .lines()
-                                      .map(|line| {
-                                          let (_, file) = line.split_at(3);
-                                          let filename = file.trim().to_string();
-                                          match &line[0..2] {
-                                              " M" => {
-                                                  StatusInfo { change_state: ChangeState::Change, filename }
-                                              }
-                                              "M " => {
-                                                  StatusInfo { change_state: ChangeState::Staging, filename }
-                                              }
-                                              " D" => {
-                                                  StatusInfo { change_state: ChangeState::Delete, filename }
-                                              }
-                                              "??" => {
-                                                  StatusInfo { change_state: ChangeState::Add, filename }
-                                              }
-                                              // git処理が遅く、新規追加ファイルのdiffを取るための`git add -N`処理後の`git diff`実行中に
-                                              // `git status`が走った場合、stagingファイル扱いとなる。
-                                              // `git status`のタイミングの関係でstaging扱いとなるが、その後の`git diff`完了後に
-                                              // `git reset`が行われるためUntrackedに戻ってはいるはずなので問題はない。
-                                              " A" => {
-                                                  StatusInfo { change_state: ChangeState::Add, filename }
-                                              }
-                                              _ => {
-                                                  StatusInfo { change_state: ChangeState::Unknown, filename }
-                                              }
-                                          }
-                                      })
-                                      .collect();
-
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &lines,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABLE);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.status(&repo_path),
+                             RESULT_LABLE,
+        ).await;
     });
 
     Ok(())
@@ -315,49 +264,35 @@ async fn git_status(app_handle: AppHandle, window_label: String) -> Result<(), S
 
 #[command]
 async fn git_status_cancel(window_label: String) {
-    cancel_task(GIT_STATUS_COMMAND, window_label).await;
+    cancel_task(GIT_STATUS_COMMAND, window_label);
 }
 
 const GIT_DIFF_COMMAND: &str = "git_diff";
 
 #[command]
-async fn git_diff(app_handle: AppHandle, window_label: String, file: String) -> Result<(), String> {
-    // 新規追加ファイルのdiffを取るため`git add -N`しておく
-    let _ = Command::new("git").arg("add").arg("-N").arg(&file).set_creation_flags().output().await;
+async fn git_diff(app_handle: AppHandle, window_label: String, file: String, staged: Option<bool>, options: Option<DiffOptions>) -> Result<(), String> {
+    // 実行中かどうかをチェック (別のファイルを表示する場合は、フロントエンドが先にgit_diff_cancelを呼ぶ)
+    if is_running_command(GIT_DIFF_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+    let staged = staged.unwrap_or(false);
+    let options = options.unwrap_or_else(|| Settings::load(&get_app_config_dir()).diff);
 
     const RESULT_LABEL: &str = "post-git-diff-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_DIFF_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_DIFF_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("diff")
-            .arg(file);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &o,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-
-                                  // 新規追加ファイルのdiffを取るための`git add -N`を元に戻す
-                                  let _ = Command::new("git").arg("reset").set_creation_flags().output();
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {
-                // 新規追加ファイルのdiffを取るための`git add -N`を元に戻す
-                let _ = Command::new("git").arg("reset").set_creation_flags().output();
-            }
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             diff_file(backend.as_ref(), &repo_path, &file, staged, &options),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -365,10 +300,146 @@ async fn git_diff(app_handle: AppHandle, window_label: String, file: String) ->
 
 #[command]
 async fn git_diff_cancel(window_label: String) {
-    cancel_task(GIT_DIFF_COMMAND, window_label).await;
+    cancel_task(GIT_DIFF_COMMAND, window_label);
+}
+
+const GIT_STAGE_COMMAND: &str = "git_stage";
 
-    // 新規追加ファイルのdiffを取るための`git add -N`を元に戻す
-    let _ = Command::new("git").arg("reset").set_creation_flags().output().await;
+#[command]
+async fn git_stage(app_handle: AppHandle, window_label: String, infos: Vec<StatusInfo>) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_STAGE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-stage-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_STAGE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.stage(&repo_path, infos.into_iter().map(|v| v.filename).collect()),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stage_cancel(window_label: String) {
+    cancel_task(GIT_STAGE_COMMAND, window_label);
+}
+
+const GIT_UNSTAGE_COMMAND: &str = "git_unstage";
+
+#[command]
+async fn git_unstage(app_handle: AppHandle, window_label: String, infos: Vec<StatusInfo>) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_UNSTAGE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-unstage-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_UNSTAGE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.unstage(&repo_path, infos),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_unstage_cancel(window_label: String) {
+    cancel_task(GIT_UNSTAGE_COMMAND, window_label);
+}
+
+const GIT_STAGE_HUNKS_COMMAND: &str = "git_stage_hunks";
+const GIT_UNSTAGE_HUNKS_COMMAND: &str = "git_unstage_hunks";
+
+// 選択されたhunk/行のみをindexに適用する (`git add -p`相当)
+async fn apply_hunks(app_handle: AppHandle,
+                     window_label: String,
+                     git_command_label: &'static str,
+                     result_label: &'static str,
+                     file: String,
+                     selections: Vec<HunkSelection>,
+                     direction: PatchDirection,
+                     options: Option<DiffOptions>) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(git_command_label, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+    // hunk/行の番号はgit_diffで表示したものなので、同じオプションを使う
+    let options = options.unwrap_or_else(|| Settings::load(&get_app_config_dir()).diff);
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(git_command_label, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend::apply_hunks(backend.as_ref(), &repo_path, &file, &selections, direction, &options),
+                             result_label,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stage_hunks(app_handle: AppHandle, window_label: String, file: String, selections: Vec<HunkSelection>, options: Option<DiffOptions>) -> Result<(), String> {
+    apply_hunks(app_handle,
+                window_label,
+                GIT_STAGE_HUNKS_COMMAND,
+                "post-git-stage-hunks-result",
+                file,
+                selections,
+                PatchDirection::Stage,
+                options).await
+}
+
+#[command]
+async fn git_stage_hunks_cancel(window_label: String) {
+    cancel_task(GIT_STAGE_HUNKS_COMMAND, window_label);
+}
+
+#[command]
+async fn git_unstage_hunks(app_handle: AppHandle, window_label: String, file: String, selections: Vec<HunkSelection>, options: Option<DiffOptions>) -> Result<(), String> {
+    apply_hunks(app_handle,
+                window_label,
+                GIT_UNSTAGE_HUNKS_COMMAND,
+                "post-git-unstage-hunks-result",
+                file,
+                selections,
+                PatchDirection::Unstage,
+                options).await
+}
+
+#[command]
+async fn git_unstage_hunks_cancel(window_label: String) {
+    cancel_task(GIT_UNSTAGE_HUNKS_COMMAND, window_label);
 }
 
 const GIT_DISCARD_CHANGES_ADDS_COMMAND: &str = "git_discard_changes_adds";
@@ -378,12 +449,14 @@ const GIT_DISCARD_CHANGES_OTHERS_COMMAND: &str = "git_discard_changes_others";
 #[command]
 async fn git_discard_changes(app_handle: AppHandle, window_label: String, infos: Vec<StatusInfo>) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_DISCARD_CHANGES_ADDS_COMMAND, window_label.clone()).await ||
-        is_running_command(GIT_DISCARD_CHANGES_OTHERS_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_DISCARD_CHANGES_ADDS_COMMAND, window_label.clone()) ||
+        is_running_command(GIT_DISCARD_CHANGES_OTHERS_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
-    let (adds, others): (Vec<StatusInfo>, Vec<StatusInfo>) = infos.into_iter().partition(|status_info| status_info.change_state == ChangeState::Add);
+    let repo_path = repository_path(&window_label)?;
+
+    let (adds, others): (Vec<StatusInfo>, Vec<StatusInfo>) = infos.into_iter().partition(|status_info| status_info.worktree_state == ChangeState::Untracked);
 
 
     const RESULT_LABEL_ADDS: &str = "post-git-discard-changes-adds-result";
@@ -392,32 +465,25 @@ async fn git_discard_changes(app_handle: AppHandle, window_label: String, infos:
     let window_label_adds = window_label.clone();
     if !&adds.is_empty() {
         // CancellationTokenをクローンして非同期タスクに渡す
-        let task_adds = create_task(GIT_DISCARD_CHANGES_ADDS_COMMAND, window_label_adds.clone()).await;
+        let task_adds = create_task(GIT_DISCARD_CHANGES_ADDS_COMMAND, window_label_adds.clone(), &repo_path, LockMode::Write);
 
         let app_handle_clone = app_handle_adds.clone();
         let window_label_clone = window_label_adds.clone();
+        let repo_path_clone = repo_path.clone();
+        let backend = task_adds.backend();
         tokio::spawn(async move {
-            let mut binding = Command::new("git");
-            let command_adds = binding.arg("clean").arg("-f");
-            for v in adds {
-                command_adds.arg(&v.filename);
-            }
-            match run_git_command(app_handle_clone,
-                                  window_label_clone.as_str(),
-                                  task_adds,
-                                  command_adds,
-                                  RESULT_LABEL_ADDS,
-                                  |h, wl, o| {
-                                      let message = EmitMessage {
-                                          is_ok: true,
-                                          result: &o,
-                                      };
-                                      post_git_command_result(h, wl, &message, RESULT_LABEL_ADDS);
-                                  },
-            ).await {
-                Ok(output) => {}
-                Err(e) => {}
-            }
+            let files: Vec<String> = adds.into_iter().map(|v| v.filename).collect();
+            let future = run_journaled(backend.as_ref(),
+                                       &repo_path_clone,
+                                       OperationKind::Discard,
+                                       &files,
+                                       backend.clean_files(&repo_path_clone, files.clone()));
+            let _ = run_git_task(app_handle_clone,
+                                 window_label_clone.as_str(),
+                                 task_adds,
+                                 future,
+                                 RESULT_LABEL_ADDS,
+            ).await;
         });
     } else {
         let message = EmitMessage {
@@ -434,32 +500,25 @@ async fn git_discard_changes(app_handle: AppHandle, window_label: String, infos:
     let window_label_others = window_label.clone();
     if !&others.is_empty() {
         // CancellationTokenをクローンして非同期タスクに渡す
-        let task_others = create_task(GIT_DISCARD_CHANGES_OTHERS_COMMAND, window_label_others.clone()).await;
+        let task_others = create_task(GIT_DISCARD_CHANGES_OTHERS_COMMAND, window_label_others.clone(), &repo_path, LockMode::Write);
 
         let app_handle_clone = app_handle_others.clone();
         let window_label_clone = window_label_others.clone();
+        let repo_path_clone = repo_path.clone();
+        let backend = task_others.backend();
         tokio::spawn(async move {
-            let mut binding = Command::new("git");
-            let command_others = binding.arg("checkout").arg("--");
-            for v in others {
-                command_others.arg(&v.filename);
-            }
-            match run_git_command(app_handle_clone,
-                                  window_label_clone.as_str(),
-                                  task_others,
-                                  command_others,
-                                  REUSLT_LABEL_OTHERS,
-                                  |h, wl, o| {
-                                      let message = EmitMessage {
-                                          is_ok: true,
-                                          result: &o,
-                                      };
-                                      post_git_command_result(h, wl, &message, REUSLT_LABEL_OTHERS);
-                                  },
-            ).await {
-                Ok(output) => {}
-                Err(e) => {}
-            }
+            let files: Vec<String> = others.into_iter().map(|v| v.filename).collect();
+            let future = run_journaled(backend.as_ref(),
+                                       &repo_path_clone,
+                                       OperationKind::Discard,
+                                       &files,
+                                       backend.checkout_files(&repo_path_clone, files.clone()));
+            let _ = run_git_task(app_handle_clone,
+                                 window_label_clone.as_str(),
+                                 task_others,
+                                 future,
+                                 REUSLT_LABEL_OTHERS,
+            ).await;
         });
     } else {
         let message = EmitMessage {
@@ -474,17 +533,21 @@ async fn git_discard_changes(app_handle: AppHandle, window_label: String, infos:
 
 #[command]
 async fn git_discard_changes_adds_cancel(window_label: String) {
-    cancel_task(GIT_DISCARD_CHANGES_ADDS_COMMAND, window_label).await;
+    cancel_task(GIT_DISCARD_CHANGES_ADDS_COMMAND, window_label);
 }
 
 #[command]
 async fn git_discard_changes_others_cancel(window_label: String) {
-    cancel_task(GIT_DISCARD_CHANGES_OTHERS_COMMAND, window_label).await;
+    cancel_task(GIT_DISCARD_CHANGES_OTHERS_COMMAND, window_label);
 }
 
 // ファイルの場所を開く関数 (macOSとWindowsに対応)
 #[command]
-async fn open_file_location(file: &str) -> Result<(), String> {
+async fn open_file_location(window_label: String, file: &str) -> Result<(), String> {
+    // ファイル名はリポジトリからの相対パスなので絶対パスにする
+    let file_path = repository_path(&window_label)?.join(file);
+    let file = file_path.to_str().ok_or("Invalid path")?;
+
     if cfg!(target_os = "macos") {
         match Command::new("open").arg("-R").arg(file).output().await {
             Ok(_) => Ok(()),
@@ -512,53 +575,29 @@ async fn open_file_location(file: &str) -> Result<(), String> {
 const GIT_COMMIT_COMMAND: &str = "git_commit";
 
 #[command]
-async fn git_commit(app_handle: AppHandle, window_label: String, files: Vec<String>, message: String) -> Result<(), String> {
+async fn git_commit(app_handle: AppHandle, window_label: String, message: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_COMMIT_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_COMMIT_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+
     const RESULT_LABEL: &str = "post-git-commit-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_COMMIT_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_COMMIT_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command: &mut Command;
-        if !message.is_empty() {
-            command = binding.arg("commit").arg("-m").arg(message);
-        } else {
-            command = binding.arg("commit").arg("--amend").arg("--no-edit");
-        }
-
-        // stagingする
-        let mut staging = Command::new("git");
-        staging.arg("add");
-        for file in files {
-            staging.arg(&file);
-
-            // コミット対象として追加
-            command.arg(file);
-        }
-        let _ = staging.set_creation_flags().output().await;
-
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &o,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        // メッセージが空の場合は直前のコミットに追加する
+        let kind = if message.is_empty() { OperationKind::Amend } else { OperationKind::Commit };
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             run_journaled(backend.as_ref(), &repo_path, kind, &[], backend.commit(&repo_path, message)),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -566,7 +605,7 @@ async fn git_commit(app_handle: AppHandle, window_label: String, files: Vec<Stri
 
 #[command]
 async fn git_commit_cancel(window_label: String) {
-    cancel_task(GIT_COMMIT_COMMAND, window_label).await;
+    cancel_task(GIT_COMMIT_COMMAND, window_label);
 }
 
 const GIT_PUSH_COMMAND: &str = "git_push";
@@ -574,39 +613,35 @@ const GIT_PUSH_COMMAND: &str = "git_push";
 #[command]
 async fn git_push(app_handle: AppHandle, window_label: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_PUSH_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_PUSH_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+    let settings = Settings::load(&get_app_config_dir()).push;
+
     const RESULT_LABEL: &str = "post-git-push-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_PUSH_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_PUSH_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("push")
-            .arg("-u")
-            .arg("origin")
-            .arg("HEAD");
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &"Push Success!".to_string(),
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
+        let on_progress = progress_emitter(app_handle.clone(), &task);
+        let future = async {
+            let push = backend.push(&repo_path, settings.remote, settings.set_upstream, &on_progress);
+            run_journaled(backend.as_ref(), &repo_path, OperationKind::Push, &[], push).await.map(|output| {
+                debug!("ok: {}", output);
+                "Push Success!".to_string()
+            })
+        };
+        match run_git_task(app_handle,
+                           window_label.as_str(),
+                           task,
+                           future,
+                           RESULT_LABEL,
         ).await {
-            Ok(output) => {
-                debug!("ok: {}", output)
-            }
+            Ok(_) => {}
             Err(e) => {
                 debug!("err: {}", e)
             }
@@ -618,7 +653,7 @@ async fn git_push(app_handle: AppHandle, window_label: String) -> Result<(), Str
 
 #[command]
 async fn git_push_cancel(window_label: String) {
-    cancel_task(GIT_PUSH_COMMAND, window_label).await;
+    cancel_task(GIT_PUSH_COMMAND, window_label);
 }
 
 const GIT_PULL_COMMAND: &str = "git_pull";
@@ -626,37 +661,35 @@ const GIT_PULL_COMMAND: &str = "git_pull";
 #[command]
 async fn git_pull(app_handle: AppHandle, window_label: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_PULL_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_PULL_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+    let settings = Settings::load(&get_app_config_dir()).pull;
+
     const RESULT_LABEL: &str = "post-git-pull-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_PULL_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_PULL_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("pull")
-            .arg("--prune");
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &"Pull Success!".to_string(),
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
+        let on_progress = progress_emitter(app_handle.clone(), &task);
+        let future = async {
+            let pull = backend.pull(&repo_path, settings.prune, &on_progress);
+            run_journaled(backend.as_ref(), &repo_path, OperationKind::Pull, &[], pull).await.map(|output| {
+                debug!("ok: {}", output);
+                "Pull Success!".to_string()
+            })
+        };
+        match run_git_task(app_handle,
+                           window_label.as_str(),
+                           task,
+                           future,
+                           RESULT_LABEL,
         ).await {
-            Ok(output) => {
-                debug!("ok: {}", output)
-            }
+            Ok(_) => {}
             Err(e) => {
                 debug!("err: {}", e)
             }
@@ -668,7 +701,7 @@ async fn git_pull(app_handle: AppHandle, window_label: String) -> Result<(), Str
 
 #[command]
 async fn git_pull_cancel(window_label: String) {
-    cancel_task(GIT_PULL_COMMAND, window_label).await;
+    cancel_task(GIT_PULL_COMMAND, window_label);
 }
 
 const GIT_FETCH_COMMAND: &str = "git_fetch";
@@ -676,37 +709,27 @@ const GIT_FETCH_COMMAND: &str = "git_fetch";
 #[command]
 async fn git_fetch(app_handle: AppHandle, window_label: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_FETCH_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_FETCH_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+    let settings = Settings::load(&get_app_config_dir()).fetch;
+
     const RESULT_LABEL: &str = "post-git-fetch-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_FETCH_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_FETCH_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("fetch")
-            .arg("--prune");
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &o,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let on_progress = progress_emitter(app_handle.clone(), &task);
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.fetch(&repo_path, settings.prune, &on_progress),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -714,35 +737,64 @@ async fn git_fetch(app_handle: AppHandle, window_label: String) -> Result<(), St
 
 #[command]
 async fn git_fetch_cancel(window_label: String) {
-    cancel_task(GIT_FETCH_COMMAND, window_label).await;
+    cancel_task(GIT_FETCH_COMMAND, window_label);
 }
 
-// (&str と String を両方取れるようにする: https://qiita.com/yasuo-ozu/items/987b7c4a7e2ebab098a4)
-fn extract_ahead_behind_counts<'a, S: Into<Cow<'a, str>>>(s: S) -> Result<(u16, u16), &'static str> {
-    let s: Cow<'a, str> = s.into();
-    let status: &str = &s;
-    let re = Regex::new(r"(?:(ahead (\d+))|(behind (\d+)))").map_err(|_| "Failed to compile regex")?;
+const GIT_CLONE_COMMAND: &str = "git_clone";
 
-    let mut ahead_count = 0;
-    let mut behind_count = 0;
+// parent_dirの下にURLから決めた名前のフォルダを作成してcloneし、このウィンドウで開く
+#[command]
+async fn git_clone(app_handle: AppHandle, window_label: String, url: String, parent_dir: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_CLONE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
 
-    for cap in re.captures_iter(status) {
-        if let Some(ahead) = cap.get(2) {
-            ahead_count = u16::from_str(ahead.as_str()).map_err(|_| "Failed to parse ahead count")?;
-        }
-        if let Some(behind) = cap.get(4) {
-            behind_count = u16::from_str(behind.as_str()).map_err(|_| "Failed to parse behind count")?;
-        }
+    let directory_name = clone_directory_name(&url).ok_or("Invalid repository URL.")?;
+    let parent_dir = PathBuf::from(parent_dir);
+    let destination = parent_dir.join(&directory_name);
+    if destination.exists() {
+        return Err(format!("'{}' already exists.", destination.to_string_lossy()));
     }
 
-    Ok((ahead_count, behind_count))
-}
+    const RESULT_LABEL: &str = "post-git-clone-result";
 
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_CLONE_COMMAND, window_label.clone(), &destination, LockMode::Write);
 
-#[derive(Debug, Serialize, Deserialize)]
-struct PullPushCountInfo {
-    push_count: u16,
-    pull_count: u16,
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let on_progress = progress_emitter(app_handle.clone(), &task);
+        // cloneが終わった後に失敗・キャンセルした場合は、作成したリポジトリを残す
+        let cloned = AtomicBool::new(false);
+        let future = async {
+            backend.clone_repository(&parent_dir, url, directory_name, &on_progress).await?;
+            cloned.store(true, Ordering::SeqCst);
+
+            let path = destination.to_string_lossy().to_string();
+            record_opened_repository(&get_app_config_dir(), &path)?;
+            set_repository(&window_label, destination.clone());
+            Ok::<String, String>(path)
+        };
+        let result = run_git_task(app_handle,
+                                  window_label.as_str(),
+                                  task,
+                                  future,
+                                  RESULT_LABEL,
+        ).await;
+
+        // cloneが失敗・キャンセルした場合は途中まで作成されたフォルダを削除する (cloneの前には存在しなかったもの)
+        if result.is_err() && !cloned.load(Ordering::SeqCst) && destination.exists() {
+            let _ = std::fs::remove_dir_all(&destination);
+        }
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_clone_cancel(window_label: String) {
+    cancel_task(GIT_CLONE_COMMAND, window_label);
 }
 
 const GET_PULL_PUSH_COUNT_COMMAND: &str = "get_pull_push_count";
@@ -750,40 +802,25 @@ const GET_PULL_PUSH_COUNT_COMMAND: &str = "get_pull_push_count";
 #[command]
 async fn get_pull_push_count(app_handle: AppHandle, window_label: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GET_PULL_PUSH_COUNT_COMMAND, window_label.clone()).await {
+    if is_running_command(GET_PULL_PUSH_COUNT_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+
     const RESULT_LABEL: &str = "post-get-pull-push-count";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GET_PULL_PUSH_COUNT_COMMAND, window_label.clone()).await;
+    let task = create_task(GET_PULL_PUSH_COUNT_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("status")
-            .arg("-sb");
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let (ahead_count, behind_count) = extract_ahead_behind_counts(o).expect("Failed to extract counts");
-                                  let res = PullPushCountInfo { push_count: ahead_count, pull_count: behind_count };
-
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &res,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.pull_push_count(&repo_path),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -791,7 +828,7 @@ async fn get_pull_push_count(app_handle: AppHandle, window_label: String) -> Res
 
 #[command]
 async fn get_pull_push_count_cancel(window_label: String) {
-    cancel_task(GET_PULL_PUSH_COUNT_COMMAND, window_label).await;
+    cancel_task(GET_PULL_PUSH_COUNT_COMMAND, window_label);
 }
 
 // フォルダパスの保存先ディレクトリを取得する関数
@@ -804,37 +841,6 @@ fn get_app_config_dir() -> PathBuf {
     config_dir
 }
 
-// フォルダパスを保存する関数
-fn save_folder_path(path: &str) -> io::Result<()> {
-    let config_dir = get_app_config_dir();
-    let file_path = config_dir.join("selected_git_folder.txt");
-    let mut file = File::create(file_path)?;
-    file.write_all(path.as_bytes())?;
-    Ok(())
-}
-
-// フォルダパスを読み込む関数
-fn load_folder_path() -> io::Result<String> {
-    let config_dir = get_app_config_dir();
-    let file_path = config_dir.join("selected_git_folder.txt");
-    let mut path = fs::read_to_string(file_path)?;
-
-    if !Path::new(&path).exists() {
-        path = home_dir().unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-    }
-
-    Ok(path)
-}
-
-fn set_current_dir(dir_path: &String) {
-    let mut path = CURRENT_PATH.lock().unwrap();
-    *path = dir_path.to_string();
-
-    let _ = env::set_current_dir(&dir_path).map_err(|e| e.to_string());
-}
 
 
 fn git_folder_exists(dir_path: &String) -> bool {
@@ -845,15 +851,13 @@ fn git_folder_exists(dir_path: &String) -> bool {
     git_path.is_dir()
 }
 
-#[command]
-fn select_git_folder() -> Result<String, String> {
+// フォルダ選択ダイアログでgitリポジトリを選ぶ
+// (macOSではダイアログをメインスレッドで開く必要があるため、asyncでないcommandで呼ぶ)
+fn pick_git_folder() -> Result<PathBuf, String> {
     match rfd::FileDialog::new().pick_folder() {
         Some(path) => {
             if git_folder_exists(&path.to_str().unwrap().to_string()) {
-                let path_str = path.to_str().ok_or("Invalid path")?.to_string();
-                save_folder_path(&path_str).map_err(|e| e.to_string())?;
-                set_current_dir(&path_str);
-                Ok(path_str)
+                Ok(path)
             } else {
                 Err("Not a git repository.".into())
             }
@@ -864,18 +868,84 @@ fn select_git_folder() -> Result<String, String> {
 }
 
 #[command]
-fn get_git_folder() -> Result<String, String> {
-    Ok(CURRENT_PATH.lock().unwrap().to_string())
+fn select_git_folder(window_label: String) -> Result<String, String> {
+    let path = pick_git_folder()?;
+    let path_str = path.to_str().ok_or("Invalid path")?.to_string();
+    record_opened_repository(&get_app_config_dir(), &path_str)?;
+    set_repository(&window_label, path);
+    Ok(path_str)
+}
+
+// 最近開いたリポジトリの一覧を取得する (ピン留めされているものが先頭)
+#[command]
+fn get_recent_repositories() -> Vec<RecentRepository> {
+    list_recent_repositories(&get_app_config_dir())
+}
+
+// 最近開いたリポジトリをこのウィンドウで開く
+#[command]
+fn open_recent_repository(window_label: String, path: String) -> Result<String, String> {
+    let config_dir = get_app_config_dir();
+    if !git_folder_exists(&path) {
+        // 存在しなくなったリポジトリは一覧から取り除く
+        forget_repository(&config_dir, &path)?;
+        return Err("Not a git repository.".into());
+    }
+
+    record_opened_repository(&config_dir, &path)?;
+    set_repository(&window_label, PathBuf::from(&path));
+    Ok(path)
+}
+
+#[command]
+fn forget_recent_repository(path: String) -> Result<Vec<RecentRepository>, String> {
+    forget_repository(&get_app_config_dir(), &path)
+}
+
+#[command]
+fn pin_recent_repository(path: String, pinned: bool) -> Result<Vec<RecentRepository>, String> {
+    pin_repository(&get_app_config_dir(), &path, pinned)
+}
+
+#[command]
+fn rename_recent_repository(path: String, display_name: String) -> Result<Vec<RecentRepository>, String> {
+    rename_repository(&get_app_config_dir(), &path, &display_name)
+}
+
+// 新しいウィンドウで開くリポジトリを選択する
+#[command]
+fn select_repository_for_new_window() -> Result<String, String> {
+    let path = pick_git_folder()?;
+    Ok(path.to_str().ok_or("Invalid path")?.to_string())
+}
+
+// clone先のフォルダを選択する (選択したフォルダの下にリポジトリのフォルダが作成される)
+#[command]
+fn select_clone_destination() -> Result<String, String> {
+    let path = rfd::FileDialog::new().pick_folder().ok_or("")?;
+    Ok(path.to_str().ok_or("Invalid path")?.to_string())
+}
+
+#[command]
+fn get_git_folder(window_label: String) -> Result<String, String> {
+    Ok(get_repository(&window_label)
+        .map(|v| v.to_string_lossy().to_string())
+        .unwrap_or_default())
 }
 
-#[derive(Serialize)]
-struct Commit {
-    graph: String,
-    hash: String,
-    author: String,
-    message: String,
-    date: String,
-    branch: String,
+#[command]
+fn get_settings() -> Settings {
+    Settings::load(&get_app_config_dir())
+}
+
+// 設定を保存し、全てのウィンドウに変更を通知する
+#[command]
+fn update_settings(app_handle: AppHandle, settings: Settings) -> Result<Settings, String> {
+    settings.save(&get_app_config_dir())?;
+
+    let settings = Settings::load(&get_app_config_dir());
+    app_handle.emit_all("settings-changed", &settings).map_err(|e| e.to_string())?;
+    Ok(settings)
 }
 
 const GIT_LOG_COMMAND: &str = "git_log";
@@ -883,81 +953,33 @@ const GIT_LOG_COMMAND: &str = "git_log";
 #[command]
 async fn git_log(app_handle: AppHandle, window_label: String, is_show_all: bool, branch_name: String, is_first_parent: bool) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_LOG_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_LOG_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+    let settings = Settings::load(&get_app_config_dir()).log;
+
     const RESULT_LABEL: &str = "post-git-log-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_LOG_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_LOG_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
-    tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("log")
-            .arg("--graph")
-            .arg("--color")
-            .arg("--oneline")
-            .arg("--date=format:%Y/%m/%d (%a) %H:%M")
-            .arg("--format=___%h___%an___%s___%ad___%C(auto)%d%C(reset)");
-        if is_show_all {
-            command.arg("--all");
-        } else {
-            if is_first_parent {
-                command.arg("--first-parent");
-            }
-            if !branch_name.is_empty() {
-                command.arg(branch_name);
-                command.arg("--"); // ファイル/フォルダ名とブランチ名が同じ場合エラーが出るためブランチ名として明示する (https://qiita.com/hakuisan/items/d2e40bec6d2785202885)
-            }
-        }
+    let query = LogQuery {
+        is_show_all,
+        branch_name,
+        is_first_parent,
+        date_format: settings.date_format,
+    };
 
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let res: Vec<Commit> = o
-                                      .lines()
-                                      .map(|line| {
-                                          let parts: Vec<&str> = line.split("___").collect();
-                                          if parts.len() > 1 {
-                                              // コミットメッセージありの行
-                                              Commit {
-                                                  graph: parts[0].to_string(),
-                                                  hash: parts[1].to_string(),
-                                                  author: parts[2].to_string(),
-                                                  message: parts[3].to_string(),
-                                                  date: parts[4].to_string(),
-                                                  branch: parts[5].to_string(),
-                                              }
-                                          } else {
-                                              // コミットメッセージなしのブランチ表記のみの行
-                                              Commit {
-                                                  graph: parts[0].to_string(),
-                                                  hash: "".to_string(),
-                                                  author: "".to_string(),
-                                                  message: "".to_string(),
-                                                  date: "".to_string(),
-                                                  branch: "".to_string(),
-                                              }
-                                          }
-                                      })
-                                      .collect();
-
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &res,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.log(&repo_path, query),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -965,15 +987,7 @@ async fn git_log(app_handle: AppHandle, window_label: String, is_show_all: bool,
 
 #[command]
 async fn git_log_cancel(window_label: String) {
-    cancel_task(GIT_LOG_COMMAND, window_label).await;
-}
-
-#[derive(Serialize)]
-struct ShowInfo {
-    hash: String,
-    author: String,
-    date: String,
-    message: String,
+    cancel_task(GIT_LOG_COMMAND, window_label);
 }
 
 const GIT_SHOW_COMMAND: &str = "git_show";
@@ -981,54 +995,25 @@ const GIT_SHOW_COMMAND: &str = "git_show";
 #[command]
 async fn git_show(app_handle: AppHandle, window_label: String, hash: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_SHOW_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_SHOW_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+
     const RESULT_LABEL: &str = "post-git-show-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_SHOW_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_SHOW_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("show")
-            .arg("--pretty=format:%H%n%an%n%ad%n%B")
-            .arg("--no-patch")
-            .arg(&hash);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let mut lines = o.lines();
-
-                                  // 最初の4行をパース
-                                  let hash = lines.next().unwrap_or("").to_string();
-                                  let author = lines.next().unwrap_or("").to_string();
-                                  let date = lines.next().unwrap_or("").to_string();
-                                  let message = lines.collect::<Vec<&str>>().join("\n");
-
-                                  let res = ShowInfo {
-                                      hash,
-                                      author,
-                                      date,
-                                      message,
-                                  };
-
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &res,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.show(&repo_path, hash),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -1036,7 +1021,7 @@ async fn git_show(app_handle: AppHandle, window_label: String, hash: String) ->
 
 #[command]
 async fn git_show_cancel(window_label: String) {
-    cancel_task(GIT_SHOW_COMMAND, window_label).await;
+    cancel_task(GIT_SHOW_COMMAND, window_label);
 }
 
 const GIT_SHOW_FILES_COMMAND: &str = "git_show_files";
@@ -1044,61 +1029,25 @@ const GIT_SHOW_FILES_COMMAND: &str = "git_show_files";
 #[command]
 async fn git_show_files(app_handle: AppHandle, window_label: String, hash: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_SHOW_FILES_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_SHOW_FILES_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+
     const RESULT_LABEL: &str = "post-git-show-files-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_SHOW_FILES_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_SHOW_FILES_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("show")
-            .arg("--pretty=format:")
-            .arg("--name-status")
-            .arg(&hash);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let res: Vec<StatusInfo> = o
-                                      .lines()
-                                      .map(|line| {
-                                          let (_, file) = line.split_at(2);
-                                          let mut filename = file.trim().to_string();
-                                          match &line[0..1] {
-                                              "M" => {
-                                                  StatusInfo { change_state: ChangeState::Change, filename }
-                                              }
-                                              "D" => {
-                                                  StatusInfo { change_state: ChangeState::Delete, filename }
-                                              }
-                                              "A" => {
-                                                  StatusInfo { change_state: ChangeState::Add, filename }
-                                              }
-                                              _ => {
-                                                  StatusInfo { change_state: ChangeState::Unknown, filename }
-                                              }
-                                          }
-                                      })
-                                      .collect();
-
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &res,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.show_files(&repo_path, hash),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -1106,49 +1055,36 @@ async fn git_show_files(app_handle: AppHandle, window_label: String, hash: Strin
 
 #[command]
 async fn git_show_files_cancel(window_label: String) {
-    cancel_task(GIT_SHOW_FILES_COMMAND, window_label).await;
+    cancel_task(GIT_SHOW_FILES_COMMAND, window_label);
 }
 
 const GIT_SHOW_FILE_DIFF_COMMAND: &str = "git_show_file_diff";
 
 // git show --pretty=format: <commit hash> -- <filename>
 #[command]
-async fn git_show_file_diff(app_handle: AppHandle, window_label: String, hash: String, file: String) -> Result<(), String> {
+async fn git_show_file_diff(app_handle: AppHandle, window_label: String, hash: String, file: String, options: Option<DiffOptions>) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_SHOW_FILE_DIFF_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_SHOW_FILE_DIFF_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+
     const RESULT_LABEL: &str = "post-git-show-file-diff-result";
 
+    let options = options.unwrap_or_else(|| Settings::load(&get_app_config_dir()).diff);
+
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_SHOW_FILE_DIFF_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_SHOW_FILE_DIFF_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("show")
-            .arg("--pretty=format:")
-            .arg(hash)
-            .arg("--")
-            .arg(file);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &o,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.show_file_diff(&repo_path, hash, file, &options),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -1156,13 +1092,13 @@ async fn git_show_file_diff(app_handle: AppHandle, window_label: String, hash: S
 
 #[command]
 async fn git_show_file_diff_cancel(window_label: String) {
-    cancel_task(GIT_SHOW_FILE_DIFF_COMMAND, window_label).await;
+    cancel_task(GIT_SHOW_FILE_DIFF_COMMAND, window_label);
 }
 
 // tauri commandでウィンドウ生成する場合、asyncにしなければwindowsでデッドロックが起きる
 // (https://tauri.app/v1/guides/features/multiwindow/)
 #[command]
-async fn open_new_window(app_handle: AppHandle, hash: String, x: f64, y: f64) {
+async fn open_new_window(app_handle: AppHandle, window_label: String, hash: String, x: f64, y: f64) -> Result<(), String> {
     // タイムスタンプを使って一意のウィンドウ識別子を生成
     let start = SystemTime::now();
     let since_the_epoch = start.duration_since(UNIX_EPOCH).expect("Time went backwards");
@@ -1170,6 +1106,9 @@ async fn open_new_window(app_handle: AppHandle, hash: String, x: f64, y: f64) {
 
     let new_window_label = format!("new_window_{}", unique_id); // 一意の識別子を設定
 
+    // 開いた元のウィンドウと同じリポジトリを参照する
+    set_repository(&new_window_label, repository_path(&window_label)?);
+
     let new_window = tauri::WindowBuilder::new(
         &app_handle,
         &new_window_label,
@@ -1179,7 +1118,7 @@ async fn open_new_window(app_handle: AppHandle, hash: String, x: f64, y: f64) {
         // .decorations(false)
         // .position(x, y)
         .inner_size(800., 600.)
-        .additional_browser_args("--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection --disable-gpu --disable-local-storage --disable-background-networking --kiosk --disable-context-menu --single-process")
+        .additional_browser_args(&Settings::load(&get_app_config_dir()).window.additional_browser_args)
         .build()
         .unwrap();
 
@@ -1194,12 +1133,47 @@ async fn open_new_window(app_handle: AppHandle, hash: String, x: f64, y: f64) {
                 .unwrap();
         }
     });
+
+    Ok(())
 }
 
-#[derive(Serialize)]
-struct BranchInfo {
-    branch_name: String,
-    branch_state: BranchState,
+// 指定したリポジトリを新しいメインウィンドウで開く
+#[command]
+async fn open_repository_window(app_handle: AppHandle, path: String) -> Result<(), String> {
+    if !git_folder_exists(&path) {
+        return Err("Not a git repository.".into());
+    }
+
+    // タイムスタンプを使って一意のウィンドウ識別子を生成
+    let since_the_epoch = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards");
+    let new_window_label = format!("main_window_{}", since_the_epoch.as_millis());
+
+    record_opened_repository(&get_app_config_dir(), &path)?;
+
+    // ウィンドウのuseEffectより前にリポジトリを設定しておく
+    set_repository(&new_window_label, PathBuf::from(path));
+
+    let new_window = tauri::WindowBuilder::new(
+        &app_handle,
+        &new_window_label,
+        WindowUrl::App("index.html".into()),
+    )
+        .title("ZenGit")
+        .inner_size(1200., 800.)
+        .decorations(false)
+        .transparent(true)
+        .additional_browser_args(&Settings::load(&get_app_config_dir()).window.additional_browser_args)
+        .build()
+        .map_err(|e| {
+            remove_repository(&new_window_label);
+            e.to_string()
+        })?;
+
+    // ウィンドウに window-shadows の装飾を適用
+    #[cfg(any(windows, target_os = "macos"))]
+    set_shadow(new_window, true).unwrap();
+
+    Ok(())
 }
 
 const GIT_BRANCH_COMMAND: &str = "git_branch";
@@ -1207,59 +1181,25 @@ const GIT_BRANCH_COMMAND: &str = "git_branch";
 #[command]
 async fn git_branch(app_handle: AppHandle, window_label: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_BRANCH_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_BRANCH_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+
     const RESULT_LABEL: &str = "post-git-branch-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_BRANCH_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_BRANCH_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("branch")
-            .arg("-a");
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let res: Vec<BranchInfo> = o
-                                      .lines()
-                                      .filter(|line| !line.contains("remotes/origin/HEAD")) // HEADは除外
-                                      .map(|line| {
-                                          let mut state: BranchState = BranchState::Default;
-                                          if line.starts_with("* ") {
-                                              state = BranchState::Current;
-                                          }
-
-                                          let branch_name = line[2..].to_string();
-
-                                          if branch_name.starts_with("remotes/") {
-                                              state = BranchState::Remote;
-                                          }
-
-                                          BranchInfo {
-                                              branch_name,
-                                              branch_state: state,
-                                          }
-                                      })
-                                      .collect();
-
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &res,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             backend.branches(&repo_path),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
@@ -1267,7 +1207,7 @@ async fn git_branch(app_handle: AppHandle, window_label: String) -> Result<(), S
 
 #[command]
 async fn git_branch_cancel(window_label: String) {
-    cancel_task(GIT_BRANCH_COMMAND, window_label).await;
+    cancel_task(GIT_BRANCH_COMMAND, window_label);
 }
 
 const GIT_BRANCH_CREATE_COMMAND: &str = "git_branch_create";
@@ -1275,269 +1215,1316 @@ const GIT_BRANCH_CREATE_COMMAND: &str = "git_branch_create";
 #[command]
 async fn git_branch_create(app_handle: AppHandle, window_label: String, new_branch_name: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_BRANCH_CREATE_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_BRANCH_CREATE_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
+    let repo_path = repository_path(&window_label)?;
+
     const RESULT_LABEL: &str = "post-git-branch-create-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_BRANCH_CREATE_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_BRANCH_CREATE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             run_journaled(backend.as_ref(),
+                                           &repo_path,
+                                           OperationKind::BranchCreate,
+                                           &[],
+                                           backend.create_branch(&repo_path, new_branch_name)),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_branch_create_cancel(window_label: String) {
+    cancel_task(GIT_BRANCH_CREATE_COMMAND, window_label);
+}
+
+const GIT_BRANCH_DELETE_COMMAND: &str = "git_branch_delete";
+
+#[command]
+async fn git_branch_delete(app_handle: AppHandle, window_label: String, delete_branch_name: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_BRANCH_DELETE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-branch-delete-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_BRANCH_DELETE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             run_journaled(backend.as_ref(),
+                                           &repo_path,
+                                           OperationKind::BranchDelete,
+                                           &[],
+                                           backend.delete_branch(&repo_path, delete_branch_name)),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_branch_delete_cancel(window_label: String) {
+    cancel_task(GIT_BRANCH_DELETE_COMMAND, window_label);
+}
+
+const GIT_BRANCH_CHECKOUT_COMMAND: &str = "git_branch_checkout";
+
+#[command]
+async fn git_branch_checkout(app_handle: AppHandle, window_label: String, checkout_branch_name: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_BRANCH_CHECKOUT_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-branch-checkout-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_BRANCH_CHECKOUT_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let future = async {
+            let checkout = backend.checkout(&repo_path, checkout_branch_name);
+            run_journaled(backend.as_ref(), &repo_path, OperationKind::Checkout, &[], checkout).await.map(|output| {
+                debug!("Success (git_branch_checkout): {}", output);
+                "".to_string()
+            })
+        };
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             future,
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_branch_checkout_cancel(window_label: String) {
+    cancel_task(GIT_BRANCH_CHECKOUT_COMMAND, window_label);
+}
+
+const GIT_BRANCH_MERGE_COMMAND: &str = "git_branch_merge";
+
+// ブランチを現在のブランチにマージする (衝突した場合もエラーにせず、結果の種類で返す)
+#[command]
+async fn git_branch_merge(app_handle: AppHandle, window_label: String, merge_branch_name: String, options: MergeOptions) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_BRANCH_MERGE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-branch-merge-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_BRANCH_MERGE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             run_journaled(backend.as_ref(),
+                                           &repo_path,
+                                           OperationKind::Merge,
+                                           &[],
+                                           merge::merge_branch(backend.as_ref(), &repo_path, merge_branch_name, &options)),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_branch_merge_cancel(window_label: String) {
+    cancel_task(GIT_BRANCH_MERGE_COMMAND, window_label);
+}
+
+const GIT_CHECKOUT_HASH_COMMAND: &str = "git_checkout_hash";
+
+#[command]
+async fn git_checkout_hash(app_handle: AppHandle, window_label: String, commit_hash: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_CHECKOUT_HASH_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-checkout-hash-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_CHECKOUT_HASH_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             run_journaled(backend.as_ref(),
+                                           &repo_path,
+                                           OperationKind::Checkout,
+                                           &[],
+                                           backend.checkout(&repo_path, commit_hash)),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_checkout_hash_cancel(window_label: String) {
+    cancel_task(GIT_CHECKOUT_HASH_COMMAND, window_label);
+}
+
+const GIT_RESET_PREVIEW_COMMAND: &str = "git_reset_preview";
+
+// 現在のブランチをリセットする前に、辿れなくなるコミットと (--hardの場合は) 失われるファイルを調べる
+#[command]
+async fn git_reset_preview(app_handle: AppHandle, window_label: String, target: String, mode: ResetMode) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_RESET_PREVIEW_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-reset-preview-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_RESET_PREVIEW_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             reset::preview_reset(backend.as_ref(), &repo_path, &target, mode),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_reset_preview_cancel(window_label: String) {
+    cancel_task(GIT_RESET_PREVIEW_COMMAND, window_label);
+}
+
+const GIT_RESET_COMMAND: &str = "git_reset";
+
+// 現在のブランチを指定したコミットにリセットする (soft/mixed/hard)
+#[command]
+async fn git_reset(app_handle: AppHandle, window_label: String, target: String, mode: ResetMode) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_RESET_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-reset-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_RESET_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             reset::reset_journaled(backend.as_ref(), &JOURNAL, &repo_path, &target, mode),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_reset_cancel(window_label: String) {
+    cancel_task(GIT_RESET_COMMAND, window_label);
+}
+
+const GIT_STASH_LIST_COMMAND: &str = "git_stash_list";
+
+// stashの一覧 (新しい順)
+#[command]
+async fn git_stash_list(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_STASH_LIST_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+    let date_format = Settings::load(&get_app_config_dir()).log.date_format;
+
+    const RESULT_LABEL: &str = "post-git-stash-list-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_STASH_LIST_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             stash::list_stashes(backend.as_ref(), &repo_path, &date_format),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stash_list_cancel(window_label: String) {
+    cancel_task(GIT_STASH_LIST_COMMAND, window_label);
+}
+
+const GIT_STASH_PUSH_COMMAND: &str = "git_stash_push";
+
+// 変更をstashする (未追跡のファイルを含める・選択したファイルだけ・stagingした変更を残す)
+#[command]
+async fn git_stash_push(app_handle: AppHandle, window_label: String, options: StashOptions) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_STASH_PUSH_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-stash-push-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_STASH_PUSH_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             stash::create_stash(backend.as_ref(), &repo_path, &options),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stash_push_cancel(window_label: String) {
+    cancel_task(GIT_STASH_PUSH_COMMAND, window_label);
+}
+
+const GIT_STASH_APPLY_COMMAND: &str = "git_stash_apply";
+
+// stashを適用する (stashは残す、衝突した場合も結果を返す)
+#[command]
+async fn git_stash_apply(app_handle: AppHandle, window_label: String, hash: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_STASH_APPLY_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-stash-apply-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_STASH_APPLY_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             stash::apply_stash(backend.as_ref(), &repo_path, &hash, false),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stash_apply_cancel(window_label: String) {
+    cancel_task(GIT_STASH_APPLY_COMMAND, window_label);
+}
+
+const GIT_STASH_POP_COMMAND: &str = "git_stash_pop";
+
+// stashを適用して削除する (衝突した場合は削除しない)
+#[command]
+async fn git_stash_pop(app_handle: AppHandle, window_label: String, hash: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_STASH_POP_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-stash-pop-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_STASH_POP_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             stash::apply_stash(backend.as_ref(), &repo_path, &hash, true),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stash_pop_cancel(window_label: String) {
+    cancel_task(GIT_STASH_POP_COMMAND, window_label);
+}
+
+const GIT_STASH_DROP_COMMAND: &str = "git_stash_drop";
+
+// stashを削除する
+#[command]
+async fn git_stash_drop(app_handle: AppHandle, window_label: String, hash: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_STASH_DROP_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-stash-drop-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_STASH_DROP_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             stash::drop_stash(backend.as_ref(), &repo_path, &hash),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stash_drop_cancel(window_label: String) {
+    cancel_task(GIT_STASH_DROP_COMMAND, window_label);
+}
+
+const GIT_STASH_SHOW_FILES_COMMAND: &str = "git_stash_show_files";
+
+// stashに含まれるファイル (git_show_filesと同じ形式)
+#[command]
+async fn git_stash_show_files(app_handle: AppHandle, window_label: String, hash: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_STASH_SHOW_FILES_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-stash-show-files-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_STASH_SHOW_FILES_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             stash::stash_files(backend.as_ref(), &repo_path, &hash),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stash_show_files_cancel(window_label: String) {
+    cancel_task(GIT_STASH_SHOW_FILES_COMMAND, window_label);
+}
+
+const GIT_STASH_SHOW_FILE_DIFF_COMMAND: &str = "git_stash_show_file_diff";
+
+// stashに含まれるファイルの差分 (git_show_file_diffと同じ形式)
+#[command]
+async fn git_stash_show_file_diff(app_handle: AppHandle, window_label: String, hash: String, file: String, options: Option<DiffOptions>) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_STASH_SHOW_FILE_DIFF_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    let options = options.unwrap_or_else(|| Settings::load(&get_app_config_dir()).diff);
+
+    const RESULT_LABEL: &str = "post-git-stash-show-file-diff-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_STASH_SHOW_FILE_DIFF_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             stash::stash_file_diff(backend.as_ref(), &repo_path, &hash, file, &options),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_stash_show_file_diff_cancel(window_label: String) {
+    cancel_task(GIT_STASH_SHOW_FILE_DIFF_COMMAND, window_label);
+}
+
+const GIT_CONFLICTS_COMMAND: &str = "git_conflicts";
+
+// マージで衝突しているファイルの一覧
+#[command]
+async fn git_conflicts(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_CONFLICTS_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-conflicts-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_CONFLICTS_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             conflict::list_conflicts(backend.as_ref(), &repo_path),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_conflicts_cancel(window_label: String) {
+    cancel_task(GIT_CONFLICTS_COMMAND, window_label);
+}
+
+const GIT_CONFLICT_DETAIL_COMMAND: &str = "git_conflict_detail";
+
+// 衝突しているファイルを共通部分と衝突部分 (ours/base/theirs) に分けて取得する
+#[command]
+async fn git_conflict_detail(app_handle: AppHandle, window_label: String, path: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_CONFLICT_DETAIL_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-conflict-detail-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_CONFLICT_DETAIL_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             conflict::conflict_detail(backend.as_ref(), &repo_path, &path),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_conflict_detail_cancel(window_label: String) {
+    cancel_task(GIT_CONFLICT_DETAIL_COMMAND, window_label);
+}
+
+const GIT_RESOLVE_CONFLICT_COMMAND: &str = "git_resolve_conflict";
+
+// 衝突しているファイルをどちらかの内容で解消する
+#[command]
+async fn git_resolve_conflict(app_handle: AppHandle, window_label: String, path: String, side: ConflictSide) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_RESOLVE_CONFLICT_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-resolve-conflict-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_RESOLVE_CONFLICT_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let future = async {
+            conflict::resolve_with_side(backend.as_ref(), &repo_path, &path, side).await.map(|_| {
+                debug!("Success (git_resolve_conflict): {}", path);
+                "".to_string()
+            })
+        };
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             future,
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_resolve_conflict_cancel(window_label: String) {
+    cancel_task(GIT_RESOLVE_CONFLICT_COMMAND, window_label);
+}
+
+const GIT_RESOLVE_CONFLICT_CONTENT_COMMAND: &str = "git_resolve_conflict_content";
+
+// 衝突しているファイルを編集した内容で保存して解消する
+#[command]
+async fn git_resolve_conflict_content(app_handle: AppHandle, window_label: String, path: String, content: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_RESOLVE_CONFLICT_CONTENT_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-resolve-conflict-content-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_RESOLVE_CONFLICT_CONTENT_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let future = async {
+            conflict::resolve_with_content(backend.as_ref(), &repo_path, &path, &content).await.map(|_| {
+                debug!("Success (git_resolve_conflict_content): {}", path);
+                "".to_string()
+            })
+        };
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             future,
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_resolve_conflict_content_cancel(window_label: String) {
+    cancel_task(GIT_RESOLVE_CONFLICT_CONTENT_COMMAND, window_label);
+}
+
+const GIT_MARK_RESOLVED_COMMAND: &str = "git_mark_resolved";
+
+// 作業ツリーで編集したファイルを解消済みにする
+#[command]
+async fn git_mark_resolved(app_handle: AppHandle, window_label: String, files: Vec<String>) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_MARK_RESOLVED_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-mark-resolved-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_MARK_RESOLVED_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let future = async {
+            conflict::mark_resolved(backend.as_ref(), &repo_path, &files).await.map(|_| {
+                debug!("Success (git_mark_resolved): {:?}", files);
+                "".to_string()
+            })
+        };
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             future,
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_mark_resolved_cancel(window_label: String) {
+    cancel_task(GIT_MARK_RESOLVED_COMMAND, window_label);
+}
+
+const GIT_MERGE_CONTINUE_COMMAND: &str = "git_merge_continue";
+
+// 全ての衝突を解消した後にマージコミットを作成する
+#[command]
+async fn git_merge_continue(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_MERGE_CONTINUE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-merge-continue-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_MERGE_CONTINUE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("checkout")
-            .arg("-b")
-            .arg(&new_branch_name);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &o,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             run_journaled(backend.as_ref(),
+                                           &repo_path,
+                                           OperationKind::Merge,
+                                           &[],
+                                           conflict::continue_merge(backend.as_ref(), &repo_path)),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
 }
 
 #[command]
-async fn git_branch_create_cancel(window_label: String) {
-    cancel_task(GIT_BRANCH_CREATE_COMMAND, window_label).await;
+async fn git_merge_continue_cancel(window_label: String) {
+    cancel_task(GIT_MERGE_CONTINUE_COMMAND, window_label);
 }
 
-const GIT_BRANCH_DELETE_COMMAND: &str = "git_branch_delete";
+const GIT_MERGE_ABORT_COMMAND: &str = "git_merge_abort";
 
+// マージを中止して、マージ前の状態に戻す
 #[command]
-async fn git_branch_delete(app_handle: AppHandle, window_label: String, delete_branch_name: String) -> Result<(), String> {
+async fn git_merge_abort(app_handle: AppHandle, window_label: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_BRANCH_DELETE_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_MERGE_ABORT_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
-    const RESULT_LABEL: &str = "post-git-branch-delete-result";
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-merge-abort-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_BRANCH_DELETE_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_MERGE_ABORT_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("branch")
-            .arg("-d")
-            .arg(&delete_branch_name);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &o,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             conflict::abort_merge(backend.as_ref(), &repo_path),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
 }
 
 #[command]
-async fn git_branch_delete_cancel(window_label: String) {
-    cancel_task(GIT_BRANCH_DELETE_COMMAND, window_label).await;
+async fn git_merge_abort_cancel(window_label: String) {
+    cancel_task(GIT_MERGE_ABORT_COMMAND, window_label);
 }
 
-const GIT_BRANCH_CHECKOUT_COMMAND: &str = "git_branch_checkout";
+const GIT_REBASE_COMMAND: &str = "git_rebase";
 
+// 現在のブランチを指定したブランチまたはコミットの上に付け替える (衝突で止まった場合も状態を返す)
 #[command]
-async fn git_branch_checkout(app_handle: AppHandle, window_label: String, checkout_branch_name: String) -> Result<(), String> {
+async fn git_rebase(app_handle: AppHandle, window_label: String, target: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_BRANCH_CHECKOUT_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_REBASE_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
-    const RESULT_LABEL: &str = "post-git-branch-checkout-result";
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-rebase-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_BRANCH_CHECKOUT_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_REBASE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("checkout")
-            .arg(&checkout_branch_name);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  debug!("Success (git_branch_checkout): {}", o);
-
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &"".to_string(),
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             rebase::run_journaled(backend.as_ref(), &JOURNAL, &repo_path, rebase::rebase_onto(backend.as_ref(), &repo_path, target)),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
 }
 
 #[command]
-async fn git_branch_checkout_cancel(window_label: String) {
-    cancel_task(GIT_BRANCH_CHECKOUT_COMMAND, window_label).await;
+async fn git_rebase_cancel(window_label: String) {
+    cancel_task(GIT_REBASE_COMMAND, window_label);
 }
 
-const GIT_BRANCH_MERGE_COMMAND: &str = "git_branch_checkout";
+const GIT_REBASE_PLAN_COMMAND: &str = "git_rebase_plan";
 
+// base (含まない) より後のコミットを、対話的リベースのtodoとして取得する
 #[command]
-async fn git_branch_merge(app_handle: AppHandle, window_label: String, merge_branch_name: String) -> Result<(), String> {
+async fn git_rebase_plan(app_handle: AppHandle, window_label: String, base: String) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_BRANCH_MERGE_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_REBASE_PLAN_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
-    const RESULT_LABEL: &str = "post-git-branch-merge-result";
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-rebase-plan-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_BRANCH_MERGE_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_REBASE_PLAN_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("merge")
-            .arg(&merge_branch_name);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &o,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             interactive_rebase::plan_interactive_rebase(backend.as_ref(), &repo_path, &base),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
 }
 
 #[command]
-async fn git_branch_merge_cancel(window_label: String) {
-    cancel_task(GIT_BRANCH_MERGE_COMMAND, window_label).await;
+async fn git_rebase_plan_cancel(window_label: String) {
+    cancel_task(GIT_REBASE_PLAN_COMMAND, window_label);
 }
 
-const GIT_CHECKOUT_HASH_COMMAND: &str = "git_checkout_hash";
+const GIT_REBASE_INTERACTIVE_COMMAND: &str = "git_rebase_interactive";
 
+// 編集したtodoのとおりに対話的リベースを実行する (衝突で止まった場合も状態を返す)
 #[command]
-async fn git_checkout_hash(app_handle: AppHandle, window_label: String, commit_hash: String) -> Result<(), String> {
+async fn git_rebase_interactive(app_handle: AppHandle, window_label: String, base: String, entries: Vec<TodoEntry>) -> Result<(), String> {
     // 実行中かどうかをチェック
-    if is_running_command(GIT_CHECKOUT_HASH_COMMAND, window_label.clone()).await {
+    if is_running_command(GIT_REBASE_INTERACTIVE_COMMAND, window_label.clone()) {
         return Err("The command is running".to_string());
     }
 
-    const RESULT_LABEL: &str = "post-git-checkout-hash-result";
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-rebase-interactive-result";
 
     // CancellationTokenをクローンして非同期タスクに渡す
-    let task = create_task(GIT_CHECKOUT_HASH_COMMAND, window_label.clone()).await;
+    let task = create_task(GIT_REBASE_INTERACTIVE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
 
+    let backend = task.backend();
     tokio::spawn(async move {
-        let mut binding = Command::new("git");
-        let command = binding
-            .kill_on_drop(true)
-            .arg("checkout")
-            .arg(&commit_hash);
-        match run_git_command(app_handle,
-                              window_label.as_str(),
-                              task,
-                              command,
-                              RESULT_LABEL,
-                              |h, wl, o| {
-                                  let message = EmitMessage {
-                                      is_ok: true,
-                                      result: &o,
-                                  };
-                                  post_git_command_result(h, wl, &message, RESULT_LABEL);
-                              },
-        ).await {
-            Ok(output) => {}
-            Err(e) => {}
-        }
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             rebase::run_journaled(backend.as_ref(),
+                                                   &JOURNAL,
+                                                   &repo_path,
+                                                   interactive_rebase::run_interactive_rebase(backend.as_ref(), &repo_path, &base, &entries, &REBASE_EDITOR)),
+                             RESULT_LABEL,
+        ).await;
     });
 
     Ok(())
 }
 
 #[command]
-async fn git_checkout_hash_cancel(window_label: String) {
-    cancel_task(GIT_CHECKOUT_HASH_COMMAND, window_label).await;
+async fn git_rebase_interactive_cancel(window_label: String) {
+    cancel_task(GIT_REBASE_INTERACTIVE_COMMAND, window_label);
 }
 
+const GIT_REBASE_STATE_COMMAND: &str = "git_rebase_state";
+
+// リベース中かどうかと、止まっているコミット
 #[command]
-async fn is_on_branch(branch_name: String, branch_state: BranchState) -> Result<bool, String> {
-    // `git symbolic-ref HEAD` コマンドを実行してHEADがブランチかどうかを確認
-    let mut command = Command::new("git");
-    command.arg("show-ref")
-        .arg("--verify");
-    if branch_state == BranchState::Remote {
-        command.arg(format!("refs/{}", branch_name));
-    } else {
-        command.arg(format!("refs/heads/{}", branch_name));
+async fn git_rebase_state(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_REBASE_STATE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
     }
 
-    let output = command.set_creation_flags()
-        .output()
-        .await
-        .expect("Failed to execute git command");
+    let repo_path = repository_path(&window_label)?;
 
-    // コマンドの実行結果をチェック
-    if output.status.success() {
-        Ok(true)
-    } else {
-        // `git symbolic-ref HEAD` が失敗した場合、HEADはブランチではなくコミットハッシュを指している
-        Ok(false)
+    const RESULT_LABEL: &str = "post-git-rebase-state-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_REBASE_STATE_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             rebase::rebase_state(backend.as_ref(), &repo_path),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_rebase_state_cancel(window_label: String) {
+    cancel_task(GIT_REBASE_STATE_COMMAND, window_label);
+}
+
+const GIT_REBASE_CONTINUE_COMMAND: &str = "git_rebase_continue";
+
+// 衝突を解消した後にリベースを続ける
+#[command]
+async fn git_rebase_continue(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_REBASE_CONTINUE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-rebase-continue-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_REBASE_CONTINUE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             rebase::continue_rebase(backend.as_ref(), &repo_path, &REBASE_EDITOR),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_rebase_continue_cancel(window_label: String) {
+    cancel_task(GIT_REBASE_CONTINUE_COMMAND, window_label);
+}
+
+const GIT_REBASE_SKIP_COMMAND: &str = "git_rebase_skip";
+
+// 止まっているコミットを飛ばしてリベースを続ける
+#[command]
+async fn git_rebase_skip(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_REBASE_SKIP_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-rebase-skip-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_REBASE_SKIP_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             rebase::skip_rebase(backend.as_ref(), &repo_path),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_rebase_skip_cancel(window_label: String) {
+    cancel_task(GIT_REBASE_SKIP_COMMAND, window_label);
+}
+
+const GIT_REBASE_ABORT_COMMAND: &str = "git_rebase_abort";
+
+// リベースを中止して、元のブランチに戻す
+#[command]
+async fn git_rebase_abort(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_REBASE_ABORT_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-rebase-abort-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_REBASE_ABORT_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             rebase::abort_rebase(backend.as_ref(), &repo_path),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_rebase_abort_cancel(window_label: String) {
+    cancel_task(GIT_REBASE_ABORT_COMMAND, window_label);
+}
+
+const GIT_CHERRY_PICK_COMMAND: &str = "git_cherry_pick";
+
+// コミットを現在のブランチに適用する (衝突で止まった場合も結果を返す)
+#[command]
+async fn git_cherry_pick(app_handle: AppHandle, window_label: String, hashes: Vec<String>, options: PickOptions) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_CHERRY_PICK_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-cherry-pick-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_CHERRY_PICK_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             run_journaled(backend.as_ref(),
+                                           &repo_path,
+                                           OperationKind::CherryPick,
+                                           &[],
+                                           sequencer::apply_commits(backend.as_ref(), &repo_path, SequencerCommand::CherryPick, &hashes, &options)),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_cherry_pick_cancel(window_label: String) {
+    cancel_task(GIT_CHERRY_PICK_COMMAND, window_label);
+}
+
+const GIT_REVERT_COMMAND: &str = "git_revert";
+
+// コミットを打ち消すコミットを作成する (衝突で止まった場合も結果を返す)
+#[command]
+async fn git_revert(app_handle: AppHandle, window_label: String, hashes: Vec<String>, options: PickOptions) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_REVERT_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-revert-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_REVERT_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             run_journaled(backend.as_ref(),
+                                           &repo_path,
+                                           OperationKind::Revert,
+                                           &[],
+                                           sequencer::apply_commits(backend.as_ref(), &repo_path, SequencerCommand::Revert, &hashes, &options)),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_revert_cancel(window_label: String) {
+    cancel_task(GIT_REVERT_COMMAND, window_label);
+}
+
+const GIT_SEQUENCER_STATE_COMMAND: &str = "git_sequencer_state";
+
+// cherry-pick・revertの途中の状態を取得する
+#[command]
+async fn git_sequencer_state(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_SEQUENCER_STATE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-sequencer-state-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_SEQUENCER_STATE_COMMAND, window_label.clone(), &repo_path, LockMode::Read);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             sequencer::sequencer_state(backend.as_ref(), &repo_path),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_sequencer_state_cancel(window_label: String) {
+    cancel_task(GIT_SEQUENCER_STATE_COMMAND, window_label);
+}
+
+const GIT_SEQUENCER_CONTINUE_COMMAND: &str = "git_sequencer_continue";
+
+// 衝突を解消した後にcherry-pick・revertを続ける
+#[command]
+async fn git_sequencer_continue(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_SEQUENCER_CONTINUE_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-sequencer-continue-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_SEQUENCER_CONTINUE_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             sequencer::run_journaled(backend.as_ref(), &JOURNAL, &repo_path, sequencer::continue_sequence(backend.as_ref(), &repo_path)),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_sequencer_continue_cancel(window_label: String) {
+    cancel_task(GIT_SEQUENCER_CONTINUE_COMMAND, window_label);
+}
+
+const GIT_SEQUENCER_SKIP_COMMAND: &str = "git_sequencer_skip";
+
+// 止まっているコミットを飛ばしてcherry-pick・revertを続ける
+#[command]
+async fn git_sequencer_skip(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_SEQUENCER_SKIP_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-sequencer-skip-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_SEQUENCER_SKIP_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             sequencer::run_journaled(backend.as_ref(), &JOURNAL, &repo_path, sequencer::skip_sequence(backend.as_ref(), &repo_path)),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_sequencer_skip_cancel(window_label: String) {
+    cancel_task(GIT_SEQUENCER_SKIP_COMMAND, window_label);
+}
+
+const GIT_SEQUENCER_ABORT_COMMAND: &str = "git_sequencer_abort";
+
+// cherry-pick・revertを中止して、始める前の状態に戻す
+#[command]
+async fn git_sequencer_abort(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(GIT_SEQUENCER_ABORT_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
+    }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-git-sequencer-abort-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(GIT_SEQUENCER_ABORT_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             sequencer::abort_sequence(backend.as_ref(), &repo_path),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn git_sequencer_abort_cancel(window_label: String) {
+    cancel_task(GIT_SEQUENCER_ABORT_COMMAND, window_label);
+}
+
+const UNDO_LAST_OPERATION_COMMAND: &str = "undo_last_operation";
+
+// 最後に記録された操作を元に戻す (pushした後など、安全に戻せない場合は理由をエラーで返す)
+#[command]
+async fn undo_last_operation(app_handle: AppHandle, window_label: String) -> Result<(), String> {
+    // 実行中かどうかをチェック
+    if is_running_command(UNDO_LAST_OPERATION_COMMAND, window_label.clone()) {
+        return Err("The command is running".to_string());
     }
+
+    let repo_path = repository_path(&window_label)?;
+
+    const RESULT_LABEL: &str = "post-undo-last-operation-result";
+
+    // CancellationTokenをクローンして非同期タスクに渡す
+    let task = create_task(UNDO_LAST_OPERATION_COMMAND, window_label.clone(), &repo_path, LockMode::Write);
+
+    let backend = task.backend();
+    tokio::spawn(async move {
+        let _ = run_git_task(app_handle,
+                             window_label.as_str(),
+                             task,
+                             journal::undo_last_operation(backend.as_ref(), &JOURNAL, &repo_path),
+                             RESULT_LABEL,
+        ).await;
+    });
+
+    Ok(())
+}
+
+#[command]
+async fn undo_last_operation_cancel(window_label: String) {
+    cancel_task(UNDO_LAST_OPERATION_COMMAND, window_label);
+}
+
+#[command]
+async fn is_on_branch(window_label: String, branch_name: String, branch_state: BranchState) -> Result<bool, String> {
+    let repo_path = repository_path(&window_label)?;
+
+    // ブランチが存在しない場合、HEADはブランチではなくコミットハッシュを指している
+    Ok(GIT_BACKEND.branch_exists(&repo_path, branch_name, branch_state).await)
 }
 
 #[tokio::main]
 async fn main() {
+    // 対話的リベースのエディタとしてgitから起動された場合
+    if let Some(code) = interactive_rebase::run_editor_helper(env::args_os()) {
+        std::process::exit(code);
+    }
+
+    // サブコマンドが指定された場合はウィンドウを開かずに結果を出力して終了する
+    if let Some(args) = cli::parse(env::args_os()) {
+        let date_format = Settings::load(&get_app_config_dir()).log.date_format;
+        std::process::exit(cli::run(args, GIT_BACKEND.as_ref(), &date_format).await);
+    }
+
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             git_status,
             git_status_cancel,
             git_diff,
             git_diff_cancel,
+            git_stage,
+            git_stage_cancel,
+            git_unstage,
+            git_unstage_cancel,
+            git_stage_hunks,
+            git_stage_hunks_cancel,
+            git_unstage_hunks,
+            git_unstage_hunks_cancel,
             git_commit,
             git_commit_cancel,
             git_discard_changes,
@@ -1550,13 +2537,28 @@ async fn main() {
             git_pull_cancel,
             git_fetch,
             git_fetch_cancel,
+            list_jobs,
+            cancel_job,
+            query_audit_log,
+            git_clone,
+            git_clone_cancel,
+            select_clone_destination,
             get_pull_push_count,
             get_pull_push_count_cancel,
             select_git_folder,
+            select_repository_for_new_window,
             get_git_folder,
+            get_recent_repositories,
+            open_recent_repository,
+            forget_recent_repository,
+            pin_recent_repository,
+            rename_recent_repository,
+            get_settings,
+            update_settings,
             git_log,
             git_log_cancel,
             open_new_window,
+            open_repository_window,
             git_show,
             git_show_cancel,
             git_show_files,
@@ -1575,15 +2577,81 @@ async fn main() {
             git_branch_merge_cancel,
             git_checkout_hash,
             git_checkout_hash_cancel,
+            git_reset_preview,
+            git_reset_preview_cancel,
+            git_reset,
+            git_reset_cancel,
+            git_stash_list,
+            git_stash_list_cancel,
+            git_stash_push,
+            git_stash_push_cancel,
+            git_stash_apply,
+            git_stash_apply_cancel,
+            git_stash_pop,
+            git_stash_pop_cancel,
+            git_stash_drop,
+            git_stash_drop_cancel,
+            git_stash_show_files,
+            git_stash_show_files_cancel,
+            git_stash_show_file_diff,
+            git_stash_show_file_diff_cancel,
+            git_conflicts,
+            git_conflicts_cancel,
+            git_conflict_detail,
+            git_conflict_detail_cancel,
+            git_resolve_conflict,
+            git_resolve_conflict_cancel,
+            git_resolve_conflict_content,
+            git_resolve_conflict_content_cancel,
+            git_mark_resolved,
+            git_mark_resolved_cancel,
+            git_merge_continue,
+            git_merge_continue_cancel,
+            git_merge_abort,
+            git_merge_abort_cancel,
+            git_rebase,
+            git_rebase_cancel,
+            git_rebase_plan,
+            git_rebase_plan_cancel,
+            git_rebase_interactive,
+            git_rebase_interactive_cancel,
+            git_rebase_state,
+            git_rebase_state_cancel,
+            git_rebase_continue,
+            git_rebase_continue_cancel,
+            git_rebase_skip,
+            git_rebase_skip_cancel,
+            git_rebase_abort,
+            git_rebase_abort_cancel,
+            git_cherry_pick,
+            git_cherry_pick_cancel,
+            git_revert,
+            git_revert_cancel,
+            git_sequencer_state,
+            git_sequencer_state_cancel,
+            git_sequencer_continue,
+            git_sequencer_continue_cancel,
+            git_sequencer_skip,
+            git_sequencer_skip_cancel,
+            git_sequencer_abort,
+            git_sequencer_abort_cancel,
+            undo_last_operation,
+            undo_last_operation_cancel,
             is_on_branch,
         ])
         .setup(|app| {
+            // ジョブの開始・終了を全てのウィンドウに通知する
+            let app_handle = app.handle();
+            JOBS.set_listener(move |event, job| {
+                let _ = app_handle.emit_all(event, job);
+            });
+
             // アプリ起動時のカレントディレクトリを確保しておく
             let init_current_dir: PathBuf = env::current_dir().expect("Failed to get current directory");
 
-            // 一旦前回開いていたフォルダをカレントに
-            if let Ok(path) = load_folder_path() {
-                set_current_dir(&path);
+            // 一旦前回開いていたフォルダをメインウィンドウのリポジトリに
+            if let Some(path) = last_opened_repository(&get_app_config_dir()) {
+                set_repository("main", PathBuf::from(path));
             }
 
             // "main" ウィンドウの取得
@@ -1607,7 +2675,8 @@ async fn main() {
                 
                 if git_folder_exists(&path) {
                     // 指定されたフォルダを開く
-                    set_current_dir(&path);
+                    let _ = record_opened_repository(&get_app_config_dir(), &path);
+                    set_repository("main", PathBuf::from(path));
                 } else {
                     let err = "fatal: not a git repository.";
                     eprintln!("{}", err); // エラーメッセージをCLIに出力
@@ -1617,6 +2686,12 @@ async fn main() {
 
             Ok(())
         })
+        .on_window_event(|event| {
+            // 閉じたウィンドウのリポジトリの情報を破棄する
+            if let WindowEvent::Destroyed = event.event() {
+                remove_repository(event.window().label());
+            }
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,154 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use crate::backend::{GitBackend, GitRequest};
+use crate::conflict::list_conflicts;
+
+// マージの方法
+#[derive(Debug, Clone, Copy, Default, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MergeMode {
+    // 可能ならfast-forward、できなければマージコミットを作成する
+    #[default]
+    Default = 0,
+    // 常にマージコミットを作成する (--no-ff)
+    NoFastForward = 1,
+    // fast-forwardできない場合は失敗させる (--ff-only)
+    FastForwardOnly = 2,
+    // 変更をまとめてstagingする (--squash)
+    Squash = 3,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MergeOptions {
+    pub mode: MergeMode,
+    // マージコミットのメッセージ (空の場合はgitが用意したもの)
+    // Squashの場合は、指定されていればまとめた変更をこのメッセージでコミットする
+    pub message: String,
+}
+
+impl MergeOptions {
+    // `git merge`に付ける引数
+    pub fn args(&self) -> Vec<String> {
+        let mut args: Vec<String> = match self.mode {
+            MergeMode::Default => vec![],
+            MergeMode::NoFastForward => vec!["--no-ff".to_string()],
+            MergeMode::FastForwardOnly => vec!["--ff-only".to_string()],
+            MergeMode::Squash => vec!["--squash".to_string()],
+        };
+        // エディタが開かないようにする (Squashはコミットしないので不要)
+        if self.mode != MergeMode::Squash {
+            if self.message.is_empty() {
+                args.push("--no-edit".to_string());
+            } else {
+                args.push("-m".to_string());
+                args.push(self.message.clone());
+            }
+        }
+        args
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MergeOutcomeKind {
+    UpToDate = 0,
+    FastForward = 1,
+    MergeCommit = 2,
+    // Squashで変更をstagingした (メッセージを指定した場合はコミットまで行う)
+    Squashed = 3,
+    // 衝突したファイルがあり、マージ中の状態になっている
+    Conflicts = 4,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MergeOutcome {
+    pub kind: MergeOutcomeKind,
+    // マージ後のHEAD (初回コミット前のブランチで何もなかった場合はNone)
+    pub head: Option<String>,
+    // 衝突したファイル
+    pub conflicts: Vec<String>,
+}
+
+async fn head_hash(backend: &dyn GitBackend, repo_path: &Path) -> Option<String> {
+    backend.exec(repo_path, GitRequest::new(["rev-parse", "-q", "--verify", "HEAD"])).await.ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+// ブランチを現在のブランチにマージし、結果の種類を返す
+// 衝突した場合はエラーにせず、マージ中のまま衝突したファイルを返す
+pub async fn merge_branch(backend: &dyn GitBackend,
+                          repo_path: &Path,
+                          branch_name: String,
+                          options: &MergeOptions) -> Result<MergeOutcome, String> {
+    // 解消していない衝突があるとマージの衝突と区別できないので先に止める
+    if !list_conflicts(backend, repo_path).await?.is_empty() {
+        return Err("Resolve the current conflicts before merging.".to_string());
+    }
+
+    // マージするコミット (fast-forwardかどうかは、マージ後のHEADと比べて判断する)
+    let request = GitRequest::new(["rev-parse", "--verify", "--end-of-options"]).arg(format!("{}^{{commit}}", branch_name));
+    let tip = backend.exec(repo_path, request).await?.trim().to_string();
+
+    let before = head_hash(backend, repo_path).await;
+    let result = backend.merge(repo_path, branch_name.clone(), options).await;
+    if let Err(e) = result {
+        let conflicts: Vec<String> = list_conflicts(backend, repo_path).await?.into_iter().map(|v| v.path).collect();
+        if conflicts.is_empty() {
+            return Err(e);
+        }
+        return Ok(MergeOutcome { kind: MergeOutcomeKind::Conflicts, head: before, conflicts });
+    }
+
+    let kind = if options.mode == MergeMode::Squash {
+        // stagingされた変更がなければ取り込むものがなかった
+        let staged = backend.exec(repo_path, GitRequest::new(["diff", "--cached", "--quiet"])).await.is_err();
+        if staged {
+            if !options.message.is_empty() {
+                backend.exec(repo_path, GitRequest::new(["commit", "-q", "-m"]).arg(&options.message)).await?;
+            }
+            MergeOutcomeKind::Squashed
+        } else {
+            MergeOutcomeKind::UpToDate
+        }
+    } else {
+        let after = head_hash(backend, repo_path).await;
+        if after == before {
+            MergeOutcomeKind::UpToDate
+        } else if after.as_deref() == Some(tip.as_str()) {
+            // マージしたコミット自体がマージコミットの場合もあるので、親の数ではなくHEADの移動先で判断する
+            MergeOutcomeKind::FastForward
+        } else {
+            // マージ前のHEADとマージしたコミットを親に持つコミットを作成した
+            let parents = backend.exec(repo_path, GitRequest::new(["rev-list", "--parents", "-n", "1", "HEAD"])).await?;
+            let parents: Vec<&str> = parents.split_whitespace().skip(1).collect();
+            if before.as_deref().is_some_and(|before| parents == [before, tip.as_str()]) {
+                MergeOutcomeKind::MergeCommit
+            } else {
+                return Err(format!("Unexpected result of merging {}.", branch_name));
+            }
+        }
+    };
+
+    Ok(MergeOutcome { kind, head: head_hash(backend, repo_path).await, conflicts: vec![] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(mode: MergeMode, message: &str) -> MergeOptions {
+        MergeOptions { mode, message: message.to_string() }
+    }
+
+    #[test]
+    fn merge_args() {
+        assert_eq!(options(MergeMode::Default, "").args(), vec!["--no-edit"]);
+        assert_eq!(options(MergeMode::NoFastForward, "Merge feature").args(), vec!["--no-ff", "-m", "Merge feature"]);
+        assert_eq!(options(MergeMode::FastForwardOnly, "").args(), vec!["--ff-only", "--no-edit"]);
+        // Squashのメッセージはコミットするときに使う
+        assert_eq!(options(MergeMode::Squash, "Squashed").args(), vec!["--squash"]);
+    }
+}
@@ -0,0 +1,400 @@
+use serde::{Deserialize, Serialize};
+use crate::diff::{DiffFile, DiffLineKind};
+
+fn is_new_file(file: &DiffFile) -> bool {
+    file.header.iter().any(|v| v.starts_with("new file mode") || v == "--- /dev/null")
+}
+
+fn is_deleted_file(file: &DiffFile) -> bool {
+    file.header.iter().any(|v| v.starts_with("deleted file mode") || v == "+++ /dev/null")
+}
+
+// フロントエンドで選択されたhunkと行
+// line_indicesがNoneの場合はhunk全体を対象とする
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HunkSelection {
+    pub hunk_index: usize,
+    pub line_indices: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchDirection {
+    // `git diff`の結果からindexに適用する (`git apply --cached`)
+    Stage,
+    // `git diff --cached`の結果をindexから取り除く (`git apply --cached -R`)
+    Unstage,
+}
+
+// diffの出力の1ファイル分を、内容を変換せずに行ごとに分けたもの
+// (DiffFileの内容は表示用に文字列へ変換されているので、パッチには使わない)
+struct RawFile<'a> {
+    header: Vec<&'a [u8]>,
+    hunks: Vec<RawHunk<'a>>,
+}
+
+struct RawHunk<'a> {
+    // "@@ -1,2 +1,3 @@"の後ろ (関数名など)
+    section: &'a [u8],
+    // 先頭の記号を含む行と、直後の"\ No newline at end of file"の行
+    lines: Vec<(&'a [u8], Option<&'a [u8]>)>,
+}
+
+// 改行(LF)のみで区切り、CRは行の内容として残す
+fn split_raw_file(raw: &[u8]) -> RawFile<'_> {
+    let mut lines: Vec<&[u8]> = raw.split(|v| *v == b'\n').collect();
+    if lines.last().is_some_and(|v| v.is_empty()) {
+        lines.pop();
+    }
+
+    let mut res = RawFile { header: vec![], hunks: vec![] };
+    for (index, line) in lines.into_iter().enumerate() {
+        if index > 0 && line.starts_with(b"diff ") {
+            break;
+        }
+        if line.starts_with(b"@@") {
+            let section = line[2..].windows(3)
+                .position(|v| v == b" @@")
+                .map_or(&b""[..], |v| &line[v + 5..]);
+            res.hunks.push(RawHunk { section, lines: vec![] });
+            continue;
+        }
+        match res.hunks.last_mut() {
+            None => res.header.push(line),
+            Some(hunk) if line.starts_with(b"\\") => {
+                if let Some(last) = hunk.lines.last_mut() {
+                    last.1 = Some(line);
+                }
+            }
+            Some(hunk) if line.is_empty() || matches!(line[0], b' ' | b'+' | b'-') => hunk.lines.push((line, None)),
+            Some(_) => {}
+        }
+    }
+    res
+}
+
+fn raw_line_kind(line: &[u8]) -> DiffLineKind {
+    match line.first() {
+        Some(b'+') => DiffLineKind::Added,
+        Some(b'-') => DiffLineKind::Removed,
+        _ => DiffLineKind::Context,
+    }
+}
+
+// 表示したdiffと同じhunk/行が出力に含まれているか (選択された番号が同じ行を指すか)
+fn matches_raw_file(file: &DiffFile, raw_file: &RawFile) -> bool {
+    file.hunks.len() == raw_file.hunks.len() &&
+        file.hunks.iter().zip(&raw_file.hunks).all(|(hunk, raw_hunk)| {
+            hunk.lines.len() == raw_hunk.lines.len() &&
+                hunk.lines.iter().zip(&raw_hunk.lines).all(|(line, (raw_line, _))| line.kind == raw_line_kind(raw_line))
+        })
+}
+
+// 選択された行のみを含むパッチを作成する
+// rawはfileを解析したdiffの出力で、パッチの各行は改行コードや文字コードを変えずにそこから作る
+// 戻り値がNoneの場合は適用する変更がない
+pub fn build_partial_patch(file: &DiffFile,
+                           raw: &[u8],
+                           selections: &[HunkSelection],
+                           direction: PatchDirection) -> Result<Option<Vec<u8>>, String> {
+    if file.is_binary {
+        return Ok(None);
+    }
+    let raw_file = split_raw_file(raw);
+    if file.is_combined || !matches_raw_file(file, &raw_file) {
+        return Err("The selected lines cannot be rebuilt from the diff.".to_string());
+    }
+
+    let mut body: Vec<u8> = vec![];
+    let mut offset: i64 = 0;
+    let mut is_partial = false;
+
+    for (hunk_index, (hunk, raw_hunk)) in file.hunks.iter().zip(&raw_file.hunks).enumerate() {
+        let selection = selections.iter().find(|v| v.hunk_index == hunk_index);
+        let is_selected = |line_index: usize| -> bool {
+            match selection {
+                Some(HunkSelection { line_indices: Some(indices), .. }) => indices.contains(&line_index),
+                Some(HunkSelection { line_indices: None, .. }) => true,
+                None => false,
+            }
+        };
+
+        let mut lines: Vec<u8> = vec![];
+        let mut old_count = 0;
+        let mut new_count = 0;
+        let mut has_change = false;
+
+        for (line_index, (line, (raw_line, no_newline_marker))) in hunk.lines.iter().zip(&raw_hunk.lines).enumerate() {
+            let selected = is_selected(line_index);
+            if !selected && line.kind != DiffLineKind::Context {
+                is_partial = true;
+            }
+
+            // 選択されていない変更行は、適用先(index)にある側ならコンテキストに、ない側なら削除する
+            let kind = match (line.kind, selected, direction) {
+                (DiffLineKind::Context, _, _) => Some(DiffLineKind::Context),
+                (kind, true, _) => Some(kind),
+                (DiffLineKind::Removed, false, PatchDirection::Stage) => Some(DiffLineKind::Context),
+                (DiffLineKind::Added, false, PatchDirection::Stage) => None,
+                (DiffLineKind::Added, false, PatchDirection::Unstage) => Some(DiffLineKind::Context),
+                (DiffLineKind::Removed, false, PatchDirection::Unstage) => None,
+            };
+
+            let prefix = match kind {
+                Some(DiffLineKind::Context) => {
+                    old_count += 1;
+                    new_count += 1;
+                    b' '
+                }
+                Some(DiffLineKind::Added) => {
+                    new_count += 1;
+                    has_change = true;
+                    b'+'
+                }
+                Some(DiffLineKind::Removed) => {
+                    old_count += 1;
+                    has_change = true;
+                    b'-'
+                }
+                None => continue,
+            };
+
+            lines.push(prefix);
+            lines.extend_from_slice(raw_line.get(1..).unwrap_or_default());
+            lines.push(b'\n');
+            if let Some(marker) = no_newline_marker {
+                lines.extend_from_slice(marker);
+                lines.push(b'\n');
+            }
+        }
+
+        if !has_change {
+            continue;
+        }
+
+        // 手前のhunkで増減した行数分、適用先でない側の開始行をずらす
+        let (old_start, new_start) = match direction {
+            PatchDirection::Stage => (hunk.old_start as i64, hunk.old_start as i64 + offset),
+            PatchDirection::Unstage => (hunk.new_start as i64 - offset, hunk.new_start as i64),
+        };
+        offset += new_count as i64 - old_count as i64;
+
+        // 行数が0でない側の開始行は1以上になる
+        let old_start = if old_count > 0 { old_start.max(1) } else { old_start.max(0) };
+        let new_start = if new_count > 0 { new_start.max(1) } else { new_start.max(0) };
+
+        body.extend_from_slice(format!("@@ -{},{} +{},{} @@", old_start, old_count, new_start, new_count).as_bytes());
+        body.extend_from_slice(raw_hunk.section);
+        body.push(b'\n');
+        body.extend_from_slice(&lines);
+    }
+
+    if body.is_empty() {
+        return Ok(None);
+    }
+
+    // 一部のみ適用する場合、適用後もファイルが残るので削除/新規追加のヘッダーを通常の変更に書き換える
+    let needs_modify_header = is_partial && match direction {
+        PatchDirection::Stage => is_deleted_file(file),
+        PatchDirection::Unstage => is_new_file(file),
+    };
+
+    let mut res: Vec<u8> = vec![];
+    for line in &raw_file.header {
+        if needs_modify_header {
+            if line.starts_with(b"new file mode") || line.starts_with(b"deleted file mode") {
+                continue;
+            }
+            if *line == b"--- /dev/null" || *line == b"+++ /dev/null" {
+                res.extend_from_slice(&modified_path_line(&raw_file.header, line));
+                res.push(b'\n');
+                continue;
+            }
+        }
+        res.extend_from_slice(line);
+        res.push(b'\n');
+    }
+    res.extend_from_slice(&body);
+
+    Ok(Some(res))
+}
+
+// "/dev/null"を反対側のパスに置き換えた行を作る
+fn modified_path_line(header: &[&[u8]], line: &[u8]) -> Vec<u8> {
+    let (prefix, other_prefix, side): (&[u8], &[u8], &[u8]) = if line.starts_with(b"---") {
+        (b"--- ", b"+++ ", b"a/")
+    } else {
+        (b"+++ ", b"--- ", b"b/")
+    };
+
+    let other_path = header.iter()
+        .find_map(|v| v.strip_prefix(other_prefix))
+        .unwrap_or_default();
+    // 特殊な文字を含むパスは"b/\346\227\245.txt"のようにクォートされるので、クォートの内側を置き換える
+    let (quote, other_path): (&[u8], &[u8]) = match other_path.strip_prefix(b"\"") {
+        Some(v) => (b"\"", v),
+        None => (b"", other_path),
+    };
+    let path = other_path.iter()
+        .position(|v| *v == b'/')
+        .map_or(other_path, |v| &other_path[v + 1..]);
+
+    [prefix, quote, side, path].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    fn build(raw: &str, selections: &[HunkSelection], direction: PatchDirection) -> Option<String> {
+        let file = parse_diff(raw).remove(0);
+        build_partial_patch(&file, raw.as_bytes(), selections, direction).unwrap()
+            .map(|v| String::from_utf8(v).unwrap())
+    }
+
+    fn hunk(hunk_index: usize, line_indices: Option<Vec<usize>>) -> HunkSelection {
+        HunkSelection { hunk_index, line_indices }
+    }
+
+    const TWO_HUNKS_DIFF: &str = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,3 @@ first
+ a
++x
+ b
+@@ -8,3 +9,3 @@ second
+ g
+-h
++H
+ i
+";
+
+    #[test]
+    fn later_hunks_are_shifted_by_applied_lines() {
+        let patch = build(TWO_HUNKS_DIFF, &[hunk(0, None), hunk(1, None)], PatchDirection::Stage).unwrap();
+        assert!(patch.contains("@@ -1,2 +1,3 @@ first\n a\n+x\n b\n@@ -8,3 +9,3 @@ second\n"), "{}", patch);
+
+        // 手前のhunkを適用しない場合は、index上の行番号のまま
+        let patch = build(TWO_HUNKS_DIFF, &[hunk(1, None)], PatchDirection::Stage).unwrap();
+        assert!(!patch.contains("first"));
+        assert!(patch.ends_with("@@ -8,3 +8,3 @@ second\n g\n-h\n+H\n i\n"), "{}", patch);
+    }
+
+    #[test]
+    fn unstage_keeps_index_side_line_numbers() {
+        // `git diff --cached`の結果なので、変更後の側がindexの内容
+        let patch = build(TWO_HUNKS_DIFF, &[hunk(1, None)], PatchDirection::Unstage).unwrap();
+        assert!(patch.ends_with("@@ -9,3 +9,3 @@ second\n g\n-h\n+H\n i\n"), "{}", patch);
+
+        // 選択されていない削除行はindexにないので含めない
+        let patch = build(TWO_HUNKS_DIFF, &[hunk(1, Some(vec![2]))], PatchDirection::Unstage).unwrap();
+        assert!(patch.ends_with("@@ -9,2 +9,3 @@ second\n g\n+H\n i\n"), "{}", patch);
+    }
+
+    #[test]
+    fn nothing_selected_returns_none() {
+        assert_eq!(build(TWO_HUNKS_DIFF, &[], PatchDirection::Stage), None);
+        assert_eq!(build(TWO_HUNKS_DIFF, &[hunk(1, Some(vec![0]))], PatchDirection::Stage), None);
+    }
+
+    #[test]
+    fn partially_unstaged_new_file_becomes_modification() {
+        let raw = "\
+diff --git \"a/\\346\\226\\260.txt\" \"b/\\346\\226\\260.txt\"
+new file mode 100644
+index 0000000..3bd1f0e
+--- /dev/null
++++ \"b/\\346\\226\\260.txt\"
+@@ -0,0 +1,2 @@
++foo
++bar
+";
+        let patch = build(raw, &[hunk(0, Some(vec![1]))], PatchDirection::Unstage).unwrap();
+        assert_eq!(patch, "\
+diff --git \"a/\\346\\226\\260.txt\" \"b/\\346\\226\\260.txt\"
+index 0000000..3bd1f0e
+--- \"a/\\346\\226\\260.txt\"
++++ \"b/\\346\\226\\260.txt\"
+@@ -1,1 +1,2 @@
+ foo
++bar
+");
+
+        // 全体を取り除く場合は新規追加のまま
+        let patch = build(raw, &[hunk(0, None)], PatchDirection::Unstage).unwrap();
+        assert!(patch.contains("new file mode 100644\n"));
+        assert!(patch.contains("--- /dev/null\n"));
+    }
+
+    #[test]
+    fn partially_staged_deleted_file_becomes_modification() {
+        let raw = "\
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+index 3bd1f0e..0000000
+--- a/old.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-foo
+-bar
+";
+        let patch = build(raw, &[hunk(0, Some(vec![0]))], PatchDirection::Stage).unwrap();
+        assert_eq!(patch, "\
+diff --git a/old.txt b/old.txt
+index 3bd1f0e..0000000
+--- a/old.txt
++++ b/old.txt
+@@ -1,2 +1,1 @@
+-foo
+ bar
+");
+    }
+
+    #[test]
+    fn no_newline_marker_follows_its_line() {
+        let raw = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,2 @@
+ a
+-b
+\\ No newline at end of file
++c
+\\ No newline at end of file
+";
+        let patch = build(raw, &[hunk(0, Some(vec![1]))], PatchDirection::Stage).unwrap();
+        assert!(patch.ends_with("@@ -1,2 +1,1 @@\n a\n-b\n\\ No newline at end of file\n"), "{}", patch);
+
+        let patch = build(raw, &[hunk(0, None)], PatchDirection::Stage).unwrap();
+        assert!(patch.ends_with("-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n"), "{}", patch);
+    }
+
+    #[test]
+    fn raw_bytes_are_copied_as_is() {
+        let raw: &[u8] = b"\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,2 @@
+-\x82\xa0\r
++\x82\xa2\r
+ end\r
+";
+        let file = parse_diff(&String::from_utf8_lossy(raw)).remove(0);
+        let patch = build_partial_patch(&file, raw, &[hunk(0, None)], PatchDirection::Stage).unwrap().unwrap();
+        assert!(patch.ends_with(b"-\x82\xa0\r\n+\x82\xa2\r\n end\r\n"));
+    }
+
+    #[test]
+    fn diff_that_does_not_match_the_parsed_file_is_refused() {
+        let file = parse_diff(TWO_HUNKS_DIFF).remove(0);
+        let other = TWO_HUNKS_DIFF.replace(" i\n", "");
+        assert!(build_partial_patch(&file, other.as_bytes(), &[hunk(0, None)], PatchDirection::Stage).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use serde::Serialize;
+
+// `--progress`付きで実行したgitが標準エラー出力に出す進捗
+// 例: "Receiving objects:  45% (450/1000), 1.20 MiB | 2.40 MiB/s"
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct GitProgress {
+    // "Receiving objects" など
+    pub phase: String,
+    // リモート側の処理 ("remote: "が付いている行)
+    pub is_remote: bool,
+    pub percent: Option<u8>,
+    pub current: Option<u64>,
+    pub total: Option<u64>,
+    // 転送済みのサイズ ("1.20 MiB")
+    pub transferred: Option<String>,
+    // 転送速度 ("2.40 MiB/s")
+    pub rate: Option<String>,
+    // この段階が完了した
+    pub done: bool,
+}
+
+// 進捗を受け取るコールバック
+pub type ProgressCallback<'a> = &'a (dyn Fn(GitProgress) + Send + Sync);
+
+// 進捗の1行を解析する (進捗でない行の場合はNone)
+pub fn parse_progress_line(line: &str) -> Option<GitProgress> {
+    let line = line.trim();
+    let (is_remote, line) = match line.strip_prefix("remote:") {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, line),
+    };
+
+    let (phase, rest) = line.split_once(": ")?;
+    // "error: ..." や "hint: ..." などを除外するため、数字で始まるものだけを進捗とする
+    let rest = rest.trim();
+    if phase.is_empty() || !rest.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut res = GitProgress {
+        phase: phase.to_string(),
+        is_remote,
+        ..Default::default()
+    };
+
+    let rest = match rest.strip_suffix(", done.") {
+        Some(rest) => {
+            res.done = true;
+            rest
+        }
+        None => rest,
+    };
+
+    let mut parts = rest.split(", ");
+
+    // "45% (450/1000)" または件数のみの "1234"
+    let counts = parts.next().unwrap_or("");
+    match counts.split_once('%') {
+        Some((percent, rest)) => {
+            res.percent = Some(percent.trim().parse().ok()?);
+            if let Some((current, total)) = rest.trim().trim_start_matches('(').trim_end_matches(')').split_once('/') {
+                res.current = current.parse().ok();
+                res.total = total.parse().ok();
+            }
+        }
+        None => res.current = Some(counts.trim().parse().ok()?),
+    }
+
+    // "1.20 MiB | 2.40 MiB/s"
+    if let Some(transfer) = parts.next() {
+        match transfer.split_once(" | ") {
+            Some((transferred, rate)) => {
+                res.transferred = Some(transferred.trim().to_string());
+                res.rate = Some(rate.trim().to_string());
+            }
+            None => res.transferred = Some(transfer.trim().to_string()),
+        }
+    }
+
+    Some(res)
+}
+
+// 標準エラー出力を行ごとに区切る
+// 進捗は同じ行を`\r`で上書きしながら出力されるので、`\r`と`\n`のどちらでも区切る
+#[derive(Debug, Default)]
+pub struct ProgressLineSplitter {
+    buffer: Vec<u8>,
+}
+
+impl ProgressLineSplitter {
+    // 読み込んだ分を追加し、区切りまで揃った行を返す
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = vec![];
+        for &b in bytes {
+            if b == b'\r' || b == b'\n' {
+                if !self.buffer.is_empty() {
+                    lines.push(String::from_utf8_lossy(&self.buffer).to_string());
+                    self.buffer.clear();
+                }
+            } else {
+                self.buffer.push(b);
+            }
+        }
+        lines
+    }
+
+    // 最後の区切りのない行
+    pub fn finish(&mut self) -> Option<String> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&self.buffer).to_string();
+        self.buffer.clear();
+        Some(line)
+    }
+}
+
+// cloneするURLから作成するフォルダ名を決める (`git clone`と同じく末尾の".git"を除く)
+pub fn clone_directory_name(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url).trim_end_matches('/');
+    // "git@github.com:user/repo" の形式も扱う
+    let name = url.rsplit(['/', ':', '\\']).next()?;
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receiving_objects_with_rate() {
+        let progress = parse_progress_line("Receiving objects:  45% (450/1000), 1.20 MiB | 2.40 MiB/s").unwrap();
+        assert_eq!(progress, GitProgress {
+            phase: "Receiving objects".to_string(),
+            is_remote: false,
+            percent: Some(45),
+            current: Some(450),
+            total: Some(1000),
+            transferred: Some("1.20 MiB".to_string()),
+            rate: Some("2.40 MiB/s".to_string()),
+            done: false,
+        });
+    }
+
+    #[test]
+    fn remote_phase_done() {
+        let progress = parse_progress_line("remote: Compressing objects: 100% (2/2), done.").unwrap();
+        assert_eq!(progress.phase, "Compressing objects");
+        assert!(progress.is_remote);
+        assert!(progress.done);
+        assert_eq!((progress.percent, progress.current, progress.total), (Some(100), Some(2), Some(2)));
+    }
+
+    #[test]
+    fn count_only_phase() {
+        let progress = parse_progress_line("remote: Enumerating objects: 5, done.").unwrap();
+        assert_eq!(progress.phase, "Enumerating objects");
+        assert_eq!((progress.percent, progress.current, progress.total), (None, Some(5), None));
+        assert!(progress.done);
+    }
+
+    #[test]
+    fn writing_objects_done_with_rate() {
+        let progress = parse_progress_line("Writing objects: 100% (3/3), 250 bytes | 250.00 KiB/s, done.").unwrap();
+        assert_eq!(progress.transferred.as_deref(), Some("250 bytes"));
+        assert_eq!(progress.rate.as_deref(), Some("250.00 KiB/s"));
+        assert!(progress.done);
+    }
+
+    #[test]
+    fn other_lines_are_not_progress() {
+        assert_eq!(parse_progress_line("To github.com:user/repo.git"), None);
+        assert_eq!(parse_progress_line("   abc1234..def5678  main -> main"), None);
+        assert_eq!(parse_progress_line("error: failed to push some refs"), None);
+        assert_eq!(parse_progress_line("remote: Resolving deltas: abc"), None);
+        assert_eq!(parse_progress_line("Cloning into 'repo'..."), None);
+    }
+
+    #[test]
+    fn splitter_splits_on_carriage_return() {
+        let mut splitter = ProgressLineSplitter::default();
+        assert_eq!(splitter.push(b"Receiving objects:  10% (1/10)\rReceiving"), vec!["Receiving objects:  10% (1/10)"]);
+        assert_eq!(splitter.push(b" objects: 100% (10/10), done.\r\nTo remote"), vec!["Receiving objects: 100% (10/10), done."]);
+        assert_eq!(splitter.finish(), Some("To remote".to_string()));
+        assert_eq!(splitter.finish(), None);
+    }
+
+    #[test]
+    fn directory_name_from_url() {
+        assert_eq!(clone_directory_name("https://github.com/user/repo.git").as_deref(), Some("repo"));
+        assert_eq!(clone_directory_name("https://github.com/user/repo/").as_deref(), Some("repo"));
+        assert_eq!(clone_directory_name("git@github.com:user/repo.git").as_deref(), Some("repo"));
+        assert_eq!(clone_directory_name("host:repo").as_deref(), Some("repo"));
+        assert_eq!(clone_directory_name("/path/to/repo/.git").as_deref(), Some("repo"));
+        assert_eq!(clone_directory_name(""), None);
+    }
+}
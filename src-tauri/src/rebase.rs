@@ -0,0 +1,135 @@
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::backend::{GitBackend, GitRequest};
+use crate::conflict::list_conflicts;
+use crate::interactive_rebase::RebaseEditor;
+use crate::journal::{run_journaled_if, OperationJournal, OperationKind};
+
+// リベース中の状態 (.git/rebase-merge または .git/rebase-apply の内容)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RebaseState {
+    pub in_progress: bool,
+    // リベースしているブランチ ("main"、ブランチでない場合はNone)
+    pub branch_name: Option<String>,
+    // 移動先のコミット
+    pub onto: Option<String>,
+    // 止まっているコミット (衝突した、または編集のために止めたもの)
+    pub stopped_at: Option<String>,
+    pub stopped_subject: Option<String>,
+    // 何番目のコミットを適用しているか (1から)
+    pub current_step: Option<u32>,
+    pub total_steps: Option<u32>,
+    pub conflicts: Vec<String>,
+}
+
+// リベースの状態を書き込むフォルダ (実行していない場合はNone)
+async fn rebase_dir(backend: &dyn GitBackend, repo_path: &Path) -> Result<Option<(PathBuf, bool)>, String> {
+    for (name, is_merge_backend) in [("rebase-merge", true), ("rebase-apply", false)] {
+        let dir = repo_path.join(backend.git_path(repo_path, name).await?);
+        // rebase-applyは`git am`でも使われる
+        if dir.is_dir() && (is_merge_backend || !dir.join("applying").exists()) {
+            return Ok(Some((dir, is_merge_backend)));
+        }
+    }
+    Ok(None)
+}
+
+fn read_file(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn read_number(dir: &Path, name: &str) -> Option<u32> {
+    read_file(dir, name).and_then(|v| v.parse().ok())
+}
+
+pub async fn rebase_state(backend: &dyn GitBackend, repo_path: &Path) -> Result<RebaseState, String> {
+    let Some((dir, is_merge_backend)) = rebase_dir(backend, repo_path).await? else {
+        return Ok(RebaseState::default());
+    };
+
+    let (current_step, total_steps) = if is_merge_backend {
+        (read_number(&dir, "msgnum"), read_number(&dir, "end"))
+    } else {
+        (read_number(&dir, "next"), read_number(&dir, "last"))
+    };
+
+    // 止まっているコミットはREBASE_HEADに書かれる (古いgitではrebase-merge/stopped-sha)
+    let stopped_at = match backend.exec(repo_path, GitRequest::new(["rev-parse", "-q", "--verify", "REBASE_HEAD"])).await {
+        Ok(output) => Some(output.trim().to_string()),
+        Err(_) => read_file(&dir, "stopped-sha"),
+    };
+    let stopped_subject = match &stopped_at {
+        Some(hash) => backend.exec(repo_path, GitRequest::new(["log", "-1", "--format=%s"]).arg(hash)).await.ok()
+            .map(|v| v.trim().to_string()),
+        None => None,
+    };
+
+    Ok(RebaseState {
+        in_progress: true,
+        branch_name: read_file(&dir, "head-name")
+            .filter(|v| v != "detached HEAD")
+            .map(|v| v.trim_start_matches("refs/heads/").to_string()),
+        onto: read_file(&dir, "onto"),
+        stopped_at,
+        stopped_subject,
+        current_step,
+        total_steps,
+        conflicts: list_conflicts(backend, repo_path).await?.into_iter().map(|v| v.path).collect(),
+    })
+}
+
+// リベースのgitを実行し、実行後の状態を返す
+// 衝突などで止まった場合はエラーにせず、リベース中の状態を返す
+pub(crate) async fn run_rebase(backend: &dyn GitBackend, repo_path: &Path, request: GitRequest) -> Result<RebaseState, String> {
+    let result = backend.exec(repo_path, request).await;
+    let state = rebase_state(backend, repo_path).await?;
+    match result {
+        Err(e) if !state.in_progress => Err(e),
+        _ => Ok(state),
+    }
+}
+
+// 現在のブランチをtarget (ブランチ名またはコミット) の上に付け替える
+pub async fn rebase_onto(backend: &dyn GitBackend, repo_path: &Path, target: String) -> Result<RebaseState, String> {
+    if rebase_state(backend, repo_path).await?.in_progress {
+        return Err("A rebase is already in progress.".to_string());
+    }
+    run_rebase(backend, repo_path, GitRequest::new(["rebase"]).arg(target).arg("--")).await
+}
+
+// リベースを開始し、止まらずに終わった場合だけ操作として記録する
+// 続行・スキップ・中止は記録しない (止まった時点からの記録では、リベース前のブランチに戻せないため)
+// 途中で止まったリベースを元に戻す場合は中止する
+pub async fn run_journaled<F>(backend: &dyn GitBackend,
+                              journal: &OperationJournal,
+                              repo_path: &Path,
+                              future: F) -> Result<RebaseState, String>
+    where F: Future<Output=Result<RebaseState, String>> {
+    run_journaled_if(backend, journal, repo_path, OperationKind::Rebase, &[], future, |state| !state.in_progress).await
+}
+
+// 衝突を解消した後に続ける
+// コミットメッセージはeditorが書き込む (対話的リベースで計画したものがなければそのまま使う)
+pub async fn continue_rebase(backend: &dyn GitBackend, repo_path: &Path, editor: &RebaseEditor) -> Result<RebaseState, String> {
+    let remaining = list_conflicts(backend, repo_path).await?;
+    if !remaining.is_empty() {
+        let files: Vec<&str> = remaining.iter().map(|v| v.path.as_str()).collect();
+        return Err(format!("Resolve all conflicts first: {}", files.join(", ")));
+    }
+    run_rebase(backend, repo_path, editor.apply(GitRequest::new(["rebase", "--continue"]))).await
+}
+
+// 止まっているコミットを適用せずに次へ進む
+pub async fn skip_rebase(backend: &dyn GitBackend, repo_path: &Path) -> Result<RebaseState, String> {
+    run_rebase(backend, repo_path, GitRequest::new(["rebase", "--skip"])).await
+}
+
+// リベースを中止して、元のブランチに戻す
+pub async fn abort_rebase(backend: &dyn GitBackend, repo_path: &Path) -> Result<RebaseState, String> {
+    backend.exec(repo_path, GitRequest::new(["rebase", "--abort"])).await?;
+    rebase_state(backend, repo_path).await
+}
@@ -0,0 +1,275 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+const RECENT_REPOSITORIES_FILE_NAME: &str = "recent_repositories.json";
+// 読み込めなかったファイルの移動先 (次の保存で上書きされないようにする)
+const BROKEN_RECENT_REPOSITORIES_FILE_NAME: &str = "recent_repositories.json.broken";
+// 以前のバージョンで最後に開いたフォルダを保存していたファイル
+const LEGACY_FOLDER_FILE_NAME: &str = "selected_git_folder.txt";
+// ピン留めされていないものはこの件数まで保持する
+const MAX_RECENT_REPOSITORIES: usize = 20;
+
+// 複数のウィンドウから同時に読み書きされないようにする
+static RECENT_REPOSITORIES_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentRepository {
+    pub path: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub pinned: bool,
+    // UNIX時間 (秒)
+    #[serde(default)]
+    pub last_opened: u64,
+    // ピン留めされたリポジトリが見つからない (読み込むたびに確認し直す)
+    #[serde(default)]
+    pub missing: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecentRepositories {
+    #[serde(default)]
+    repositories: Vec<RecentRepository>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|v| v.as_secs()).unwrap_or(0)
+}
+
+fn is_repository(path: &str) -> bool {
+    Path::new(path).join(".git").exists()
+}
+
+// フォルダ名を既定の表示名にする
+fn default_display_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|v| v.to_string_lossy().to_string())
+        .unwrap_or(path.to_string())
+}
+
+impl RecentRepositories {
+    // 保存されている一覧を読み込み、存在しなくなったリポジトリを取り除く
+    // 壊れたファイルは別の名前に移して残し、空の一覧から始める
+    fn load(config_dir: &Path) -> Self {
+        let path = config_dir.join(RECENT_REPOSITORIES_FILE_NAME);
+        let mut res = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|_| {
+                let _ = fs::rename(&path, config_dir.join(BROKEN_RECENT_REPOSITORIES_FILE_NAME));
+                Self::default()
+            }),
+            Err(_) => Self::load_legacy(config_dir),
+        };
+        res.prune();
+        res
+    }
+
+    // selected_git_folder.txtからの移行
+    fn load_legacy(config_dir: &Path) -> Self {
+        let mut res = Self::default();
+        if let Ok(path) = fs::read_to_string(config_dir.join(LEGACY_FOLDER_FILE_NAME)) {
+            let path = path.trim();
+            if !path.is_empty() {
+                res.touch(path);
+            }
+        }
+        res
+    }
+
+    fn save(&self, config_dir: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(config_dir.join(RECENT_REPOSITORIES_FILE_NAME), json)
+    }
+
+    fn prune(&mut self) {
+        // ピン留めされたものは、外付けドライブを外している場合などもあるので見つからなくても残す
+        self.repositories.retain_mut(|v| {
+            v.missing = !is_repository(&v.path);
+            v.pinned || !v.missing
+        });
+
+        // ピン留めされていないものは新しい順に上限まで残す
+        self.repositories.sort_by_key(|v| Reverse(v.last_opened));
+        let mut unpinned_count = 0;
+        self.repositories.retain(|v| {
+            if v.pinned {
+                return true;
+            }
+            unpinned_count += 1;
+            unpinned_count <= MAX_RECENT_REPOSITORIES
+        });
+    }
+
+    // 開いた日時を更新する (一覧になければ追加する)
+    fn touch(&mut self, path: &str) {
+        match self.repositories.iter_mut().find(|v| v.path == path) {
+            Some(repository) => repository.last_opened = now(),
+            None => self.repositories.push(RecentRepository {
+                path: path.to_string(),
+                display_name: default_display_name(path),
+                pinned: false,
+                last_opened: now(),
+                missing: false,
+            }),
+        }
+    }
+
+    // ピン留めされているものを先頭に、それぞれ最近開いた順に並べる
+    fn list(&self) -> Vec<RecentRepository> {
+        let mut res = self.repositories.clone();
+        res.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.last_opened.cmp(&a.last_opened)));
+        res
+    }
+
+    fn find_mut(&mut self, path: &str) -> Result<&mut RecentRepository, String> {
+        self.repositories.iter_mut()
+            .find(|v| v.path == path)
+            .ok_or("The repository is not in the recent list.".to_string())
+    }
+}
+
+// 一覧を読み込んで更新し、保存する
+fn update<T, F>(config_dir: &Path, f: F) -> Result<T, String>
+    where F: FnOnce(&mut RecentRepositories) -> Result<T, String> {
+    let _guard = RECENT_REPOSITORIES_LOCK.lock().unwrap();
+    let mut repositories = RecentRepositories::load(config_dir);
+    let res = f(&mut repositories)?;
+    repositories.prune();
+    repositories.save(config_dir).map_err(|e| e.to_string())?;
+    Ok(res)
+}
+
+pub fn list_recent_repositories(config_dir: &Path) -> Vec<RecentRepository> {
+    let _guard = RECENT_REPOSITORIES_LOCK.lock().unwrap();
+    RecentRepositories::load(config_dir).list()
+}
+
+// 最後に開いたリポジトリ (起動時に開く)
+pub fn last_opened_repository(config_dir: &Path) -> Option<String> {
+    let _guard = RECENT_REPOSITORIES_LOCK.lock().unwrap();
+    RecentRepositories::load(config_dir)
+        .repositories
+        .into_iter()
+        .filter(|v| !v.missing)
+        .max_by_key(|v| v.last_opened)
+        .map(|v| v.path)
+}
+
+pub fn record_opened_repository(config_dir: &Path, path: &str) -> Result<(), String> {
+    update(config_dir, |v| {
+        v.touch(path);
+        Ok(())
+    })
+}
+
+pub fn forget_repository(config_dir: &Path, path: &str) -> Result<Vec<RecentRepository>, String> {
+    update(config_dir, |v| {
+        v.repositories.retain(|v| v.path != path);
+        Ok(v.list())
+    })
+}
+
+pub fn pin_repository(config_dir: &Path, path: &str, pinned: bool) -> Result<Vec<RecentRepository>, String> {
+    update(config_dir, |v| {
+        v.find_mut(path)?.pinned = pinned;
+        Ok(v.list())
+    })
+}
+
+// 表示名を変更する (空の場合はフォルダ名に戻す)
+pub fn rename_repository(config_dir: &Path, path: &str, display_name: &str) -> Result<Vec<RecentRepository>, String> {
+    update(config_dir, |v| {
+        let display_name = display_name.trim();
+        v.find_mut(path)?.display_name = if display_name.is_empty() {
+            default_display_name(path)
+        } else {
+            display_name.to_string()
+        };
+        Ok(v.list())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // .gitを含むフォルダを作る
+    fn create_repository(dir: &TempDir, name: &str) -> String {
+        let path = dir.path().join(name);
+        fs::create_dir_all(path.join(".git")).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn paths(repositories: &[RecentRepository]) -> Vec<&str> {
+        repositories.iter().map(|v| v.path.as_str()).collect()
+    }
+
+    #[test]
+    fn missing_unpinned_repository_is_removed() {
+        let dir = TempDir::new().unwrap();
+        let kept = create_repository(&dir, "kept");
+        let removed = create_repository(&dir, "removed");
+        record_opened_repository(dir.path(), &removed).unwrap();
+        record_opened_repository(dir.path(), &kept).unwrap();
+
+        fs::remove_dir_all(&removed).unwrap();
+        assert_eq!(paths(&list_recent_repositories(dir.path())), [kept.as_str()]);
+    }
+
+    #[test]
+    fn missing_pinned_repository_is_kept_and_flagged() {
+        let dir = TempDir::new().unwrap();
+        let other = create_repository(&dir, "other");
+        let pinned = create_repository(&dir, "pinned");
+        record_opened_repository(dir.path(), &other).unwrap();
+        record_opened_repository(dir.path(), &pinned).unwrap();
+        pin_repository(dir.path(), &pinned, true).unwrap();
+
+        fs::remove_dir_all(&pinned).unwrap();
+        record_opened_repository(dir.path(), &other).unwrap();
+        let list = list_recent_repositories(dir.path());
+        assert_eq!(paths(&list), [pinned.as_str(), other.as_str()]);
+        assert!(list[0].missing);
+        assert!(!list[1].missing);
+        // 見つからないリポジトリは起動時に開かない
+        assert_eq!(last_opened_repository(dir.path()), Some(other.clone()));
+
+        // 戻ってきた場合は通常の表示に戻る
+        fs::create_dir_all(Path::new(&pinned).join(".git")).unwrap();
+        assert!(!list_recent_repositories(dir.path())[0].missing);
+    }
+
+    #[test]
+    fn unpinned_repositories_are_capped() {
+        let dir = TempDir::new().unwrap();
+        let mut repositories = RecentRepositories::default();
+        for i in 0..MAX_RECENT_REPOSITORIES + 2 {
+            repositories.touch(&create_repository(&dir, &format!("repo{}", i)));
+            repositories.repositories.last_mut().unwrap().last_opened = i as u64;
+        }
+        repositories.repositories[0].pinned = true;
+
+        repositories.prune();
+        assert_eq!(repositories.repositories.len(), MAX_RECENT_REPOSITORIES + 1);
+        // 最も古いものでもピン留めされていれば残る
+        assert!(repositories.repositories.iter().any(|v| v.pinned && v.last_opened == 0));
+        assert!(!repositories.repositories.iter().any(|v| v.last_opened == 1));
+    }
+
+    #[test]
+    fn corrupt_file_is_moved_aside() {
+        let dir = TempDir::new().unwrap();
+        let repository = create_repository(&dir, "repo");
+        fs::write(dir.path().join(RECENT_REPOSITORIES_FILE_NAME), "{broken").unwrap();
+
+        record_opened_repository(dir.path(), &repository).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join(BROKEN_RECENT_REPOSITORIES_FILE_NAME)).unwrap(), "{broken");
+        assert_eq!(paths(&list_recent_repositories(dir.path())), [repository.as_str()]);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+// リポジトリに対する操作の種類
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockMode {
+    // status, diff, logなど (同時に実行できる)
+    Read,
+    // commit, checkout, pushなど (1つずつ実行する)
+    Write,
+}
+
+// dropするまでリポジトリのロックを保持する
+#[derive(Debug)]
+pub enum RepositoryGuard {
+    Read(OwnedRwLockReadGuard<()>),
+    Write(OwnedRwLockWriteGuard<()>),
+}
+
+// リポジトリごとの読み書きのロック
+// 書き込みは1つずつ実行し、読み取りは実行中・待機中の書き込みが終わるまで待つ
+// (tokioのRwLockは要求した順に取得されるため、読み取りが続いても書き込みが待たされ続けることはない)
+#[derive(Default)]
+pub struct RepositoryLocks {
+    locks: Mutex<HashMap<PathBuf, Arc<RwLock<()>>>>,
+}
+
+impl RepositoryLocks {
+    pub async fn acquire(&self, repo_path: &Path, mode: LockMode) -> RepositoryGuard {
+        let lock = self.lock_for(repo_path);
+        match mode {
+            LockMode::Read => RepositoryGuard::Read(lock.read_owned().await),
+            LockMode::Write => RepositoryGuard::Write(lock.write_owned().await),
+        }
+    }
+
+    // 同じリポジトリを別の表記 (相対パス、シンボリックリンクなど) で開いても同じロックを使う
+    fn lock_for(&self, repo_path: &Path) -> Arc<RwLock<()>> {
+        let key = repo_path.canonicalize().unwrap_or_else(|_| repo_path.to_path_buf());
+        self.locks.lock().unwrap()
+            .entry(key)
+            .or_default()
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    // 待たされずにロックを取得できるか
+    async fn acquire_now(locks: &RepositoryLocks, repo_path: &Path, mode: LockMode) -> Option<RepositoryGuard> {
+        timeout(Duration::from_millis(50), locks.acquire(repo_path, mode)).await.ok()
+    }
+
+    #[tokio::test]
+    async fn writes_run_one_at_a_time() {
+        let locks = RepositoryLocks::default();
+        let repo_path = Path::new("/path/to/repo");
+
+        let guard = locks.acquire(repo_path, LockMode::Write).await;
+        assert!(acquire_now(&locks, repo_path, LockMode::Write).await.is_none());
+        assert!(acquire_now(&locks, repo_path, LockMode::Read).await.is_none());
+
+        drop(guard);
+        assert!(acquire_now(&locks, repo_path, LockMode::Write).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn reads_run_together_and_wait_for_queued_write() {
+        let locks = Arc::new(RepositoryLocks::default());
+        let repo_path = Path::new("/path/to/repo");
+
+        let read = locks.acquire(repo_path, LockMode::Read).await;
+        assert!(acquire_now(&locks, repo_path, LockMode::Read).await.is_some());
+
+        // 書き込みが待っている間は、後から来た読み取りも待つ
+        let write = tokio::spawn({
+            let locks = locks.clone();
+            async move {
+                let _guard = locks.acquire(Path::new("/path/to/repo"), LockMode::Write).await;
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(acquire_now(&locks, repo_path, LockMode::Read).await.is_none());
+
+        drop(read);
+        write.await.unwrap();
+        assert!(acquire_now(&locks, repo_path, LockMode::Read).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn repositories_are_independent() {
+        let locks = RepositoryLocks::default();
+        let _guard = locks.acquire(Path::new("/path/to/repo"), LockMode::Write).await;
+        assert!(acquire_now(&locks, Path::new("/path/to/other"), LockMode::Write).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn same_repository_with_different_path_shares_lock() {
+        let dir = std::env::temp_dir();
+        let locks = RepositoryLocks::default();
+        let _guard = locks.acquire(&dir, LockMode::Write).await;
+        assert!(acquire_now(&locks, &dir.join("."), LockMode::Read).await.is_none());
+    }
+}
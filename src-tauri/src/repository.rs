@@ -0,0 +1,25 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+
+// ウィンドウごとに開いているリポジトリ (key: ウィンドウのラベル)
+// プロセスのカレントディレクトリは使わず、gitコマンドには必ず作業ディレクトリを指定する
+static REPOSITORIES: Lazy<Mutex<HashMap<String, PathBuf>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn set_repository(window_label: &str, path: PathBuf) {
+    REPOSITORIES.lock().unwrap().insert(window_label.to_string(), path);
+}
+
+pub fn get_repository(window_label: &str) -> Option<PathBuf> {
+    REPOSITORIES.lock().unwrap().get(window_label).cloned()
+}
+
+// コマンド実行用。リポジトリが開かれていない場合はエラー
+pub fn repository_path(window_label: &str) -> Result<PathBuf, String> {
+    get_repository(window_label).ok_or("No repository is opened in this window.".to_string())
+}
+
+pub fn remove_repository(window_label: &str) {
+    REPOSITORIES.lock().unwrap().remove(window_label);
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use crate::backend::{GitBackend, GitRequest};
+use crate::journal::{run_journaled, OperationJournal, OperationKind};
+
+// リセットの方法
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ResetMode {
+    // ブランチだけを移動し、取り消したコミットの変更はstagingされた状態で残す (--soft)
+    Soft = 0,
+    // ブランチとindexを移動し、変更は作業ツリーに残す (--mixed)
+    Mixed = 1,
+    // ブランチ・index・作業ツリーを全て移動し、コミットしていない変更を破棄する (--hard)
+    Hard = 2,
+}
+
+impl ResetMode {
+    fn arg(&self) -> &'static str {
+        match self {
+            ResetMode::Soft => "--soft",
+            ResetMode::Mixed => "--mixed",
+            ResetMode::Hard => "--hard",
+        }
+    }
+
+    // 元に戻す方法がリセットの方法ごとに違うので、別の操作として記録する
+    pub fn operation_kind(&self) -> OperationKind {
+        match self {
+            ResetMode::Soft => OperationKind::ResetSoft,
+            ResetMode::Mixed => OperationKind::ResetMixed,
+            ResetMode::Hard => OperationKind::ResetHard,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResetCommit {
+    pub hash: String,
+    pub subject: String,
+}
+
+// リセットする前に確認する内容
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResetPreview {
+    pub mode: ResetMode,
+    // 移動先のコミット
+    pub target: String,
+    pub target_subject: String,
+    // 移動するブランチ (HEADがブランチでない場合はNone)
+    pub branch_name: Option<String>,
+    // 他のブランチ・タグから辿れなくなるコミット (新しい順、reflogからは戻せる)
+    pub unreachable_commits: Vec<ResetCommit>,
+    // Hardの場合に失われる、コミットしていない変更のあるファイル
+    pub lost_files: Vec<String>,
+}
+
+async fn resolve_commit(backend: &dyn GitBackend, repo_path: &Path, target: &str) -> Result<String, String> {
+    let request = GitRequest::new(["rev-parse", "--verify", "--end-of-options"]).arg(format!("{}^{{commit}}", target));
+    Ok(backend.exec(repo_path, request).await?.trim().to_string())
+}
+
+// --hardで失われるファイル
+// stagingの有無に関わらずHEADから変更されているファイルと、移動先で管理されていて上書きされる未追跡のファイル
+async fn files_lost_by_hard_reset(backend: &dyn GitBackend, repo_path: &Path, target: &str) -> Result<Vec<String>, String> {
+    let request = GitRequest::new(["diff", "--name-only", "--no-renames", "-z", "HEAD", "--"]);
+    let mut files: Vec<String> = backend.exec(repo_path, request).await?
+        .split('\0')
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let untracked = backend.exec(repo_path, GitRequest::new(["ls-files", "-z", "--others", "--exclude-standard"])).await?;
+    let untracked: HashSet<&str> = untracked.split('\0').filter(|v| !v.is_empty()).collect();
+    if !untracked.is_empty() {
+        // 未追跡のファイルが多くてもコマンドラインが長くならないように、移動先のファイルを全て取得して比べる
+        let request = GitRequest::new(["ls-tree", "-r", "-z", "--name-only"]).arg(target).arg("--");
+        let tracked = backend.exec(repo_path, request).await?;
+        files.extend(tracked.split('\0').filter(|v| untracked.contains(v)).map(str::to_string));
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+// 現在のブランチをtargetにリセットした場合に起きることを調べる
+pub async fn preview_reset(backend: &dyn GitBackend, repo_path: &Path, target: &str, mode: ResetMode) -> Result<ResetPreview, String> {
+    let target = resolve_commit(backend, repo_path, target).await?;
+    let target_subject = backend.exec(repo_path, GitRequest::new(["log", "-1", "--format=%s"]).arg(&target)).await?
+        .trim().to_string();
+    if backend.exec(repo_path, GitRequest::new(["rev-parse", "-q", "--verify", "HEAD"])).await.is_err() {
+        return Err("The current branch has no commits yet.".to_string());
+    }
+    let branch_name = backend.exec(repo_path, GitRequest::new(["symbolic-ref", "-q", "--short", "HEAD"])).await.ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+
+    // HEADから辿れて、移動先・(現在のブランチ以外の) ブランチ・タグ・リモートから辿れないコミット
+    let mut request = GitRequest::new(["log", "--format=%H%x00%s", "HEAD", "--not"]).arg(&target);
+    if let Some(branch_name) = &branch_name {
+        request = request.arg(format!("--exclude={}", branch_name));
+    }
+    let output = backend.exec(repo_path, request.args(["--branches", "--tags", "--remotes", "--"])).await?;
+    let unreachable_commits = output.lines()
+        .filter_map(|line| line.split_once('\0'))
+        .map(|(hash, subject)| ResetCommit { hash: hash.to_string(), subject: subject.to_string() })
+        .collect();
+
+    let lost_files = match mode {
+        ResetMode::Hard => files_lost_by_hard_reset(backend, repo_path, &target).await?,
+        _ => vec![],
+    };
+
+    Ok(ResetPreview { mode, target, target_subject, branch_name, unreachable_commits, lost_files })
+}
+
+// 現在のブランチをtargetにリセットし、リセット後のHEADを返す
+pub async fn reset_to(backend: &dyn GitBackend, repo_path: &Path, target: &str, mode: ResetMode) -> Result<String, String> {
+    let target = resolve_commit(backend, repo_path, target).await?;
+    backend.exec(repo_path, GitRequest::new(["reset", "-q", mode.arg()]).arg(&target).arg("--")).await?;
+    Ok(target)
+}
+
+// --hardで失われるファイルは、元に戻せるように退避してからリセットする
+pub async fn reset_journaled(backend: &dyn GitBackend,
+                             journal: &OperationJournal,
+                             repo_path: &Path,
+                             target: &str,
+                             mode: ResetMode) -> Result<String, String> {
+    let preview = preview_reset(backend, repo_path, target, mode).await?;
+    run_journaled(backend,
+                  journal,
+                  repo_path,
+                  mode.operation_kind(),
+                  &preview.lost_files,
+                  reset_to(backend, repo_path, &preview.target, mode)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use tempfile::TempDir;
+    use super::*;
+    use crate::backend::scripted::ScriptedBackend;
+    use crate::journal::expect_snapshot;
+
+    #[tokio::test]
+    async fn untracked_files_are_compared_with_whole_target_tree() {
+        let backend = ScriptedBackend::new()
+            .expect(&["diff", "--name-only", "--no-renames", "-z", "HEAD", "--"], Ok("b.txt\0"))
+            .expect(&["ls-files", "-z", "--others", "--exclude-standard"], Ok(&["new.txt", "a.txt", "tmp.txt"].join("\0")))
+            .expect(&["ls-tree", "-r", "-z", "--name-only", "aaa", "--"], Ok(&["a.txt", "b.txt", "dir/c.txt"].join("\0")));
+
+        let files = files_lost_by_hard_reset(&backend, &env::temp_dir(), "aaa").await.unwrap();
+        assert_eq!(files, vec!["a.txt", "b.txt"]);
+        backend.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn reset_is_recorded_with_kind_of_mode() {
+        let dir = TempDir::new().unwrap();
+        let journal = OperationJournal::new(dir.path().to_path_buf());
+        let repo_path = env::temp_dir();
+        let backend = ScriptedBackend::new()
+            .expect(&["rev-parse", "--verify", "--end-of-options", "HEAD~1^{commit}"], Ok("aaa\n"))
+            .expect(&["log", "-1", "--format=%s", "aaa"], Ok("first\n"))
+            .expect(&["rev-parse", "-q", "--verify", "HEAD"], Ok("bbb\n"))
+            .expect(&["symbolic-ref", "-q", "--short", "HEAD"], Ok("main\n"))
+            .expect(&["log", "--format=%H%x00%s", "HEAD", "--not", "aaa", "--exclude=main", "--branches", "--tags", "--remotes", "--"], Ok(""));
+        // プレビューで解決したコミットにリセットする
+        let backend = expect_snapshot(backend, "bbb")
+            .expect(&["rev-parse", "--verify", "--end-of-options", "aaa^{commit}"], Ok("aaa\n"))
+            .expect(&["reset", "-q", "--mixed", "aaa", "--"], Ok(""));
+        let backend = expect_snapshot(backend, "aaa");
+
+        let head = reset_journaled(&backend, &journal, &repo_path, "HEAD~1", ResetMode::Mixed).await.unwrap();
+        assert_eq!(head, "aaa");
+        backend.assert_finished();
+        assert_eq!(journal.last(&repo_path).unwrap().kind, OperationKind::ResetMixed);
+    }
+}
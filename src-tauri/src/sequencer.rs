@@ -0,0 +1,297 @@
+// cherry-pickとrevert (どちらもgitのsequencerで1コミットずつ適用し、衝突すると止まる)
+
+use std::collections::HashSet;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use crate::backend::{GitBackend, GitRequest};
+use crate::conflict::list_conflicts;
+use crate::journal::{self, OperationJournal, OperationKind};
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SequencerCommand {
+    CherryPick = 0,
+    Revert = 1,
+}
+
+impl SequencerCommand {
+    fn name(&self) -> &'static str {
+        match self {
+            SequencerCommand::CherryPick => "cherry-pick",
+            SequencerCommand::Revert => "revert",
+        }
+    }
+
+    // 止まっているコミットを指すref
+    fn head_ref(&self) -> &'static str {
+        match self {
+            SequencerCommand::CherryPick => "CHERRY_PICK_HEAD",
+            SequencerCommand::Revert => "REVERT_HEAD",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PickOptions {
+    // 元のコミットを示す行 "(cherry picked from commit ...)" をメッセージに追加する (-x、cherry-pickのみ)
+    pub record_origin: bool,
+    // マージコミットの場合に、どの親との差分を適用するか (1から、-m)
+    pub mainline: Option<u32>,
+}
+
+impl PickOptions {
+    fn args(&self, command: SequencerCommand) -> Vec<String> {
+        let mut args = vec![];
+        if self.record_origin && command == SequencerCommand::CherryPick {
+            args.push("-x".to_string());
+        }
+        if let Some(mainline) = self.mainline {
+            args.push("-m".to_string());
+            args.push(mainline.to_string());
+        }
+        args
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PickOutcomeKind {
+    // 全て適用した
+    Applied = 0,
+    // 衝突したファイルがあり、途中で止まっている
+    Conflicts = 1,
+    // 既に含まれている変更などで空のコミットになるため、途中で止まっている (skipで飛ばせる)
+    Empty = 2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PickOutcome {
+    pub kind: PickOutcomeKind,
+    // 作成したコミット (古い順)
+    pub created: Vec<String>,
+    pub state: SequencerState,
+}
+
+// cherry-pick・revertの途中の状態
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SequencerState {
+    // 実行中の操作 (止まっていない場合はNone)
+    pub command: Option<SequencerCommand>,
+    pub stopped_at: Option<String>,
+    pub stopped_subject: Option<String>,
+    // 止まっているコミットより後に適用するコミットの数
+    pub remaining: usize,
+    pub conflicts: Vec<String>,
+}
+
+impl SequencerState {
+    pub fn in_progress(&self) -> bool {
+        self.command.is_some()
+    }
+}
+
+async fn rev_parse(backend: &dyn GitBackend, repo_path: &Path, name: &str) -> Option<String> {
+    backend.exec(repo_path, GitRequest::new(["rev-parse", "-q", "--verify"]).arg(name)).await.ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+pub async fn sequencer_state(backend: &dyn GitBackend, repo_path: &Path) -> Result<SequencerState, String> {
+    for command in [SequencerCommand::CherryPick, SequencerCommand::Revert] {
+        let Some(stopped_at) = rev_parse(backend, repo_path, command.head_ref()).await else {
+            continue;
+        };
+
+        let stopped_subject = backend.exec(repo_path, GitRequest::new(["log", "-1", "--format=%s"]).arg(&stopped_at)).await.ok()
+            .map(|v| v.trim().to_string());
+        // 複数のコミットを適用している場合は、残りがsequencer/todoに書かれる (先頭は止まっているコミット)
+        let todo = repo_path.join(backend.git_path(repo_path, "sequencer/todo").await?);
+        let remaining = fs::read_to_string(todo).unwrap_or_default().lines()
+            .filter(|v| !v.trim().is_empty() && !v.starts_with('#'))
+            .count()
+            .saturating_sub(1);
+
+        return Ok(SequencerState {
+            command: Some(command),
+            stopped_at: Some(stopped_at),
+            stopped_subject,
+            remaining,
+            conflicts: list_conflicts(backend, repo_path).await?.into_iter().map(|v| v.path).collect(),
+        });
+    }
+    Ok(SequencerState::default())
+}
+
+// gitを実行し、止まった場合はエラーにせず途中の状態を返す
+async fn run_sequence(backend: &dyn GitBackend, repo_path: &Path, request: GitRequest) -> Result<PickOutcome, String> {
+    let before = rev_parse(backend, repo_path, "HEAD").await;
+    let result = backend.exec(repo_path, request).await;
+    let state = sequencer_state(backend, repo_path).await?;
+    if let Err(e) = result {
+        if !state.in_progress() {
+            return Err(e);
+        }
+    }
+
+    // 止まるまでに作成したコミット
+    let created = match &before {
+        Some(before) => {
+            let request = GitRequest::new(["rev-list", "--reverse"]).arg(format!("{}..HEAD", before));
+            backend.exec(repo_path, request).await?.lines().map(str::to_string).collect()
+        }
+        None => vec![],
+    };
+    let kind = match (state.in_progress(), state.conflicts.is_empty()) {
+        (false, _) => PickOutcomeKind::Applied,
+        (true, false) => PickOutcomeKind::Conflicts,
+        (true, true) => PickOutcomeKind::Empty,
+    };
+    Ok(PickOutcome { kind, created, state })
+}
+
+// 適用する順に並べる (gitは渡した順に適用するので、cherry-pickは古い順、revertは新しい順にする)
+// 同じ時刻のコミットもあるので、日時ではなく履歴の順に並べる
+async fn sort_commits(backend: &dyn GitBackend,
+                      repo_path: &Path,
+                      command: SequencerCommand,
+                      hashes: &[String]) -> Result<Vec<String>, String> {
+    let request = GitRequest::new(["rev-parse", "--end-of-options"])
+        .args(hashes.iter().map(|v| format!("{}^{{commit}}", v)));
+    let selected: Vec<String> = backend.exec(repo_path, request).await?.lines().map(str::to_string).collect();
+
+    // 選択したコミットの共通の祖先より前は辿らない (共通の祖先がない場合は全て辿る)
+    // 共通の祖先自体も選択されている場合があるので、その親から除外する
+    let request = GitRequest::new(["merge-base", "--octopus"]).args(&selected);
+    let base = backend.exec(repo_path, request).await.ok().and_then(|v| v.lines().next().map(str::to_string));
+    let mut request = GitRequest::new(["rev-list", "--topo-order"]).args(&selected);
+    if let Some(base) = base {
+        request = request.arg(format!("^{}^@", base));
+    }
+
+    let selected: HashSet<&str> = selected.iter().map(String::as_str).collect();
+    let mut sorted: Vec<String> = backend.exec(repo_path, request.arg("--")).await?.lines()
+        .filter(|v| selected.contains(v))
+        .map(str::to_string)
+        .collect();
+    if command == SequencerCommand::CherryPick {
+        sorted.reverse();
+    }
+    Ok(sorted)
+}
+
+// hashesのコミットを現在のブランチに適用する (選択した順に関係なく、履歴の順に適用する)
+pub async fn apply_commits(backend: &dyn GitBackend,
+                           repo_path: &Path,
+                           command: SequencerCommand,
+                           hashes: &[String],
+                           options: &PickOptions) -> Result<PickOutcome, String> {
+    if hashes.is_empty() {
+        return Err("No commits are selected.".to_string());
+    }
+    if let Some(command) = sequencer_state(backend, repo_path).await?.command {
+        return Err(format!("A {} is already in progress.", command.name()));
+    }
+
+    let sorted = sort_commits(backend, repo_path, command, hashes).await?;
+    // マージコミットはどの親との差分を使うか指定しないと適用できない
+    if options.mainline.is_none() {
+        let request = GitRequest::new(["rev-list", "--no-walk", "--min-parents=2"]).args(&sorted).arg("--");
+        if let Some(merge) = backend.exec(repo_path, request).await?.lines().next() {
+            return Err(format!("{} is a merge commit. Choose the parent to use as the mainline.", merge));
+        }
+    }
+
+    let request = GitRequest::new([command.name()])
+        .args(options.args(command))
+        .args(sorted)
+        .env("GIT_EDITOR", "true");
+    run_sequence(backend, repo_path, request).await
+}
+
+// 衝突を解消した後に続ける (コミットメッセージはそのまま使う)
+pub async fn continue_sequence(backend: &dyn GitBackend, repo_path: &Path) -> Result<PickOutcome, String> {
+    let state = sequencer_state(backend, repo_path).await?;
+    let Some(command) = state.command else {
+        return Err("No cherry-pick or revert is in progress.".to_string());
+    };
+    if !state.conflicts.is_empty() {
+        return Err(format!("Resolve all conflicts first: {}", state.conflicts.join(", ")));
+    }
+    run_sequence(backend, repo_path, GitRequest::new([command.name(), "--continue"]).env("GIT_EDITOR", "true")).await
+}
+
+// 止まっているコミットを適用せずに次へ進む
+pub async fn skip_sequence(backend: &dyn GitBackend, repo_path: &Path) -> Result<PickOutcome, String> {
+    let Some(command) = sequencer_state(backend, repo_path).await?.command else {
+        return Err("No cherry-pick or revert is in progress.".to_string());
+    };
+    run_sequence(backend, repo_path, GitRequest::new([command.name(), "--skip"]).env("GIT_EDITOR", "true")).await
+}
+
+// 中止して、始める前の状態に戻す
+pub async fn abort_sequence(backend: &dyn GitBackend, repo_path: &Path) -> Result<SequencerState, String> {
+    let Some(command) = sequencer_state(backend, repo_path).await?.command else {
+        return Err("No cherry-pick or revert is in progress.".to_string());
+    };
+    backend.exec(repo_path, GitRequest::new([command.name(), "--abort"])).await?;
+    sequencer_state(backend, repo_path).await
+}
+
+// 途中から続けるcherry-pick・revertを、実行中の操作の種類で記録する
+pub async fn run_journaled<F>(backend: &dyn GitBackend,
+                              journal: &OperationJournal,
+                              repo_path: &Path,
+                              future: F) -> Result<PickOutcome, String>
+    where F: Future<Output=Result<PickOutcome, String>> {
+    let kind = match sequencer_state(backend, repo_path).await?.command {
+        Some(SequencerCommand::Revert) => OperationKind::Revert,
+        _ => OperationKind::CherryPick,
+    };
+    journal::run_journaled(backend, journal, repo_path, kind, &[], future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use tempfile::TempDir;
+    use super::*;
+    use crate::backend::scripted::ScriptedBackend;
+    use crate::journal::expect_snapshot;
+
+    #[test]
+    fn pick_args() {
+        let options = PickOptions { record_origin: true, mainline: Some(2) };
+        assert_eq!(options.args(SequencerCommand::CherryPick), vec!["-x", "-m", "2"]);
+        // revertには-xがない
+        assert_eq!(options.args(SequencerCommand::Revert), vec!["-m", "2"]);
+        assert!(PickOptions::default().args(SequencerCommand::CherryPick).is_empty());
+    }
+
+    #[tokio::test]
+    async fn continued_revert_is_recorded_as_revert() {
+        let dir = TempDir::new().unwrap();
+        let journal = OperationJournal::new(dir.path().to_path_buf());
+        let repo_path = env::temp_dir();
+        let backend = ScriptedBackend::new()
+            .expect(&["rev-parse", "-q", "--verify", "CHERRY_PICK_HEAD"], Err(""))
+            .expect(&["rev-parse", "-q", "--verify", "REVERT_HEAD"], Ok("ccc\n"))
+            .expect(&["log", "-1", "--format=%s", "ccc"], Ok("reverted\n"))
+            .expect(&["rev-parse", "--git-path", "sequencer/todo"], Ok("zengit-test-missing/sequencer/todo\n"))
+            .expect(&["ls-files", "-u", "-z"], Ok(""));
+        let backend = expect_snapshot(backend, "aaa")
+            .expect(&["revert", "--continue"], Ok(""));
+        let backend = expect_snapshot(backend, "bbb");
+
+        let future = async {
+            backend.exec(&repo_path, GitRequest::new(["revert", "--continue"])).await?;
+            Ok(PickOutcome { kind: PickOutcomeKind::Applied, created: vec!["bbb".to_string()], state: SequencerState::default() })
+        };
+        run_journaled(&backend, &journal, &repo_path, future).await.unwrap();
+        backend.assert_finished();
+        assert_eq!(journal.last(&repo_path).unwrap().kind, OperationKind::Revert);
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::diff_options::DiffOptions;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+// 設定の形式を変更した場合はこれを上げて、migrateに変換処理を追加する
+pub const SETTINGS_SCHEMA_VERSION: u32 = 1;
+
+// 複数のウィンドウから同時に読み書きされないようにする
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LogSettings {
+    // `git log --date=format:`に渡す書式 (strftime形式)
+    pub date_format: String,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            date_format: "%Y/%m/%d (%a) %H:%M".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PushSettings {
+    pub remote: String,
+    // `-u`を付けてupstreamを設定する
+    pub set_upstream: bool,
+}
+
+impl Default for PushSettings {
+    fn default() -> Self {
+        PushSettings {
+            remote: "origin".to_string(),
+            set_upstream: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PullSettings {
+    pub prune: bool,
+}
+
+impl Default for PullSettings {
+    fn default() -> Self {
+        PullSettings { prune: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FetchSettings {
+    pub prune: bool,
+}
+
+impl Default for FetchSettings {
+    fn default() -> Self {
+        FetchSettings { prune: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WindowSettings {
+    // アプリから開くウィンドウのWebViewに渡す引数
+    // (起動時のメインウィンドウはtauri.conf.jsonの設定が使われる)
+    pub additional_browser_args: String,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            additional_browser_args: "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection --disable-gpu --disable-local-storage --disable-background-networking --kiosk --disable-context-menu --single-process".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub schema_version: u32,
+    pub log: LogSettings,
+    pub push: PushSettings,
+    pub pull: PullSettings,
+    pub fetch: FetchSettings,
+    pub window: WindowSettings,
+    // diffの既定のオプション
+    pub diff: DiffOptions,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            schema_version: SETTINGS_SCHEMA_VERSION,
+            log: LogSettings::default(),
+            push: PushSettings::default(),
+            pull: PullSettings::default(),
+            fetch: FetchSettings::default(),
+            window: WindowSettings::default(),
+            diff: DiffOptions::default(),
+        }
+    }
+}
+
+// 保存されている設定を最新の形式に変換する
+// version 0: schema_versionがない設定 (形式はversion 1と同じ)
+fn migrate(mut value: Value) -> Value {
+    let mut version = value.get("schema_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+
+    while version < SETTINGS_SCHEMA_VERSION {
+        // 形式を変更した場合は、ここにversionから次のversionへの変換を追加する
+        version += 1;
+        value["schema_version"] = Value::from(version);
+    }
+
+    value
+}
+
+impl Settings {
+    // 読み込めない場合は初期値 (壊れたファイルは上書きしない)
+    pub fn load(config_dir: &Path) -> Self {
+        let _guard = SETTINGS_LOCK.lock().unwrap();
+        let value = match fs::read_to_string(config_dir.join(SETTINGS_FILE_NAME)) {
+            Ok(json) => match serde_json::from_str::<Value>(&json) {
+                Ok(value) if value.is_object() => value,
+                _ => return Settings::default(),
+            },
+            Err(_) => Value::Object(Default::default()),
+        };
+
+        serde_json::from_value(migrate(value)).unwrap_or_default()
+    }
+
+    pub fn save(&self, config_dir: &Path) -> Result<(), String> {
+        let _guard = SETTINGS_LOCK.lock().unwrap();
+        let mut settings = self.clone();
+        settings.schema_version = SETTINGS_SCHEMA_VERSION;
+        let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+        fs::write(config_dir.join(SETTINGS_FILE_NAME), json).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff_options::DiffAlgorithm;
+    use tempfile::TempDir;
+
+    fn load_json(json: &str) -> (TempDir, Settings) {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(SETTINGS_FILE_NAME), json).unwrap();
+        let settings = Settings::load(dir.path());
+        (dir, settings)
+    }
+
+    #[test]
+    fn missing_file_uses_defaults() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(Settings::load(dir.path()), Settings::default());
+    }
+
+    #[test]
+    fn every_earlier_version_is_migrated() {
+        for version in 0..SETTINGS_SCHEMA_VERSION {
+            // version 0はschema_versionがない
+            let mut value = serde_json::json!({ "push": { "remote": "upstream" } });
+            if version > 0 {
+                value["schema_version"] = Value::from(version);
+            }
+            let migrated = migrate(value);
+            assert_eq!(migrated["schema_version"], Value::from(SETTINGS_SCHEMA_VERSION), "version {}", version);
+            assert_eq!(migrated["push"]["remote"], "upstream", "version {}", version);
+        }
+
+        let (_dir, settings) = load_json(r#"{ "log": { "date_format": "%Y-%m-%d" } }"#);
+        assert_eq!(settings.schema_version, SETTINGS_SCHEMA_VERSION);
+        assert_eq!(settings.log.date_format, "%Y-%m-%d");
+    }
+
+    #[test]
+    fn missing_and_unknown_fields_fall_back_to_defaults() {
+        let (_dir, settings) = load_json(r#"{
+            "schema_version": 1,
+            "push": { "set_upstream": false, "unknown": 1 },
+            "diff": { "algorithm": 3 },
+            "removed_section": { "value": true }
+        }"#);
+        assert!(!settings.push.set_upstream);
+        assert_eq!(settings.push.remote, PushSettings::default().remote);
+        assert_eq!(settings.diff.algorithm, DiffAlgorithm::Histogram);
+        assert_eq!(settings.pull, PullSettings::default());
+        assert_eq!(settings.window, WindowSettings::default());
+    }
+
+    #[test]
+    fn corrupt_file_is_not_overwritten_by_load() {
+        let json = "{ \"push\": ";
+        let (dir, settings) = load_json(json);
+        assert_eq!(settings, Settings::default());
+        assert_eq!(fs::read_to_string(dir.path().join(SETTINGS_FILE_NAME)).unwrap(), json);
+
+        // 型が合わない値もファイル全体を初期値として扱う
+        let (_dir, settings) = load_json(r#"{ "pull": { "prune": "yes" } }"#);
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn saved_settings_are_loaded() {
+        let dir = TempDir::new().unwrap();
+        let mut settings = Settings::default();
+        settings.fetch.prune = false;
+        settings.schema_version = 0;
+        settings.save(dir.path()).unwrap();
+
+        let loaded = Settings::load(dir.path());
+        assert!(!loaded.fetch.prune);
+        assert_eq!(loaded.schema_version, SETTINGS_SCHEMA_VERSION);
+    }
+}
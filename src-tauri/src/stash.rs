@@ -0,0 +1,245 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::backend::{to_diff_result, GitBackend, GitRequest};
+use crate::conflict::list_conflicts;
+use crate::diff::DiffResult;
+use crate::diff_options::DiffOptions;
+use crate::history::parse_show_files;
+use crate::status::{ChangeState, StatusInfo};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StashEntry {
+    // "stash@{0}" (stashを作成・削除すると番号がずれるので、操作にはhashを使う)
+    pub name: String,
+    pub hash: String,
+    pub message: String,
+    // stashしたときのブランチ (HEADがブランチでなかった場合はNone)
+    pub branch: Option<String>,
+    pub date: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StashOptions {
+    // 空の場合はgitが用意したもの ("WIP on main: ...")
+    pub message: String,
+    // 未追跡のファイルも含める (-u)
+    pub include_untracked: bool,
+    // stagingした変更を作業ツリーに残す (--keep-index)
+    pub keep_index: bool,
+    // 指定した場合はこのファイルの変更だけをstashする
+    pub files: Vec<StatusInfo>,
+}
+
+impl StashOptions {
+    // `git stash push`に付ける引数
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if self.include_untracked {
+            args.push("--include-untracked".to_string());
+        }
+        if self.keep_index {
+            args.push("--keep-index".to_string());
+        }
+        if !self.message.is_empty() {
+            args.push("-m".to_string());
+            args.push(self.message.clone());
+        }
+        if !self.files.is_empty() {
+            args.push("--".to_string());
+            for info in &self.files {
+                // リネームは元のパスも含めないと削除がstashされない
+                if let Some(orig_filename) = &info.orig_filename {
+                    args.push(orig_filename.clone());
+                }
+                args.push(info.filename.clone());
+            }
+        }
+        args
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StashApplyOutcome {
+    // popでstashを削除したか (衝突した場合は残る)
+    pub dropped: bool,
+    // 衝突したファイル (解消してからコミットする)
+    pub conflicts: Vec<String>,
+}
+
+// `git stash list --format=%H%x00%gs%x00%ad`の出力をパースする
+// %gsは"WIP on main: abc1234 subject"または (メッセージを指定した場合) "On main: message"
+// (%gdは--dateを指定すると"stash@{日時}"になるので、名前は行の順番から作る)
+pub fn parse_stash_list(output: &str) -> Vec<StashEntry> {
+    output
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let fields: Vec<&str> = line.split('\0').collect();
+            let [hash, subject, date] = fields.as_slice() else {
+                return None;
+            };
+            let (branch, message) = subject
+                .strip_prefix("WIP on ")
+                .or_else(|| subject.strip_prefix("On "))
+                .and_then(|v| v.split_once(": "))
+                .map(|(branch, message)| (Some(branch).filter(|v| *v != "(no branch)"), message))
+                .unwrap_or((None, *subject));
+            Some(StashEntry {
+                name: format!("stash@{{{}}}", index),
+                hash: hash.to_string(),
+                message: message.to_string(),
+                branch: branch.map(str::to_string),
+                date: date.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub async fn list_stashes(backend: &dyn GitBackend, repo_path: &Path, date_format: &str) -> Result<Vec<StashEntry>, String> {
+    let request = GitRequest::new(["stash", "list", "--format=%H%x00%gs%x00%ad"])
+        .arg(format!("--date=format:{}", date_format));
+    let output = backend.exec(repo_path, request).await?;
+    Ok(parse_stash_list(&output))
+}
+
+// hashのstashの現在の名前 ("stash@{1}")
+async fn stash_name(backend: &dyn GitBackend, repo_path: &Path, hash: &str) -> Result<String, String> {
+    list_stashes(backend, repo_path, "%s").await?
+        .into_iter()
+        .find(|v| v.hash == hash)
+        .map(|v| v.name)
+        .ok_or_else(|| "The stash no longer exists.".to_string())
+}
+
+// 変更をstashし、作成したstashを返す
+pub async fn create_stash(backend: &dyn GitBackend, repo_path: &Path, options: &StashOptions) -> Result<StashEntry, String> {
+    let before = list_stashes(backend, repo_path, "%s").await?.into_iter().next().map(|v| v.hash);
+    backend.exec(repo_path, GitRequest::new(["stash", "push", "-q"]).args(options.args())).await?;
+
+    // 変更がない場合もgitは成功するので、stashが増えたかどうかで判断する
+    let created = list_stashes(backend, repo_path, "%s").await?.into_iter().next();
+    match created {
+        Some(created) if Some(&created.hash) != before.as_ref() => Ok(created),
+        _ => Err("No local changes to save.".to_string()),
+    }
+}
+
+// stashを作業ツリーに適用する (popの場合は、衝突しなければstashを削除する)
+// 衝突した場合はエラーにせず、衝突したファイルを返す
+pub async fn apply_stash(backend: &dyn GitBackend, repo_path: &Path, hash: &str, pop: bool) -> Result<StashApplyOutcome, String> {
+    // 解消していない衝突があると、stashの衝突と区別できないので先に止める
+    if !list_conflicts(backend, repo_path).await?.is_empty() {
+        return Err("Resolve the current conflicts before applying a stash.".to_string());
+    }
+
+    let name = stash_name(backend, repo_path, hash).await?;
+    let command = if pop { "pop" } else { "apply" };
+    let result = backend.exec(repo_path, GitRequest::new(["stash", command, "-q"]).arg(name)).await;
+
+    let conflicts: Vec<String> = list_conflicts(backend, repo_path).await?.into_iter().map(|v| v.path).collect();
+    if let Err(e) = result {
+        if conflicts.is_empty() {
+            return Err(e);
+        }
+    }
+    let dropped = pop && stash_name(backend, repo_path, hash).await.is_err();
+    Ok(StashApplyOutcome { dropped, conflicts })
+}
+
+pub async fn drop_stash(backend: &dyn GitBackend, repo_path: &Path, hash: &str) -> Result<(), String> {
+    let name = stash_name(backend, repo_path, hash).await?;
+    backend.exec(repo_path, GitRequest::new(["stash", "drop", "-q"]).arg(name)).await?;
+    Ok(())
+}
+
+// 未追跡のファイルを含めたstashは、3番目の親にそのファイルだけのコミットを持つ
+async fn untracked_commit(backend: &dyn GitBackend, repo_path: &Path, hash: &str) -> Option<String> {
+    let request = GitRequest::new(["rev-parse", "-q", "--verify"]).arg(format!("{}^3", hash));
+    backend.exec(repo_path, request).await.ok().map(|v| v.trim().to_string())
+}
+
+// stashに含まれるファイル (git_show_filesと同じ形式、未追跡だったファイルはUntracked)
+pub async fn stash_files(backend: &dyn GitBackend, repo_path: &Path, hash: &str) -> Result<Vec<StatusInfo>, String> {
+    // 日本語などのパスが8進数でエスケープされないようにする
+    let request = GitRequest::new(["-c", "core.quotePath=false", "diff", "--name-status"])
+        .arg(format!("{}^1", hash))
+        .arg(hash)
+        .arg("--");
+    let mut files = parse_show_files(&backend.exec(repo_path, request).await?);
+
+    if let Some(untracked) = untracked_commit(backend, repo_path, hash).await {
+        let request = GitRequest::new(["-c", "core.quotePath=false", "show", "--pretty=format:", "--name-only"]).arg(untracked);
+        let output = backend.exec(repo_path, request).await?;
+        files.extend(output.lines()
+            .filter(|v| !v.is_empty())
+            .map(|v| StatusInfo::with_state(v.to_string(), ChangeState::Untracked)));
+    }
+    Ok(files)
+}
+
+// stashに含まれるファイルの差分 (git_show_file_diffと同じ形式)
+pub async fn stash_file_diff(backend: &dyn GitBackend,
+                             repo_path: &Path,
+                             hash: &str,
+                             file: String,
+                             options: &DiffOptions) -> Result<DiffResult, String> {
+    // 未追跡だったファイルは、そのファイルだけのコミットの差分 (全て追加) を表示する
+    if let Some(untracked) = untracked_commit(backend, repo_path, hash).await {
+        let request = GitRequest::new(["rev-parse", "-q", "--verify"]).arg(format!("{}:{}", untracked, file));
+        if backend.exec(repo_path, request).await.is_ok() {
+            return backend.show_file_diff(repo_path, untracked, file, options).await;
+        }
+    }
+
+    let request = GitRequest::new(options.config_args())
+        .arg("diff")
+        .args(options.args())
+        .arg(format!("{}^1", hash))
+        .arg(hash)
+        .arg("--")
+        .arg(file);
+    let output = backend.exec(repo_path, request).await?;
+    Ok(to_diff_result(&output, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_list() {
+        let output = [
+            ["aaa", "On main: my message", "2026/01/02"].join("\0"),
+            ["bbb", "WIP on feature/x: 1234567 subject: with colon", "2026/01/01"].join("\0"),
+            ["ccc", "WIP on (no branch): 1234567 detached", "2025/12/31"].join("\0"),
+        ].join("\n");
+        let entries = parse_stash_list(&output);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0], StashEntry {
+            name: "stash@{0}".to_string(),
+            hash: "aaa".to_string(),
+            message: "my message".to_string(),
+            branch: Some("main".to_string()),
+            date: "2026/01/02".to_string(),
+        });
+        assert_eq!(entries[1].name, "stash@{1}");
+        assert_eq!(entries[1].branch.as_deref(), Some("feature/x"));
+        assert_eq!(entries[1].message, "1234567 subject: with colon");
+        assert_eq!(entries[2].branch, None);
+    }
+
+    #[test]
+    fn stash_args() {
+        let mut renamed = StatusInfo::with_state("new.txt".to_string(), ChangeState::Rename);
+        renamed.orig_filename = Some("old.txt".to_string());
+        let options = StashOptions {
+            message: "wip".to_string(),
+            include_untracked: true,
+            keep_index: true,
+            files: vec![StatusInfo::with_state("a.txt".to_string(), ChangeState::Change), renamed],
+        };
+        assert_eq!(options.args(), vec!["--include-untracked", "--keep-index", "-m", "wip", "--", "a.txt", "old.txt", "new.txt"]);
+        assert!(StashOptions::default().args().is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+// Typescript側のenumがswitch-caseで正しく動作しなかったのでu8としてserialize
+// (既存の値は互換性のため変更しないこと。追加する場合は末尾に追加する)
+#[derive(Debug, Clone, Copy, Default, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ChangeState {
+    #[default]
+    Unknown = 0,
+    Change = 1,
+    Staging = 2,
+    Delete = 3,
+    Add = 4,
+    Unmodified = 5,
+    Rename = 6,
+    Copy = 7,
+    TypeChange = 8,
+    Unmerged = 9,
+    Untracked = 10,
+    Ignored = 11,
+    SubmoduleChange = 12,
+}
+
+impl ChangeState {
+    // porcelain v2 の XY の1文字を変換する
+    fn from_xy_char(c: char) -> Self {
+        match c {
+            '.' => ChangeState::Unmodified,
+            'M' => ChangeState::Change,
+            'T' => ChangeState::TypeChange,
+            'A' => ChangeState::Add,
+            'D' => ChangeState::Delete,
+            'R' => ChangeState::Rename,
+            'C' => ChangeState::Copy,
+            'U' => ChangeState::Unmerged,
+            _ => ChangeState::Unknown,
+        }
+    }
+
+    pub fn is_changed(&self) -> bool {
+        !matches!(self, ChangeState::Unmodified | ChangeState::Unknown)
+    }
+}
+
+// コンフリクトの種類 (git status の XY 表記に対応)
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConflictKind {
+    BothDeleted = 0,   // DD
+    AddedByUs = 1,     // AU
+    DeletedByThem = 2, // UD
+    AddedByThem = 3,   // UA
+    DeletedByUs = 4,   // DU
+    BothAdded = 5,     // AA
+    BothModified = 6,  // UU
+}
+
+impl ConflictKind {
+    fn from_xy(xy: &str) -> Option<Self> {
+        match xy {
+            "DD" => Some(ConflictKind::BothDeleted),
+            "AU" => Some(ConflictKind::AddedByUs),
+            "UD" => Some(ConflictKind::DeletedByThem),
+            "UA" => Some(ConflictKind::AddedByThem),
+            "DU" => Some(ConflictKind::DeletedByUs),
+            "AA" => Some(ConflictKind::BothAdded),
+            "UU" => Some(ConflictKind::BothModified),
+            _ => None,
+        }
+    }
+
+    // `git ls-files -u`のステージ (1: 共通の祖先, 2: ours, 3: theirs) の有無から判定する
+    pub fn from_stages(base: bool, ours: bool, theirs: bool) -> Option<Self> {
+        match (base, ours, theirs) {
+            (true, false, false) => Some(ConflictKind::BothDeleted),
+            (false, true, false) => Some(ConflictKind::AddedByUs),
+            (true, true, false) => Some(ConflictKind::DeletedByThem),
+            (false, false, true) => Some(ConflictKind::AddedByThem),
+            (true, false, true) => Some(ConflictKind::DeletedByUs),
+            (false, true, true) => Some(ConflictKind::BothAdded),
+            (true, true, true) => Some(ConflictKind::BothModified),
+            (false, false, false) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusInfo {
+    // 一覧表示用にまとめた状態
+    pub change_state: ChangeState,
+    pub filename: String,
+    #[serde(default)]
+    pub index_state: ChangeState,
+    #[serde(default)]
+    pub worktree_state: ChangeState,
+    // リネーム/コピー元のパス
+    #[serde(default)]
+    pub orig_filename: Option<String>,
+    #[serde(default)]
+    pub conflict: Option<ConflictKind>,
+    #[serde(default)]
+    pub is_submodule: bool,
+}
+
+impl StatusInfo {
+    // コミット内容の一覧など、index/ワークツリーの区別がないもの用
+    pub fn with_state(filename: String, change_state: ChangeState) -> Self {
+        StatusInfo {
+            change_state,
+            filename,
+            index_state: ChangeState::Unknown,
+            worktree_state: ChangeState::Unknown,
+            orig_filename: None,
+            conflict: None,
+            is_submodule: false,
+        }
+    }
+
+    fn new(filename: String, index_state: ChangeState, worktree_state: ChangeState) -> Self {
+        let mut info = StatusInfo {
+            change_state: ChangeState::Unknown,
+            filename,
+            index_state,
+            worktree_state,
+            orig_filename: None,
+            conflict: None,
+            is_submodule: false,
+        };
+        info.change_state = info.summary_state();
+        info
+    }
+
+    // 一覧表示用の状態を決める (ワークツリー側の変更を優先する)
+    fn summary_state(&self) -> ChangeState {
+        if self.conflict.is_some() {
+            return ChangeState::Unmerged;
+        }
+
+        match (self.index_state, self.worktree_state) {
+            (_, ChangeState::Untracked) => ChangeState::Add,
+            (_, ChangeState::Add) => ChangeState::Add, // intent-to-add (`git add -N`)
+            (_, ChangeState::Ignored) => ChangeState::Ignored,
+            (_, ChangeState::Delete) => ChangeState::Delete,
+            (ChangeState::Rename, _) => ChangeState::Rename,
+            (ChangeState::Copy, _) => ChangeState::Copy,
+            (ChangeState::Add, _) => ChangeState::Add,
+            (ChangeState::Delete, _) => ChangeState::Delete,
+            (_, ChangeState::TypeChange) => ChangeState::TypeChange,
+            (_, ChangeState::SubmoduleChange) => ChangeState::SubmoduleChange,
+            (_, ChangeState::Change) => ChangeState::Change,
+            (ChangeState::TypeChange, _) => ChangeState::TypeChange,
+            (ChangeState::Change, _) => ChangeState::Staging,
+            _ => ChangeState::Unknown,
+        }
+    }
+}
+
+// `<sub>` フィールドの解析 ("N..." ならサブモジュールではない)
+// サブモジュールの場合は "S<c><m><u>" 形式で、ワークツリーの中身に変更があるかを返す
+fn parse_submodule_field(sub: &str) -> (bool, bool) {
+    let mut chars = sub.chars();
+    if chars.next() != Some('S') {
+        return (false, false);
+    }
+
+    let has_changes = chars.any(|c| c != '.');
+    (true, has_changes)
+}
+
+// `git status --porcelain=v2 -z -uall` の出力を解析する
+// (https://git-scm.com/docs/git-status#_porcelain_format_version_2)
+pub fn parse_status_porcelain_v2(output: &str) -> Vec<StatusInfo> {
+    let mut res = Vec::new();
+    let mut entries = output.split('\0').filter(|v| !v.is_empty());
+
+    while let Some(entry) = entries.next() {
+        let (kind, rest) = entry.split_at(1);
+        let rest = rest.strip_prefix(' ').unwrap_or(rest);
+
+        match kind {
+            // 通常の変更
+            // 1 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <path>
+            "1" => {
+                let fields: Vec<&str> = rest.splitn(8, ' ').collect();
+                if fields.len() < 8 {
+                    continue;
+                }
+                res.push(parse_changed_entry(fields[0], fields[1], fields[7].to_string(), None));
+            }
+            // リネーム/コピー (元のパスは次のNUL区切りに入っている)
+            // 2 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <X><score> <path>
+            "2" => {
+                let fields: Vec<&str> = rest.splitn(9, ' ').collect();
+                let orig_filename = entries.next().map(|v| v.to_string());
+                if fields.len() < 9 {
+                    continue;
+                }
+                res.push(parse_changed_entry(fields[0], fields[1], fields[8].to_string(), orig_filename));
+            }
+            // コンフリクト
+            // u <XY> <sub> <m1> <m2> <m3> <mW> <h1> <h2> <h3> <path>
+            "u" => {
+                let fields: Vec<&str> = rest.splitn(10, ' ').collect();
+                if fields.len() < 10 {
+                    continue;
+                }
+                let mut info = StatusInfo::new(fields[9].to_string(), ChangeState::Unmerged, ChangeState::Unmerged);
+                info.conflict = ConflictKind::from_xy(fields[0]);
+                info.is_submodule = parse_submodule_field(fields[1]).0;
+                info.change_state = info.summary_state();
+                res.push(info);
+            }
+            // 未追跡
+            "?" => {
+                res.push(StatusInfo::new(rest.to_string(), ChangeState::Unmodified, ChangeState::Untracked));
+            }
+            // 無視 (--ignored指定時のみ)
+            "!" => {
+                res.push(StatusInfo::new(rest.to_string(), ChangeState::Unmodified, ChangeState::Ignored));
+            }
+            // "# branch.oid" などのヘッダー行は無視
+            _ => {}
+        }
+    }
+
+    res
+}
+
+fn parse_changed_entry(xy: &str, sub: &str, filename: String, orig_filename: Option<String>) -> StatusInfo {
+    let mut xy_chars = xy.chars();
+    let index_state = ChangeState::from_xy_char(xy_chars.next().unwrap_or('.'));
+    let mut worktree_state = ChangeState::from_xy_char(xy_chars.next().unwrap_or('.'));
+
+    let (is_submodule, has_submodule_changes) = parse_submodule_field(sub);
+    if is_submodule && has_submodule_changes && worktree_state == ChangeState::Change {
+        worktree_state = ChangeState::SubmoduleChange;
+    }
+
+    let mut info = StatusInfo::new(filename, index_state, worktree_state);
+    info.orig_filename = orig_filename;
+    info.is_submodule = is_submodule;
+    info.change_state = info.summary_state();
+    info
+}
+
+// index(staging済み)とワークツリー(未staging)の変更を分けた結果
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StatusResult {
+    pub staged: Vec<StatusInfo>,
+    pub unstaged: Vec<StatusInfo>,
+}
+
+// 1ファイルにindexとワークツリー両方の変更がある場合は、それぞれの一覧に1件ずつ入れる
+pub fn split_staged_unstaged(infos: Vec<StatusInfo>) -> StatusResult {
+    let mut res = StatusResult::default();
+
+    for info in infos {
+        // コンフリクト中のファイルは解消するまでstagingできないのでunstaged扱い
+        if info.conflict.is_some() {
+            res.unstaged.push(info);
+            continue;
+        }
+
+        if info.index_state.is_changed() {
+            let mut staged = info.clone();
+            staged.change_state = info.index_state;
+            staged.worktree_state = ChangeState::Unmodified;
+            res.staged.push(staged);
+        }
+
+        if info.worktree_state.is_changed() {
+            let mut unstaged = info;
+            unstaged.change_state = match unstaged.worktree_state {
+                ChangeState::Untracked => ChangeState::Add,
+                state => state,
+            };
+            // リネーム元はindex側の情報なのでワークツリー側には載せない
+            unstaged.orig_filename = None;
+            res.unstaged.push(unstaged);
+        }
+    }
+
+    res
+}
@@ -0,0 +1,52 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::backend::{GitBackend, GitRequest};
+
+// ユーザーのindex(.git/index)を変更せずに`git add -N`などを行うための一時index
+// `GIT_INDEX_FILE`に指定して使う。Drop時に削除される。
+pub struct TempIndex {
+    repo_path: PathBuf,
+    path: PathBuf,
+}
+
+impl TempIndex {
+    // リポジトリのindexをコピーした一時indexを作成する
+    pub async fn create(backend: &dyn GitBackend, repo_path: &Path) -> Result<Self, String> {
+        // `--git-path`は作業ディレクトリからの相対パスで返る場合がある
+        let index_path = repo_path.join(backend.git_path(repo_path, "index").await?);
+
+        let since_the_epoch = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards");
+        let path = env::temp_dir().join(format!("zengit_{}_{}.index", std::process::id(), since_the_epoch.as_nanos()));
+
+        // 初回コミット前などindexがまだない場合は空のindexから始める
+        if index_path.exists() {
+            fs::copy(&index_path, &path).map_err(|e| e.to_string())?;
+        }
+
+        Ok(TempIndex { repo_path: repo_path.to_path_buf(), path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 未追跡ファイルをintent-to-addとして一時indexに登録する (diffを取れるようにするため)
+    pub async fn add_intent_to_add(&self, backend: &dyn GitBackend, file: &str) -> Result<(), String> {
+        let request = GitRequest::new(["add", "-N", "--", file])
+            .env("GIT_INDEX_FILE", self.path.to_string_lossy());
+        backend.exec(&self.repo_path, request).await?;
+        Ok(())
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        // gitが異常終了した場合に残るロックファイルも消しておく
+        let mut lock = self.path.clone().into_os_string();
+        lock.push(".lock");
+        let _ = fs::remove_file(lock);
+    }
+}
@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+use crate::diff::{DiffFile, DiffLine, DiffLineKind};
+
+// 行の長さの積がこれを超える場合は計算量が大きくなるので単語単位の差分を出さない
+const MAX_LCS_CELLS: usize = 250_000;
+
+// 行内の変更箇所
+// (フロントエンドでString.sliceにそのまま使えるようUTF-16単位のオフセットで返す)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+#[derive(PartialEq)]
+enum CharClass {
+    Word,
+    Space,
+    Other,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_ascii_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else if c.is_whitespace() {
+        CharClass::Space
+    } else {
+        CharClass::Other
+    }
+}
+
+// 英数字の連続・空白の連続・それ以外の1文字ずつに分割する
+// (日本語など単語の区切りがない文字は1文字ずつ比較する)
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut utf16_pos = 0;
+    let mut token_start: Option<(usize, usize, CharClass)> = None; // (バイト位置, UTF-16位置, 種類)
+
+    for (byte_pos, c) in line.char_indices() {
+        let class = char_class(c);
+        let continues = match &token_start {
+            Some((_, _, current)) => *current == class && class != CharClass::Other,
+            None => false,
+        };
+
+        if !continues {
+            if let Some((start_byte, start_utf16, _)) = token_start.take() {
+                tokens.push(Token { text: &line[start_byte..byte_pos], start: start_utf16, end: utf16_pos });
+            }
+            token_start = Some((byte_pos, utf16_pos, class));
+        }
+
+        utf16_pos += c.len_utf16();
+    }
+
+    if let Some((start_byte, start_utf16, _)) = token_start {
+        tokens.push(Token { text: &line[start_byte..], start: start_utf16, end: utf16_pos });
+    }
+
+    tokens
+}
+
+// 最長共通部分列から、それぞれの行で共通部分に含まれないトークンを求める
+fn diff_tokens(old: &[Token], new: &[Token]) -> Option<(Vec<bool>, Vec<bool>)> {
+    if old.len() * new.len() > MAX_LCS_CELLS {
+        return None;
+    }
+
+    let width = new.len() + 1;
+    let mut table = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i * width + j] = if old[i].text == new[j].text {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut old_changed = vec![true; old.len()];
+    let mut new_changed = vec![true; new.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i].text == new[j].text {
+            old_changed[i] = false;
+            new_changed[j] = false;
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    Some((old_changed, new_changed))
+}
+
+// 変更されたトークンを連続する範囲にまとめる
+fn to_spans(tokens: &[Token], changed: &[bool]) -> Vec<ChangeSpan> {
+    let mut spans: Vec<ChangeSpan> = vec![];
+    for (token, _) in tokens.iter().zip(changed).filter(|(_, changed)| **changed) {
+        match spans.last_mut() {
+            Some(last) if last.end == token.start => last.end = token.end,
+            _ => spans.push(ChangeSpan { start: token.start, end: token.end }),
+        }
+    }
+    spans
+}
+
+// 削除行と追加行のペアから、それぞれの行内の変更箇所を求める
+pub fn compute_change_spans(old_line: &str, new_line: &str) -> Option<(Vec<ChangeSpan>, Vec<ChangeSpan>)> {
+    let old_tokens = tokenize(old_line);
+    let new_tokens = tokenize(new_line);
+    let (old_changed, new_changed) = diff_tokens(&old_tokens, &new_tokens)?;
+
+    // 空白以外に共通部分がない場合は行全体の変更なので強調しない
+    let has_common = old_tokens.iter()
+        .zip(&old_changed)
+        .any(|(token, changed)| !changed && !token.text.trim().is_empty());
+    if !has_common {
+        return None;
+    }
+
+    Some((to_spans(&old_tokens, &old_changed), to_spans(&new_tokens, &new_changed)))
+}
+
+// 連続する削除行の直後に続く追加行を先頭から順にペアにして、行内の変更箇所を設定する
+fn annotate_lines(lines: &mut [DiffLine]) {
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].kind != DiffLineKind::Removed {
+            i += 1;
+            continue;
+        }
+
+        let removed_start = i;
+        while i < lines.len() && lines[i].kind == DiffLineKind::Removed {
+            i += 1;
+        }
+        let added_start = i;
+        while i < lines.len() && lines[i].kind == DiffLineKind::Added {
+            i += 1;
+        }
+
+        let pair_count = (added_start - removed_start).min(i - added_start);
+        for n in 0..pair_count {
+            let (old_index, new_index) = (removed_start + n, added_start + n);
+            if let Some((old_spans, new_spans)) = compute_change_spans(&lines[old_index].content, &lines[new_index].content) {
+                lines[old_index].changes = old_spans;
+                lines[new_index].changes = new_spans;
+            }
+        }
+    }
+}
+
+pub fn annotate_word_diff(files: &mut [DiffFile]) {
+    for file in files.iter_mut().filter(|v| !v.is_combined && !v.is_binary) {
+        for hunk in file.hunks.iter_mut() {
+            annotate_lines(&mut hunk.lines);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffResult;
+
+    fn span(start: usize, end: usize) -> ChangeSpan {
+        ChangeSpan { start, end }
+    }
+
+    #[test]
+    fn ascii_words_are_compared() {
+        let (old, new) = compute_change_spans("let foo = 1;", "let barbaz = 1;").unwrap();
+        assert_eq!(old, [span(4, 7)]);
+        assert_eq!(new, [span(4, 10)]);
+    }
+
+    #[test]
+    fn offsets_are_utf16_units() {
+        // 日本語は1文字ずつ比較する
+        let (old, new) = compute_change_spans("日本語のテスト", "日本語の試験").unwrap();
+        assert_eq!(old, [span(4, 7)]);
+        assert_eq!(new, [span(4, 6)]);
+
+        // サロゲートペアは2単位として数える
+        let (old, new) = compute_change_spans("😀 old", "😀 new").unwrap();
+        assert_eq!(old, [span(3, 6)]);
+        assert_eq!(new, [span(3, 6)]);
+
+        let (old, new) = compute_change_spans("x 😀 y", "x 🎉 y").unwrap();
+        assert_eq!(old, [span(2, 4)]);
+        assert_eq!(new, [span(2, 4)]);
+    }
+
+    #[test]
+    fn lines_without_common_words_are_not_highlighted() {
+        assert_eq!(compute_change_spans("abc", "xyz"), None);
+        // 空白のみが共通の場合も行全体の変更として扱う
+        assert_eq!(compute_change_spans("a b", "x y"), None);
+    }
+
+    #[test]
+    fn long_lines_fall_back_to_whole_line() {
+        // トークン数の積がMAX_LCS_CELLSを超えると比較しない
+        let old = "a ".repeat(300);
+        let new = format!("{}b", old);
+        assert!(tokenize(&old).len() * tokenize(&new).len() > MAX_LCS_CELLS);
+        assert_eq!(compute_change_spans(&old, &new), None);
+
+        let old = "a ".repeat(200);
+        let new = format!("{}b", old);
+        assert!(tokenize(&old).len() * tokenize(&new).len() <= MAX_LCS_CELLS);
+        assert_eq!(compute_change_spans(&old, &new), Some((vec![], vec![span(400, 401)])));
+    }
+
+    #[test]
+    fn removed_and_added_lines_are_paired_in_order() {
+        let raw = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,4 +1,4 @@
+-first line
+-second line
++first row
+ same
+-third line
++third row
++extra row
+";
+        let result = DiffResult::from_raw(raw);
+        let lines = &result.files[0].hunks[0].lines;
+        // 削除行の方が多い場合、余った削除行は強調しない
+        assert_eq!(lines[0].changes, [span(6, 10)]);
+        assert_eq!(lines[1].changes, []);
+        assert_eq!(lines[2].changes, [span(6, 9)]);
+        assert_eq!(lines[3].changes, []);
+        // 追加行の方が多い場合、余った追加行は強調しない
+        assert_eq!(lines[4].changes, [span(6, 10)]);
+        assert_eq!(lines[5].changes, [span(6, 9)]);
+        assert_eq!(lines[6].changes, []);
+    }
+}
@@ -0,0 +1,111 @@
+// 結合テスト用の使い捨てリポジトリ
+// (テストごとに一時フォルダへ作成し、dropで削除される)
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+pub struct TestRepo {
+    // dropされるまでフォルダを残す
+    _dir: TempDir,
+    path: PathBuf,
+}
+
+impl TestRepo {
+    // 空のリポジトリを作成する (ブランチ名は環境の設定によらずmain)
+    pub fn new() -> Self {
+        let dir = TempDir::new().expect("failed to create temp dir");
+        let path = dir.path().join("repo");
+        fs::create_dir(&path).unwrap();
+
+        let repo = TestRepo { _dir: dir, path };
+        repo.git(&["init", "-q"]);
+        repo.configure();
+        repo
+    }
+
+    // originから複製したリポジトリを作成する
+    pub fn clone_from(origin: &TestRepo) -> Self {
+        let dir = TempDir::new().expect("failed to create temp dir");
+        let path = dir.path().join("clone");
+        run_git(dir.path(), &["clone", "-q", &origin.path.to_string_lossy(), "clone"]);
+
+        let repo = TestRepo { _dir: dir, path };
+        repo.configure();
+        repo
+    }
+
+    fn configure(&self) {
+        self.git(&["symbolic-ref", "HEAD", "refs/heads/main"]);
+        self.git(&["config", "user.name", "Test User"]);
+        self.git(&["config", "user.email", "test@example.com"]);
+        self.git(&["config", "commit.gpgsign", "false"]);
+        self.git(&["config", "core.autocrlf", "false"]);
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // gitを実行して標準出力を返す (失敗した場合はpanic)
+    pub fn git(&self, args: &[&str]) -> String {
+        run_git(&self.path, args)
+    }
+
+    // 標準出力を変換せずに返す
+    pub fn git_bytes(&self, args: &[&str]) -> Vec<u8> {
+        run_git_bytes(&self.path, args)
+    }
+
+    pub fn write(&self, file: &str, content: &str) {
+        let path = self.path.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, content).unwrap();
+    }
+
+    // UTF-8でない内容や改行コードをそのまま書き込む
+    pub fn write_bytes(&self, file: &str, content: &[u8]) {
+        fs::write(self.path.join(file), content).unwrap();
+    }
+
+    pub fn read(&self, file: &str) -> String {
+        fs::read_to_string(self.path.join(file)).unwrap()
+    }
+
+    pub fn exists(&self, file: &str) -> bool {
+        self.path.join(file).exists()
+    }
+
+    // ファイルを書き込んでコミットする
+    pub fn commit_file(&self, file: &str, content: &str, message: &str) -> String {
+        self.write(file, content);
+        self.git(&["add", "--", file]);
+        self.git(&["commit", "-q", "-m", message]);
+        self.head()
+    }
+
+    pub fn head(&self) -> String {
+        self.git(&["rev-parse", "HEAD"]).trim().to_string()
+    }
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> String {
+    String::from_utf8_lossy(&run_git_bytes(dir, args)).to_string()
+}
+
+fn run_git_bytes(dir: &Path, args: &[&str]) -> Vec<u8> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        // 利用者の設定に影響されないようにする
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_CONFIG_GLOBAL", if cfg!(windows) { "NUL" } else { "/dev/null" })
+        .output()
+        .expect("failed to run git");
+    assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    output.stdout
+}
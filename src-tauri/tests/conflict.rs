@@ -0,0 +1,122 @@
+// マージの衝突の解消を、実際のgitで一時リポジトリを操作して確認する
+mod common;
+
+use common::TestRepo;
+use zengit_lib::backend::{CliBackend, GitBackend};
+use zengit_lib::conflict::{abort_merge, conflict_detail, continue_merge, is_merge_in_progress, list_conflicts, mark_resolved,
+                           resolve_with_content, resolve_with_side, ConflictRegionKind, ConflictSide};
+use zengit_lib::merge::MergeOptions;
+use zengit_lib::status::ConflictKind;
+
+// mainとfeatureで同じファイルを変更し、featureをマージして衝突させる
+async fn conflicted_repo() -> TestRepo {
+    let repo = TestRepo::new();
+    repo.commit_file("conflict.txt", "first\nbase\nlast\n", "initial");
+    repo.commit_file("deleted.txt", "base\n", "add deleted.txt");
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.commit_file("conflict.txt", "first\nfeature\nlast\n", "feature");
+    repo.git(&["rm", "-q", "deleted.txt"]);
+    repo.git(&["commit", "-q", "-m", "delete"]);
+    repo.git(&["checkout", "-q", "main"]);
+    repo.commit_file("conflict.txt", "first\nmain\nlast\n", "main");
+    repo.commit_file("deleted.txt", "changed on main\n", "change deleted.txt");
+
+    let backend = CliBackend::default();
+    backend.merge(repo.path(), "feature".to_string(), &MergeOptions::default()).await.unwrap_err();
+    repo
+}
+
+#[tokio::test]
+async fn conflicts_are_listed_with_stages() {
+    let repo = conflicted_repo().await;
+    let backend = CliBackend::default();
+
+    assert!(is_merge_in_progress(&backend, repo.path()).await.unwrap());
+    let conflicts = list_conflicts(&backend, repo.path()).await.unwrap();
+    let kinds: Vec<(&str, Option<ConflictKind>)> = conflicts.iter().map(|v| (v.path.as_str(), v.kind)).collect();
+    assert_eq!(kinds, vec![
+        ("conflict.txt", Some(ConflictKind::BothModified)),
+        ("deleted.txt", Some(ConflictKind::DeletedByThem)),
+    ]);
+    assert!(conflicts[1].theirs.is_none());
+}
+
+#[tokio::test]
+async fn detail_is_read_from_markers_or_stages() {
+    let repo = conflicted_repo().await;
+    let backend = CliBackend::default();
+
+    let detail = conflict_detail(&backend, repo.path(), "conflict.txt").await.unwrap();
+    assert!(detail.from_markers);
+    assert_eq!((detail.ours_label.as_str(), detail.theirs_label.as_str()), ("HEAD", "feature"));
+    let kinds: Vec<ConflictRegionKind> = detail.regions.iter().map(|v| v.kind).collect();
+    assert_eq!(kinds, vec![ConflictRegionKind::Common, ConflictRegionKind::Conflict, ConflictRegionKind::Common]);
+    assert_eq!(detail.regions[1].ours, "main\n");
+    assert_eq!(detail.regions[1].theirs, "feature\n");
+
+    // 削除と変更の衝突はファイルにマーカーがないので、indexのステージから取得する
+    let detail = conflict_detail(&backend, repo.path(), "deleted.txt").await.unwrap();
+    assert!(!detail.from_markers);
+    assert_eq!(detail.regions.len(), 1);
+    assert_eq!(detail.regions[0].base.as_deref(), Some("base\n"));
+    assert_eq!(detail.regions[0].ours, "changed on main\n");
+    assert_eq!(detail.regions[0].theirs, "");
+
+    assert!(conflict_detail(&backend, repo.path(), "missing.txt").await.is_err());
+}
+
+#[tokio::test]
+async fn resolve_and_continue_merge() {
+    let repo = conflicted_repo().await;
+    let backend = CliBackend::default();
+
+    resolve_with_side(&backend, repo.path(), "conflict.txt", ConflictSide::Theirs).await.unwrap();
+    assert_eq!(repo.read("conflict.txt"), "first\nfeature\nlast\n");
+
+    // 選んだ側で削除されている場合は削除して解消する
+    let err = continue_merge(&backend, repo.path()).await.unwrap_err();
+    assert!(err.contains("deleted.txt"), "{}", err);
+    resolve_with_side(&backend, repo.path(), "deleted.txt", ConflictSide::Theirs).await.unwrap();
+    assert!(!repo.exists("deleted.txt"));
+
+    continue_merge(&backend, repo.path()).await.unwrap();
+    assert!(!is_merge_in_progress(&backend, repo.path()).await.unwrap());
+    assert_eq!(repo.git(&["rev-list", "--count", "--merges", "HEAD"]).trim(), "1");
+}
+
+#[tokio::test]
+async fn resolve_with_edited_content_and_mark_resolved() {
+    let repo = conflicted_repo().await;
+    let backend = CliBackend::default();
+
+    resolve_with_content(&backend, repo.path(), "conflict.txt", "first\nmain and feature\nlast\n").await.unwrap();
+    assert_eq!(repo.git(&["show", ":conflict.txt"]), "first\nmain and feature\nlast\n");
+
+    // マーカーが残っているものは解消済みにしない
+    repo.write("deleted.txt", "<<<<<<< HEAD\nchanged on main\n=======\n>>>>>>> feature\n");
+    let err = mark_resolved(&backend, repo.path(), &["deleted.txt".to_string()]).await.unwrap_err();
+    assert!(err.contains("conflict markers"), "{}", err);
+    repo.write("deleted.txt", "kept\n");
+    // 空の場合や衝突していないファイルは、全てstagingされたり関係ない変更がstagingされたりしないように失敗させる
+    repo.write("other.txt", "untracked\n");
+    assert!(mark_resolved(&backend, repo.path(), &[]).await.is_err());
+    let err = mark_resolved(&backend, repo.path(), &["deleted.txt".to_string(), "other.txt".to_string()]).await.unwrap_err();
+    assert!(err.contains("'other.txt' is not in conflict"), "{}", err);
+    assert_eq!(list_conflicts(&backend, repo.path()).await.unwrap().len(), 1);
+    mark_resolved(&backend, repo.path(), &["deleted.txt".to_string()]).await.unwrap();
+
+    assert!(list_conflicts(&backend, repo.path()).await.unwrap().is_empty());
+    assert_eq!(repo.git(&["ls-files", "--", "other.txt"]), "");
+}
+
+#[tokio::test]
+async fn abort_restores_state_before_merge() {
+    let repo = conflicted_repo().await;
+    let backend = CliBackend::default();
+    let head = repo.head();
+
+    abort_merge(&backend, repo.path()).await.unwrap();
+    assert!(!is_merge_in_progress(&backend, repo.path()).await.unwrap());
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.read("conflict.txt"), "first\nmain\nlast\n");
+}
@@ -0,0 +1,131 @@
+// 対話的リベースを、zengitの補助モードをエディタにして実際のgitで確認する
+mod common;
+
+use std::path::PathBuf;
+use common::TestRepo;
+use zengit_lib::backend::CliBackend;
+use zengit_lib::interactive_rebase::{plan_interactive_rebase, run_interactive_rebase, RebaseEditor, TodoAction, TodoEntry};
+use zengit_lib::rebase::{abort_rebase, continue_rebase};
+
+fn editor() -> RebaseEditor {
+    RebaseEditor::new(PathBuf::from(env!("CARGO_BIN_EXE_zengit")))
+}
+
+// baseの後に4つコミットしたリポジトリ
+fn history_repo() -> (TestRepo, String) {
+    let repo = TestRepo::new();
+    let base = repo.commit_file("base.txt", "base\n", "base");
+    repo.commit_file("a.txt", "a\n", "add a");
+    repo.commit_file("b.txt", "b\n", "add b");
+    repo.commit_file("a.txt", "a fixed\n", "fix a");
+    repo.commit_file("d.txt", "d\n", "add d");
+    (repo, base)
+}
+
+fn subjects(repo: &TestRepo, base: &str) -> String {
+    repo.git(&["log", "--reverse", "--format=%s", &format!("{}..HEAD", base)])
+}
+
+// 計画からsubjectのコミットを取り出して、actionとmessageを設定する
+fn take(plan: &mut Vec<TodoEntry>, subject: &str, action: TodoAction, message: Option<&str>) -> TodoEntry {
+    let index = plan.iter().position(|v| v.subject == subject).unwrap();
+    let mut entry = plan.remove(index);
+    entry.action = action;
+    entry.message = message.map(str::to_string);
+    entry
+}
+
+#[tokio::test]
+async fn plan_lists_commits_after_base() {
+    let (repo, base) = history_repo();
+    let backend = CliBackend::default();
+
+    let plan = plan_interactive_rebase(&backend, repo.path(), &base).await.unwrap();
+    let subjects: Vec<&str> = plan.iter().map(|v| v.subject.as_str()).collect();
+    assert_eq!(subjects, vec!["add a", "add b", "fix a", "add d"]);
+    assert!(plan.iter().all(|v| v.action == TodoAction::Pick && v.hash.starts_with(&v.short_hash)));
+
+    repo.git(&["checkout", "-q", "-b", "other", &base]);
+    repo.commit_file("other.txt", "other\n", "other");
+    let err = plan_interactive_rebase(&backend, repo.path(), "main").await.unwrap_err();
+    assert!(err.contains("not an ancestor"), "{}", err);
+}
+
+#[tokio::test]
+async fn edited_plan_is_applied_without_editor() {
+    let (repo, base) = history_repo();
+    let backend = CliBackend::default();
+    let mut plan = plan_interactive_rebase(&backend, repo.path(), &base).await.unwrap();
+
+    let entries = vec![
+        take(&mut plan, "add a", TodoAction::Reword, Some("Add the a file")),
+        take(&mut plan, "fix a", TodoAction::Fixup, None),
+        take(&mut plan, "add d", TodoAction::Pick, None),
+        take(&mut plan, "add b", TodoAction::Squash, Some("Add b and d\n\nBoth files are needed.")),
+    ];
+    let state = run_interactive_rebase(&backend, repo.path(), &base, &entries, &editor()).await.unwrap();
+
+    assert!(!state.in_progress);
+    assert_eq!(subjects(&repo, &base), "Add the a file\nAdd b and d\n");
+    assert_eq!(repo.git(&["log", "-1", "--format=%b"]).trim(), "Both files are needed.");
+    assert_eq!(repo.git(&["show", "HEAD~1:a.txt"]), "a fixed\n");
+    // 計画のファイルは残さない
+    assert!(!repo.exists(".git/zengit-rebase-plan.json"));
+}
+
+#[tokio::test]
+async fn dropped_commits_are_removed() {
+    let (repo, base) = history_repo();
+    let backend = CliBackend::default();
+    let mut plan = plan_interactive_rebase(&backend, repo.path(), &base).await.unwrap();
+    plan[1].action = TodoAction::Drop;
+
+    run_interactive_rebase(&backend, repo.path(), &base, &plan, &editor()).await.unwrap();
+    assert_eq!(subjects(&repo, &base), "add a\nfix a\nadd d\n");
+    assert!(!repo.exists("b.txt"));
+}
+
+#[tokio::test]
+async fn stale_or_invalid_plan_is_refused() {
+    let (repo, base) = history_repo();
+    let backend = CliBackend::default();
+    let mut plan = plan_interactive_rebase(&backend, repo.path(), &base).await.unwrap();
+
+    plan[0].action = TodoAction::Fixup;
+    let err = run_interactive_rebase(&backend, repo.path(), &base, &plan, &editor()).await.unwrap_err();
+    assert!(err.contains("no commit before it"), "{}", err);
+
+    // 計画を作った後にコミットされた場合
+    plan[0].action = TodoAction::Pick;
+    let head = repo.commit_file("e.txt", "e\n", "add e");
+    let err = run_interactive_rebase(&backend, repo.path(), &base, &plan, &editor()).await.unwrap_err();
+    assert!(err.contains("have changed"), "{}", err);
+    assert_eq!(repo.head(), head);
+}
+
+#[tokio::test]
+async fn reword_is_applied_after_resolving_conflict() {
+    let (repo, base) = history_repo();
+    let backend = CliBackend::default();
+    let mut plan = plan_interactive_rebase(&backend, repo.path(), &base).await.unwrap();
+
+    // "fix a"を"add a"より前にすると、a.txtがないので衝突する
+    let entries = vec![
+        take(&mut plan, "fix a", TodoAction::Reword, Some("Fix a before adding it")),
+        take(&mut plan, "add a", TodoAction::Pick, None),
+        take(&mut plan, "add b", TodoAction::Pick, None),
+        take(&mut plan, "add d", TodoAction::Pick, None),
+    ];
+    let state = run_interactive_rebase(&backend, repo.path(), &base, &entries, &editor()).await.unwrap();
+    assert!(state.in_progress);
+    assert_eq!(state.stopped_subject.as_deref(), Some("fix a"));
+    assert_eq!(state.conflicts, vec!["a.txt".to_string()]);
+
+    repo.write("a.txt", "a fixed\n");
+    repo.git(&["add", "a.txt"]);
+    continue_rebase(&backend, repo.path(), &editor()).await.unwrap();
+    assert_eq!(subjects(&repo, &base).lines().next(), Some("Fix a before adding it"));
+
+    abort_rebase(&backend, repo.path()).await.unwrap();
+    assert_eq!(subjects(&repo, &base), "add a\nadd b\nfix a\nadd d\n");
+}
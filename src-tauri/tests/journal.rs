@@ -0,0 +1,249 @@
+// 操作の記録と取り消しを、実際のgitで一時リポジトリを操作して確認する
+mod common;
+
+use std::path::Path;
+use common::TestRepo;
+use tempfile::TempDir;
+use zengit_lib::backend::{CliBackend, GitBackend};
+use zengit_lib::journal::{undo_last_operation, OperationJournal, OperationKind, PendingOperation};
+
+struct Fixture {
+    repo: TestRepo,
+    backend: CliBackend,
+    journal: OperationJournal,
+    _config_dir: TempDir,
+}
+
+impl Fixture {
+    fn new(repo: TestRepo) -> Self {
+        let config_dir = TempDir::new().unwrap();
+        Fixture {
+            repo,
+            backend: CliBackend::default(),
+            journal: OperationJournal::new(config_dir.path().to_path_buf()),
+            _config_dir: config_dir,
+        }
+    }
+
+    fn path(&self) -> &Path {
+        self.repo.path()
+    }
+
+    // アプリと同じように操作の前後を記録して実行する
+    async fn run(&self, kind: OperationKind, args: &[&str]) {
+        let pending = PendingOperation::begin(&self.backend, self.path(), kind).await.unwrap();
+        self.repo.git(args);
+        pending.finish(&self.backend, &self.journal).await.unwrap();
+    }
+
+    async fn discard(&self, files: &[&str]) {
+        let files: Vec<String> = files.iter().map(|v| v.to_string()).collect();
+        let mut pending = PendingOperation::begin(&self.backend, self.path(), OperationKind::Discard).await.unwrap();
+        pending.backup_files(&self.journal, &files).unwrap();
+        let (adds, others): (Vec<String>, Vec<String>) = files.into_iter().partition(|v| self.repo.git(&["ls-files", "--", v]).is_empty());
+        if !adds.is_empty() {
+            self.backend.clean_files(self.path(), adds).await.unwrap();
+        }
+        if !others.is_empty() {
+            self.backend.checkout_files(self.path(), others).await.unwrap();
+        }
+        pending.finish(&self.backend, &self.journal).await.unwrap();
+    }
+
+    async fn undo(&self) -> Result<String, String> {
+        undo_last_operation(&self.backend, &self.journal, self.path()).await
+    }
+
+    fn branches(&self) -> String {
+        self.repo.git(&["for-each-ref", "--format=%(refname:short)", "refs/heads"])
+    }
+}
+
+#[tokio::test]
+async fn undo_commit_keeps_changes_staged() {
+    let f = Fixture::new(TestRepo::new());
+    let first = f.repo.commit_file("a.txt", "a\n", "initial");
+    f.repo.write("a.txt", "changed\n");
+    f.repo.git(&["add", "a.txt"]);
+    f.run(OperationKind::Commit, &["commit", "-q", "-m", "second"]).await;
+
+    assert_eq!(f.undo().await.unwrap(), "Undid the last commit.");
+    assert_eq!(f.repo.head(), first);
+    assert_eq!(f.repo.git(&["diff", "--cached", "--name-only"]), "a.txt\n");
+    assert_eq!(f.repo.read("a.txt"), "changed\n");
+    assert_eq!(f.undo().await.unwrap_err(), "There is no operation to undo.");
+}
+
+#[tokio::test]
+async fn undo_first_commit_returns_to_unborn_branch() {
+    let f = Fixture::new(TestRepo::new());
+    f.repo.write("a.txt", "a\n");
+    f.repo.git(&["add", "a.txt"]);
+    f.run(OperationKind::Commit, &["commit", "-q", "-m", "initial"]).await;
+
+    f.undo().await.unwrap();
+    assert_eq!(f.branches(), "");
+    assert_eq!(f.repo.git(&["symbolic-ref", "HEAD"]).trim(), "refs/heads/main");
+    assert_eq!(f.repo.git(&["diff", "--cached", "--name-only"]), "a.txt\n");
+}
+
+#[tokio::test]
+async fn undo_amend_restores_original_commit() {
+    let f = Fixture::new(TestRepo::new());
+    let original = f.repo.commit_file("a.txt", "a\n", "initial");
+    f.run(OperationKind::Amend, &["commit", "-q", "--amend", "-m", "reworded"]).await;
+    assert_ne!(f.repo.head(), original);
+
+    f.undo().await.unwrap();
+    assert_eq!(f.repo.head(), original);
+}
+
+#[tokio::test]
+async fn undo_branch_creation_checks_out_previous_branch() {
+    let f = Fixture::new(TestRepo::new());
+    f.repo.commit_file("a.txt", "a\n", "initial");
+    f.run(OperationKind::BranchCreate, &["checkout", "-q", "-b", "feature"]).await;
+
+    f.undo().await.unwrap();
+    assert_eq!(f.repo.git(&["symbolic-ref", "--short", "HEAD"]).trim(), "main");
+    assert_eq!(f.branches(), "main\n");
+}
+
+#[tokio::test]
+async fn undo_branch_deletion_restores_branch() {
+    let f = Fixture::new(TestRepo::new());
+    f.repo.commit_file("a.txt", "a\n", "initial");
+    f.repo.git(&["checkout", "-q", "-b", "feature"]);
+    let feature = f.repo.commit_file("b.txt", "b\n", "feature");
+    f.repo.git(&["checkout", "-q", "main"]);
+    f.run(OperationKind::BranchDelete, &["branch", "-q", "-D", "feature"]).await;
+
+    f.undo().await.unwrap();
+    assert_eq!(f.repo.git(&["rev-parse", "feature"]).trim(), feature);
+}
+
+#[tokio::test]
+async fn undo_checkout_of_commit() {
+    let f = Fixture::new(TestRepo::new());
+    let first = f.repo.commit_file("a.txt", "a\n", "first");
+    f.repo.commit_file("a.txt", "b\n", "second");
+    f.run(OperationKind::Checkout, &["checkout", "-q", &first]).await;
+
+    f.undo().await.unwrap();
+    assert_eq!(f.repo.git(&["symbolic-ref", "--short", "HEAD"]).trim(), "main");
+    assert_eq!(f.repo.read("a.txt"), "b\n");
+}
+
+#[tokio::test]
+async fn undo_fast_forward_merge() {
+    let f = Fixture::new(TestRepo::new());
+    let first = f.repo.commit_file("a.txt", "a\n", "initial");
+    f.repo.git(&["checkout", "-q", "-b", "feature"]);
+    f.repo.commit_file("b.txt", "b\n", "feature");
+    f.repo.git(&["checkout", "-q", "main"]);
+    f.run(OperationKind::Merge, &["merge", "-q", "feature"]).await;
+
+    f.undo().await.unwrap();
+    assert_eq!(f.repo.head(), first);
+    assert!(!f.repo.exists("b.txt"));
+}
+
+#[tokio::test]
+async fn operation_without_changes_is_not_recorded() {
+    let f = Fixture::new(TestRepo::new());
+    f.repo.commit_file("a.txt", "a\n", "initial");
+    f.run(OperationKind::Commit, &["commit", "-q", "--allow-empty", "-m", "second"]).await;
+    f.run(OperationKind::Merge, &["merge", "-q", "main"]).await;
+
+    // 既に最新だったマージではなく、その前のコミットを戻す
+    assert_eq!(f.undo().await.unwrap(), "Undid the last commit.");
+}
+
+#[tokio::test]
+async fn undo_discard_restores_files() {
+    let f = Fixture::new(TestRepo::new());
+    f.repo.commit_file("a.txt", "a\n", "initial");
+    f.repo.commit_file("gone.txt", "gone\n", "gone");
+    f.repo.write("a.txt", "local change\r\n");
+    f.repo.write("new.txt", "untracked\n");
+    std::fs::remove_file(f.path().join("gone.txt")).unwrap();
+    f.discard(&["a.txt", "new.txt", "gone.txt"]).await;
+    assert_eq!(f.repo.read("a.txt"), "a\n");
+    assert!(!f.repo.exists("new.txt"));
+    assert!(f.repo.exists("gone.txt"));
+
+    f.undo().await.unwrap();
+    assert_eq!(f.repo.read("a.txt"), "local change\r\n");
+    assert_eq!(f.repo.read("new.txt"), "untracked\n");
+    assert!(!f.repo.exists("gone.txt"));
+}
+
+#[tokio::test]
+async fn discard_is_not_undone_over_newer_changes() {
+    let f = Fixture::new(TestRepo::new());
+    f.repo.commit_file("a.txt", "a\n", "initial");
+    f.repo.write("a.txt", "discarded\n");
+    f.discard(&["a.txt"]).await;
+    f.repo.write("a.txt", "newer\n");
+
+    let err = f.undo().await.unwrap_err();
+    assert!(err.contains("'a.txt' has been changed"), "{}", err);
+    assert_eq!(f.repo.read("a.txt"), "newer\n");
+}
+
+#[tokio::test]
+async fn undo_is_refused_after_other_changes() {
+    let f = Fixture::new(TestRepo::new());
+    f.repo.commit_file("a.txt", "a\n", "initial");
+    f.run(OperationKind::BranchCreate, &["checkout", "-q", "-b", "feature"]).await;
+    // アプリの外でのコミット
+    let outside = f.repo.commit_file("b.txt", "b\n", "outside");
+
+    let err = f.undo().await.unwrap_err();
+    assert_eq!(err, "The repository has changed since the last branch creation, so it can't be undone safely.");
+    assert_eq!(f.repo.head(), outside);
+}
+
+#[tokio::test]
+async fn undo_is_refused_after_leaving_and_returning() {
+    let f = Fixture::new(TestRepo::new());
+    f.repo.commit_file("a.txt", "a\n", "initial");
+    f.run(OperationKind::BranchCreate, &["checkout", "-q", "-b", "feature"]).await;
+    // アプリの外で移動して戻ってきた場合は、ブランチとHEADが同じでもreflogの最新の記録が変わる
+    f.repo.git(&["checkout", "-q", "--detach"]);
+    f.repo.git(&["checkout", "-q", "feature"]);
+
+    let err = f.undo().await.unwrap_err();
+    assert_eq!(err, "The repository has changed since the last branch creation, so it can't be undone safely.");
+}
+
+#[tokio::test]
+async fn undo_is_refused_when_commit_was_pushed() {
+    let origin = TestRepo::new();
+    origin.commit_file("a.txt", "a\n", "initial");
+    origin.git(&["config", "receive.denyCurrentBranch", "ignore"]);
+    let f = Fixture::new(TestRepo::clone_from(&origin));
+    f.repo.git(&["checkout", "-q", "-B", "main", "origin/main"]);
+    f.repo.write("b.txt", "b\n");
+    f.repo.git(&["add", "b.txt"]);
+    f.run(OperationKind::Commit, &["commit", "-q", "-m", "second"]).await;
+    let head = f.repo.head();
+
+    // アプリのpushは記録されるので、直前の操作がpushであることを伝える
+    f.run(OperationKind::Push, &["push", "-q", "origin", "HEAD:main"]).await;
+    let err = f.undo().await.unwrap_err();
+    assert!(err.starts_with("The last operation was a push."), "{}", err);
+
+    // アプリの外でpushした場合は、コミットがupstreamに含まれていることで判断する
+    let journal_dir = TempDir::new().unwrap();
+    let f = Fixture { journal: OperationJournal::new(journal_dir.path().to_path_buf()), ..f };
+    f.repo.write("c.txt", "c\n");
+    f.repo.git(&["add", "c.txt"]);
+    f.run(OperationKind::Commit, &["commit", "-q", "-m", "third"]).await;
+    f.repo.git(&["push", "-q", "origin", "HEAD:main"]);
+    f.repo.git(&["fetch", "-q"]);
+
+    let err = f.undo().await.unwrap_err();
+    assert!(err.contains("has already been pushed to origin/main"), "{}", err);
+    assert_ne!(f.repo.head(), head);
+}
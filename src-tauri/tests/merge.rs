@@ -0,0 +1,118 @@
+// マージの方法と結果の種類を、実際のgitで一時リポジトリを操作して確認する
+mod common;
+
+use common::TestRepo;
+use zengit_lib::backend::CliBackend;
+use zengit_lib::merge::{merge_branch, MergeMode, MergeOptions, MergeOutcomeKind};
+
+// mainから分岐したfeatureにコミットする (divergedならmainにもコミットする)
+fn branched_repo(diverged: bool) -> TestRepo {
+    let repo = TestRepo::new();
+    repo.commit_file("a.txt", "a\n", "initial");
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.commit_file("b.txt", "b\n", "feature");
+    repo.git(&["checkout", "-q", "main"]);
+    if diverged {
+        repo.commit_file("c.txt", "c\n", "main");
+    }
+    repo
+}
+
+fn options(mode: MergeMode, message: &str) -> MergeOptions {
+    MergeOptions { mode, message: message.to_string() }
+}
+
+fn parent_count(repo: &TestRepo) -> usize {
+    repo.git(&["rev-list", "--parents", "-n", "1", "HEAD"]).split_whitespace().count() - 1
+}
+
+#[tokio::test]
+async fn fast_forward_and_up_to_date() {
+    let repo = branched_repo(false);
+    let backend = CliBackend::default();
+
+    let outcome = merge_branch(&backend, repo.path(), "feature".to_string(), &MergeOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, MergeOutcomeKind::FastForward);
+    assert_eq!(outcome.head, Some(repo.git(&["rev-parse", "feature"]).trim().to_string()));
+
+    let outcome = merge_branch(&backend, repo.path(), "feature".to_string(), &MergeOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, MergeOutcomeKind::UpToDate);
+}
+
+#[tokio::test]
+async fn fast_forward_onto_merge_commit() {
+    // featureの先頭がマージコミットで、mainはそこからfast-forwardできる
+    let repo = branched_repo(true);
+    repo.git(&["checkout", "-q", "feature"]);
+    repo.git(&["merge", "-q", "--no-edit", "main"]);
+    repo.git(&["checkout", "-q", "main"]);
+    let backend = CliBackend::default();
+
+    let outcome = merge_branch(&backend, repo.path(), "feature".to_string(), &MergeOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, MergeOutcomeKind::FastForward);
+    assert_eq!(outcome.head, Some(repo.git(&["rev-parse", "feature"]).trim().to_string()));
+    assert_eq!(parent_count(&repo), 2);
+}
+
+#[tokio::test]
+async fn no_fast_forward_creates_merge_commit_with_message() {
+    let repo = branched_repo(false);
+    let backend = CliBackend::default();
+
+    let outcome = merge_branch(&backend, repo.path(), "feature".to_string(), &options(MergeMode::NoFastForward, "Merge the feature")).await.unwrap();
+    assert_eq!(outcome.kind, MergeOutcomeKind::MergeCommit);
+    assert_eq!(parent_count(&repo), 2);
+    assert_eq!(repo.git(&["log", "-1", "--format=%s"]).trim(), "Merge the feature");
+}
+
+#[tokio::test]
+async fn fast_forward_only_fails_when_diverged() {
+    let repo = branched_repo(true);
+    let backend = CliBackend::default();
+    let head = repo.head();
+
+    assert!(merge_branch(&backend, repo.path(), "feature".to_string(), &options(MergeMode::FastForwardOnly, "")).await.is_err());
+    assert_eq!(repo.head(), head);
+
+    let outcome = merge_branch(&backend, repo.path(), "feature".to_string(), &MergeOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, MergeOutcomeKind::MergeCommit);
+}
+
+#[tokio::test]
+async fn squash_stages_changes_or_commits_with_message() {
+    let repo = branched_repo(true);
+    let backend = CliBackend::default();
+    let head = repo.head();
+
+    let outcome = merge_branch(&backend, repo.path(), "feature".to_string(), &options(MergeMode::Squash, "")).await.unwrap();
+    assert_eq!(outcome.kind, MergeOutcomeKind::Squashed);
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "b.txt\n");
+
+    repo.git(&["reset", "-q", "--hard"]);
+    let outcome = merge_branch(&backend, repo.path(), "feature".to_string(), &options(MergeMode::Squash, "Squashed feature")).await.unwrap();
+    assert_eq!(outcome.kind, MergeOutcomeKind::Squashed);
+    assert_eq!(outcome.head, Some(repo.head()));
+    assert_eq!(parent_count(&repo), 1);
+    assert_eq!(repo.git(&["log", "-1", "--format=%s"]).trim(), "Squashed feature");
+}
+
+#[tokio::test]
+async fn conflicts_are_returned_as_outcome() {
+    let repo = TestRepo::new();
+    repo.commit_file("conflict.txt", "base\n", "initial");
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.commit_file("conflict.txt", "feature\n", "feature");
+    repo.git(&["checkout", "-q", "main"]);
+    let head = repo.commit_file("conflict.txt", "main\n", "main");
+    let backend = CliBackend::default();
+
+    let outcome = merge_branch(&backend, repo.path(), "feature".to_string(), &MergeOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, MergeOutcomeKind::Conflicts);
+    assert_eq!(outcome.conflicts, vec!["conflict.txt".to_string()]);
+    assert_eq!(outcome.head, Some(head));
+
+    // 衝突を解消するまでは次のマージをしない
+    let err = merge_branch(&backend, repo.path(), "feature".to_string(), &MergeOptions::default()).await.unwrap_err();
+    assert!(err.contains("Resolve the current conflicts"), "{}", err);
+}
@@ -0,0 +1,116 @@
+// リベースと状態の取得を、実際のgitで一時リポジトリを操作して確認する
+mod common;
+
+use common::TestRepo;
+use tempfile::TempDir;
+use zengit_lib::backend::CliBackend;
+use zengit_lib::interactive_rebase::RebaseEditor;
+use zengit_lib::journal::{undo_last_operation, OperationJournal, OperationKind};
+use zengit_lib::rebase::{abort_rebase, continue_rebase, rebase_onto, rebase_state, run_journaled, skip_rebase};
+
+// mainから分岐したfeatureに2つコミットし、mainにもコミットする
+// conflictの場合は1つ目のコミットがmainとぶつかる
+fn diverged_repo(conflict: bool) -> TestRepo {
+    let repo = TestRepo::new();
+    repo.commit_file("a.txt", "base\n", "initial");
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.commit_file(if conflict { "a.txt" } else { "b.txt" }, "feature\n", "feature 1");
+    repo.commit_file("c.txt", "c\n", "feature 2");
+    repo.git(&["checkout", "-q", "main"]);
+    repo.commit_file("a.txt", "main\n", "main");
+    repo.git(&["checkout", "-q", "feature"]);
+    repo
+}
+
+#[tokio::test]
+async fn rebase_without_conflicts() {
+    let repo = diverged_repo(false);
+    let backend = CliBackend::default();
+    let main = repo.git(&["rev-parse", "main"]).trim().to_string();
+
+    let state = rebase_onto(&backend, repo.path(), "main".to_string()).await.unwrap();
+    assert!(!state.in_progress);
+    assert_eq!(repo.git(&["rev-parse", "HEAD~2"]).trim(), main);
+    assert_eq!(repo.git(&["symbolic-ref", "--short", "HEAD"]).trim(), "feature");
+
+    assert!(rebase_onto(&backend, repo.path(), "missing".to_string()).await.is_err());
+}
+
+#[tokio::test]
+async fn stopped_rebase_is_detected_and_continued() {
+    let repo = diverged_repo(true);
+    let backend = CliBackend::default();
+
+    let state = rebase_onto(&backend, repo.path(), "main".to_string()).await.unwrap();
+    assert!(state.in_progress);
+    assert_eq!(state.branch_name.as_deref(), Some("feature"));
+    assert_eq!(state.onto, Some(repo.git(&["rev-parse", "main"]).trim().to_string()));
+    assert_eq!(state.stopped_subject.as_deref(), Some("feature 1"));
+    assert_eq!((state.current_step, state.total_steps), (Some(1), Some(2)));
+    assert_eq!(state.conflicts, vec!["a.txt".to_string()]);
+    assert_eq!(rebase_state(&backend, repo.path()).await.unwrap(), state);
+
+    let err = continue_rebase(&backend, repo.path(), &RebaseEditor::default()).await.unwrap_err();
+    assert!(err.contains("a.txt"), "{}", err);
+    assert!(rebase_onto(&backend, repo.path(), "main".to_string()).await.is_err());
+
+    repo.write("a.txt", "main and feature\n");
+    repo.git(&["add", "a.txt"]);
+    let state = continue_rebase(&backend, repo.path(), &RebaseEditor::default()).await.unwrap();
+    assert!(!state.in_progress);
+    assert_eq!(repo.git(&["log", "--format=%s", "main.."]), "feature 2\nfeature 1\n");
+}
+
+#[tokio::test]
+async fn skip_drops_the_stopped_commit() {
+    let repo = diverged_repo(true);
+    let backend = CliBackend::default();
+    rebase_onto(&backend, repo.path(), "main".to_string()).await.unwrap();
+
+    let state = skip_rebase(&backend, repo.path()).await.unwrap();
+    assert!(!state.in_progress);
+    assert_eq!(repo.git(&["log", "--format=%s", "main.."]), "feature 2\n");
+    assert_eq!(repo.read("a.txt"), "main\n");
+}
+
+#[tokio::test]
+async fn abort_returns_to_original_branch() {
+    let repo = diverged_repo(true);
+    let backend = CliBackend::default();
+    let head = repo.head();
+    rebase_onto(&backend, repo.path(), "main".to_string()).await.unwrap();
+
+    let state = abort_rebase(&backend, repo.path()).await.unwrap();
+    assert!(!state.in_progress);
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.git(&["symbolic-ref", "--short", "HEAD"]).trim(), "feature");
+}
+
+#[tokio::test]
+async fn finished_rebase_is_journaled_and_can_be_undone() {
+    let repo = diverged_repo(false);
+    let backend = CliBackend::default();
+    let config_dir = TempDir::new().unwrap();
+    let journal = OperationJournal::new(config_dir.path().to_path_buf());
+    let head = repo.head();
+
+    run_journaled(&backend, &journal, repo.path(), rebase_onto(&backend, repo.path(), "main".to_string())).await.unwrap();
+    assert_ne!(repo.head(), head);
+    assert_eq!(journal.last(repo.path()).unwrap().kind, OperationKind::Rebase);
+
+    undo_last_operation(&backend, &journal, repo.path()).await.unwrap();
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.git(&["symbolic-ref", "--short", "HEAD"]).trim(), "feature");
+}
+
+#[tokio::test]
+async fn stopped_rebase_is_not_journaled() {
+    let repo = diverged_repo(true);
+    let backend = CliBackend::default();
+    let config_dir = TempDir::new().unwrap();
+    let journal = OperationJournal::new(config_dir.path().to_path_buf());
+
+    let state = run_journaled(&backend, &journal, repo.path(), rebase_onto(&backend, repo.path(), "main".to_string())).await.unwrap();
+    assert!(state.in_progress);
+    assert!(journal.last(repo.path()).is_none());
+}
@@ -0,0 +1,129 @@
+// リセットとその確認内容を、実際のgitで一時リポジトリを操作して確認する
+mod common;
+
+use common::TestRepo;
+use tempfile::TempDir;
+use zengit_lib::backend::CliBackend;
+use zengit_lib::journal::{undo_last_operation, OperationJournal, PendingOperation};
+use zengit_lib::reset::{preview_reset, reset_to, ResetMode};
+
+// initial → second → third のリポジトリ (initialのハッシュを返す)
+fn three_commits() -> (TestRepo, String) {
+    let repo = TestRepo::new();
+    let initial = repo.commit_file("a.txt", "a\n", "initial");
+    repo.commit_file("a.txt", "a2\n", "second");
+    repo.commit_file("b.txt", "b\n", "third");
+    (repo, initial)
+}
+
+// アプリと同じように、--hardで失われるファイルを退避しながらリセットする
+async fn journaled_reset(backend: &CliBackend, journal: &OperationJournal, repo: &TestRepo, target: &str, mode: ResetMode) {
+    let preview = preview_reset(backend, repo.path(), target, mode).await.unwrap();
+    let mut pending = PendingOperation::begin(backend, repo.path(), mode.operation_kind()).await.unwrap();
+    pending.backup_files(journal, &preview.lost_files).unwrap();
+    reset_to(backend, repo.path(), target, mode).await.unwrap();
+    pending.finish(backend, journal).await.unwrap();
+}
+
+#[tokio::test]
+async fn preview_lists_commits_that_become_unreachable() {
+    let (repo, initial) = three_commits();
+    let backend = CliBackend::default();
+
+    let preview = preview_reset(&backend, repo.path(), &initial, ResetMode::Mixed).await.unwrap();
+    assert_eq!(preview.target, initial);
+    assert_eq!(preview.target_subject, "initial");
+    assert_eq!(preview.branch_name.as_deref(), Some("main"));
+    let subjects: Vec<&str> = preview.unreachable_commits.iter().map(|v| v.subject.as_str()).collect();
+    assert_eq!(subjects, vec!["third", "second"]);
+    assert!(preview.lost_files.is_empty());
+
+    // 他のブランチから辿れるコミットは失われない
+    repo.git(&["branch", "keep", "HEAD~1"]);
+    let preview = preview_reset(&backend, repo.path(), &initial, ResetMode::Mixed).await.unwrap();
+    let subjects: Vec<&str> = preview.unreachable_commits.iter().map(|v| v.subject.as_str()).collect();
+    assert_eq!(subjects, vec!["third"]);
+
+    assert!(preview_reset(&backend, repo.path(), "no-such-commit", ResetMode::Soft).await.is_err());
+}
+
+#[tokio::test]
+async fn preview_of_hard_reset_lists_lost_files() {
+    let (repo, initial) = three_commits();
+    let backend = CliBackend::default();
+    repo.write("a.txt", "unstaged\n");
+    repo.write("c.txt", "staged\n");
+    repo.git(&["add", "c.txt"]);
+    repo.write("untracked.txt", "kept\n");
+    std::fs::remove_file(repo.path().join("b.txt")).unwrap();
+
+    let preview = preview_reset(&backend, repo.path(), &initial, ResetMode::Hard).await.unwrap();
+    assert_eq!(preview.lost_files, vec!["a.txt", "b.txt", "c.txt"]);
+
+    // 移動先で管理されている未追跡のファイルは上書きされる
+    repo.git(&["rm", "-q", "--cached", "a.txt"]);
+    let preview = preview_reset(&backend, repo.path(), &initial, ResetMode::Hard).await.unwrap();
+    assert!(preview.lost_files.contains(&"a.txt".to_string()));
+    assert!(!preview.lost_files.contains(&"untracked.txt".to_string()));
+}
+
+#[tokio::test]
+async fn reset_modes_keep_or_discard_changes() {
+    let (repo, initial) = three_commits();
+    let backend = CliBackend::default();
+    let head = repo.head();
+
+    reset_to(&backend, repo.path(), &initial, ResetMode::Soft).await.unwrap();
+    assert_eq!(repo.head(), initial);
+    assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "a.txt\nb.txt\n");
+
+    repo.git(&["reset", "-q", "--hard", &head]);
+    reset_to(&backend, repo.path(), &initial, ResetMode::Mixed).await.unwrap();
+    assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "");
+    assert_eq!(repo.read("a.txt"), "a2\n");
+
+    repo.git(&["reset", "-q", "--hard", &head]);
+    reset_to(&backend, repo.path(), &initial, ResetMode::Hard).await.unwrap();
+    assert_eq!(repo.read("a.txt"), "a\n");
+    assert!(!repo.exists("b.txt"));
+}
+
+#[tokio::test]
+async fn undo_restores_branch_for_each_mode() {
+    let (repo, initial) = three_commits();
+    let backend = CliBackend::default();
+    let journal_dir = TempDir::new().unwrap();
+    let journal = OperationJournal::new(journal_dir.path().to_path_buf());
+    let head = repo.head();
+
+    for mode in [ResetMode::Soft, ResetMode::Mixed, ResetMode::Hard] {
+        journaled_reset(&backend, &journal, &repo, &initial, mode).await;
+        assert_eq!(repo.head(), initial);
+
+        undo_last_operation(&backend, &journal, repo.path()).await.unwrap();
+        assert_eq!(repo.head(), head);
+        assert_eq!(repo.git(&["status", "--porcelain"]), "", "{:?}", mode);
+    }
+}
+
+#[tokio::test]
+async fn undo_of_hard_reset_restores_discarded_changes() {
+    let (repo, initial) = three_commits();
+    let backend = CliBackend::default();
+    let journal_dir = TempDir::new().unwrap();
+    let journal = OperationJournal::new(journal_dir.path().to_path_buf());
+    let head = repo.head();
+    repo.write("a.txt", "local change\n");
+    repo.write("c.txt", "new file\n");
+    repo.git(&["add", "c.txt"]);
+
+    journaled_reset(&backend, &journal, &repo, &initial, ResetMode::Hard).await;
+    assert_eq!(repo.read("a.txt"), "a\n");
+    assert!(!repo.exists("c.txt"));
+
+    let message = undo_last_operation(&backend, &journal, repo.path()).await.unwrap();
+    assert_eq!(message, "Undid the last hard reset.");
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.read("a.txt"), "local change\n");
+    assert_eq!(repo.read("c.txt"), "new file\n");
+}
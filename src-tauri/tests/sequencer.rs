@@ -0,0 +1,152 @@
+// cherry-pickとrevertを、実際のgitで一時リポジトリを操作して確認する
+mod common;
+
+use common::TestRepo;
+use zengit_lib::backend::CliBackend;
+use zengit_lib::sequencer::{abort_sequence, apply_commits, continue_sequence, sequencer_state, skip_sequence, PickOptions,
+                            PickOutcomeKind, SequencerCommand};
+
+// featureに2つコミットしたリポジトリ (mainはinitialのまま)
+fn feature_repo() -> (TestRepo, Vec<String>) {
+    let repo = TestRepo::new();
+    repo.commit_file("a.txt", "a\n", "initial");
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    let first = repo.commit_file("b.txt", "b\n", "add b");
+    let second = repo.commit_file("a.txt", "a from feature\n", "change a");
+    repo.git(&["checkout", "-q", "main"]);
+    (repo, vec![first, second])
+}
+
+fn subjects(repo: &TestRepo) -> String {
+    repo.git(&["log", "--format=%s"])
+}
+
+#[tokio::test]
+async fn cherry_pick_applies_commits_in_order() {
+    let (repo, hashes) = feature_repo();
+    let backend = CliBackend::default();
+
+    // 新しい順に渡しても古い順に適用される
+    let reversed: Vec<String> = hashes.iter().rev().cloned().collect();
+    let options = PickOptions { record_origin: true, ..Default::default() };
+    let outcome = apply_commits(&backend, repo.path(), SequencerCommand::CherryPick, &reversed, &options).await.unwrap();
+
+    assert_eq!(outcome.kind, PickOutcomeKind::Applied);
+    assert_eq!(outcome.created.len(), 2);
+    assert_eq!(outcome.created.last(), Some(&repo.head()));
+    assert!(!outcome.state.in_progress());
+    assert_eq!(subjects(&repo), "change a\nadd b\ninitial\n");
+    assert!(repo.git(&["log", "-1", "--format=%b"]).contains(&format!("(cherry picked from commit {})", hashes[1])));
+}
+
+#[tokio::test]
+async fn commits_without_common_ancestor_are_applied() {
+    let (repo, hashes) = feature_repo();
+    repo.git(&["checkout", "-q", "--orphan", "other"]);
+    repo.git(&["rm", "-q", "-r", "--cached", "."]);
+    let other = repo.commit_file("c.txt", "c\n", "add c");
+    repo.git(&["checkout", "-q", "-f", "main"]);
+    let backend = CliBackend::default();
+
+    // 共通の祖先がない場合は履歴全体から順番を決める
+    let selected = vec![other, hashes[0].clone()];
+    let outcome = apply_commits(&backend, repo.path(), SequencerCommand::CherryPick, &selected, &PickOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, PickOutcomeKind::Applied);
+    assert_eq!(outcome.created.len(), 2);
+    assert_eq!(repo.read("b.txt"), "b\n");
+    assert_eq!(repo.read("c.txt"), "c\n");
+}
+
+#[tokio::test]
+async fn revert_creates_inverse_commit() {
+    let (repo, _) = feature_repo();
+    repo.git(&["checkout", "-q", "feature"]);
+    let backend = CliBackend::default();
+
+    let outcome = apply_commits(&backend, repo.path(), SequencerCommand::Revert, &["HEAD".to_string()], &PickOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, PickOutcomeKind::Applied);
+    assert_eq!(repo.read("a.txt"), "a\n");
+    assert!(subjects(&repo).starts_with("Revert \"change a\"\n"));
+}
+
+#[tokio::test]
+async fn merge_commit_requires_mainline() {
+    let (repo, _) = feature_repo();
+    repo.commit_file("c.txt", "c\n", "main");
+    repo.git(&["merge", "-q", "--no-ff", "--no-edit", "feature"]);
+    let merge = repo.head();
+    let backend = CliBackend::default();
+
+    let hashes = vec![merge.clone()];
+    let err = apply_commits(&backend, repo.path(), SequencerCommand::Revert, &hashes, &PickOptions::default()).await.unwrap_err();
+    assert_eq!(err, format!("{} is a merge commit. Choose the parent to use as the mainline.", merge));
+
+    let options = PickOptions { mainline: Some(1), ..Default::default() };
+    let outcome = apply_commits(&backend, repo.path(), SequencerCommand::Revert, &hashes, &options).await.unwrap();
+    assert_eq!(outcome.kind, PickOutcomeKind::Applied);
+    assert!(!repo.exists("b.txt"));
+    assert!(repo.exists("c.txt"));
+}
+
+#[tokio::test]
+async fn conflict_stops_and_can_be_continued() {
+    let (repo, hashes) = feature_repo();
+    repo.commit_file("a.txt", "a from main\n", "change a on main");
+    let backend = CliBackend::default();
+
+    let outcome = apply_commits(&backend, repo.path(), SequencerCommand::CherryPick, &hashes, &PickOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, PickOutcomeKind::Conflicts);
+    assert_eq!(outcome.created.len(), 1);
+    assert_eq!(outcome.state.command, Some(SequencerCommand::CherryPick));
+    assert_eq!(outcome.state.stopped_at.as_deref(), Some(hashes[1].as_str()));
+    assert_eq!(outcome.state.stopped_subject.as_deref(), Some("change a"));
+    assert_eq!(outcome.state.conflicts, vec!["a.txt".to_string()]);
+    assert_eq!(sequencer_state(&backend, repo.path()).await.unwrap(), outcome.state);
+
+    let err = apply_commits(&backend, repo.path(), SequencerCommand::Revert, &hashes, &PickOptions::default()).await.unwrap_err();
+    assert!(err.contains("cherry-pick is already in progress"), "{}", err);
+    assert!(continue_sequence(&backend, repo.path()).await.is_err());
+
+    repo.write("a.txt", "a from both\n");
+    repo.git(&["add", "a.txt"]);
+    let outcome = continue_sequence(&backend, repo.path()).await.unwrap();
+    assert_eq!(outcome.kind, PickOutcomeKind::Applied);
+    assert_eq!(subjects(&repo), "change a\nadd b\nchange a on main\ninitial\n");
+}
+
+#[tokio::test]
+async fn stopped_sequence_can_be_skipped_or_aborted() {
+    let (repo, hashes) = feature_repo();
+    repo.commit_file("a.txt", "a from main\n", "change a on main");
+    let head = repo.head();
+    let backend = CliBackend::default();
+
+    let reversed: Vec<String> = hashes.iter().rev().cloned().collect();
+    apply_commits(&backend, repo.path(), SequencerCommand::CherryPick, &hashes, &PickOptions::default()).await.unwrap();
+    let outcome = skip_sequence(&backend, repo.path()).await.unwrap();
+    assert_eq!(outcome.kind, PickOutcomeKind::Applied);
+    assert_eq!(subjects(&repo), "add b\nchange a on main\ninitial\n");
+
+    repo.git(&["reset", "-q", "--hard", &head]);
+    let outcome = apply_commits(&backend, repo.path(), SequencerCommand::CherryPick, &reversed, &PickOptions::default()).await.unwrap();
+    assert_eq!(outcome.state.remaining, 0);
+    let state = abort_sequence(&backend, repo.path()).await.unwrap();
+    assert!(!state.in_progress());
+    assert_eq!(repo.head(), head);
+    assert!(abort_sequence(&backend, repo.path()).await.is_err());
+}
+
+#[tokio::test]
+async fn already_applied_change_stops_as_empty() {
+    let (repo, hashes) = feature_repo();
+    repo.commit_file("b.txt", "b\n", "add b on main");
+    let backend = CliBackend::default();
+
+    let outcome = apply_commits(&backend, repo.path(), SequencerCommand::CherryPick, &hashes, &PickOptions::default()).await.unwrap();
+    assert_eq!(outcome.kind, PickOutcomeKind::Empty);
+    assert_eq!(outcome.state.remaining, 1);
+
+    let outcome = skip_sequence(&backend, repo.path()).await.unwrap();
+    assert_eq!(outcome.kind, PickOutcomeKind::Applied);
+    assert_eq!(repo.read("a.txt"), "a from feature\n");
+}
@@ -0,0 +1,133 @@
+// stashの作成・適用と内容の表示を、実際のgitで一時リポジトリを操作して確認する
+mod common;
+
+use common::TestRepo;
+use zengit_lib::backend::CliBackend;
+use zengit_lib::diff_options::DiffOptions;
+use zengit_lib::stash::{apply_stash, create_stash, drop_stash, list_stashes, stash_file_diff, stash_files, StashOptions};
+use zengit_lib::status::{ChangeState, StatusInfo};
+
+fn changed_repo() -> TestRepo {
+    let repo = TestRepo::new();
+    repo.commit_file("a.txt", "a\n", "initial");
+    repo.commit_file("b.txt", "b\n", "second");
+    repo.write("a.txt", "a changed\n");
+    repo.write("b.txt", "b changed\n");
+    repo.write("new.txt", "untracked\n");
+    repo
+}
+
+fn status(repo: &TestRepo) -> String {
+    repo.git(&["status", "--porcelain"])
+}
+
+#[tokio::test]
+async fn create_and_list_stashes() {
+    let repo = changed_repo();
+    let backend = CliBackend::default();
+
+    let first = create_stash(&backend, repo.path(), &StashOptions::default()).await.unwrap();
+    assert_eq!(first.name, "stash@{0}");
+    assert_eq!(first.branch.as_deref(), Some("main"));
+    assert!(first.message.ends_with("second"), "{}", first.message);
+    // 未追跡のファイルは含めていない
+    assert_eq!(status(&repo), "?? new.txt\n");
+
+    let options = StashOptions { message: "with untracked".to_string(), include_untracked: true, ..Default::default() };
+    let second = create_stash(&backend, repo.path(), &options).await.unwrap();
+    assert_eq!(second.message, "with untracked");
+    assert_eq!(status(&repo), "");
+
+    let stashes = list_stashes(&backend, repo.path(), "%Y").await.unwrap();
+    let hashes: Vec<&str> = stashes.iter().map(|v| v.hash.as_str()).collect();
+    assert_eq!(hashes, vec![second.hash.as_str(), first.hash.as_str()]);
+    assert_eq!(stashes[1].name, "stash@{1}");
+    assert_eq!(stashes[0].date.len(), 4);
+
+    let err = create_stash(&backend, repo.path(), &StashOptions::default()).await.unwrap_err();
+    assert_eq!(err, "No local changes to save.");
+}
+
+#[tokio::test]
+async fn stash_selected_files_or_keep_index() {
+    let repo = changed_repo();
+    let backend = CliBackend::default();
+
+    let options = StashOptions {
+        files: vec![StatusInfo::with_state("a.txt".to_string(), ChangeState::Change)],
+        ..Default::default()
+    };
+    create_stash(&backend, repo.path(), &options).await.unwrap();
+    assert_eq!(repo.read("a.txt"), "a\n");
+    assert_eq!(repo.read("b.txt"), "b changed\n");
+
+    repo.git(&["add", "b.txt"]);
+    let options = StashOptions { keep_index: true, ..Default::default() };
+    create_stash(&backend, repo.path(), &options).await.unwrap();
+    assert_eq!(status(&repo), "M  b.txt\n?? new.txt\n");
+}
+
+#[tokio::test]
+async fn apply_pop_and_drop() {
+    let repo = changed_repo();
+    let backend = CliBackend::default();
+    let first = create_stash(&backend, repo.path(), &StashOptions::default()).await.unwrap();
+    repo.write("b.txt", "b again\n");
+    let second = create_stash(&backend, repo.path(), &StashOptions::default()).await.unwrap();
+
+    // 番号がずれていても、hashで指定したstashを適用する
+    let outcome = apply_stash(&backend, repo.path(), &first.hash, false).await.unwrap();
+    assert!(!outcome.dropped);
+    assert!(outcome.conflicts.is_empty());
+    assert_eq!(repo.read("a.txt"), "a changed\n");
+    assert_eq!(list_stashes(&backend, repo.path(), "%Y").await.unwrap().len(), 2);
+
+    repo.git(&["checkout", "--", "."]);
+    let outcome = apply_stash(&backend, repo.path(), &first.hash, true).await.unwrap();
+    assert!(outcome.dropped);
+    let stashes = list_stashes(&backend, repo.path(), "%Y").await.unwrap();
+    assert_eq!(stashes.len(), 1);
+    assert_eq!(stashes[0].hash, second.hash);
+
+    drop_stash(&backend, repo.path(), &second.hash).await.unwrap();
+    assert!(list_stashes(&backend, repo.path(), "%Y").await.unwrap().is_empty());
+    let err = drop_stash(&backend, repo.path(), &second.hash).await.unwrap_err();
+    assert_eq!(err, "The stash no longer exists.");
+}
+
+#[tokio::test]
+async fn conflicting_pop_keeps_stash() {
+    let repo = changed_repo();
+    let backend = CliBackend::default();
+    let stash = create_stash(&backend, repo.path(), &StashOptions::default()).await.unwrap();
+    repo.commit_file("a.txt", "a committed\n", "conflicting");
+
+    let outcome = apply_stash(&backend, repo.path(), &stash.hash, true).await.unwrap();
+    assert!(!outcome.dropped);
+    assert_eq!(outcome.conflicts, vec!["a.txt".to_string()]);
+    assert_eq!(list_stashes(&backend, repo.path(), "%Y").await.unwrap().len(), 1);
+
+    let err = apply_stash(&backend, repo.path(), &stash.hash, false).await.unwrap_err();
+    assert!(err.starts_with("Resolve the current conflicts"), "{}", err);
+}
+
+#[tokio::test]
+async fn show_stash_files_and_diff() {
+    let repo = changed_repo();
+    let backend = CliBackend::default();
+    let options = StashOptions { include_untracked: true, ..Default::default() };
+    let stash = create_stash(&backend, repo.path(), &options).await.unwrap();
+
+    let files = stash_files(&backend, repo.path(), &stash.hash).await.unwrap();
+    let files: Vec<(&str, ChangeState)> = files.iter().map(|v| (v.filename.as_str(), v.change_state)).collect();
+    assert_eq!(files, vec![("a.txt", ChangeState::Change), ("b.txt", ChangeState::Change), ("new.txt", ChangeState::Untracked)]);
+
+    let diff_options = DiffOptions::default();
+    let diff = stash_file_diff(&backend, repo.path(), &stash.hash, "a.txt".to_string(), &diff_options).await.unwrap();
+    assert_eq!(diff.files.len(), 1);
+    assert!(diff.files[0].hunks[0].lines.iter().any(|v| v.content == "a changed"));
+
+    let diff = stash_file_diff(&backend, repo.path(), &stash.hash, "new.txt".to_string(), &diff_options).await.unwrap();
+    assert_eq!(diff.files[0].new_path.as_deref(), Some("new.txt"));
+    assert!(diff.files[0].hunks[0].lines.iter().any(|v| v.content == "untracked"));
+}
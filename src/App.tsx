@@ -14,8 +14,10 @@ const GitCommand = {
     Push: "git_push",
     Pull: "git_pull",
     Fetch: "git_fetch",
+    Clone: "git_clone",
     GetPullPushCount: "get_pull_push_count",
     Commit: "git_commit",
+    Stage: "git_stage",
     Log: "git_log",
     Diff: "git_diff",
     Branch: "git_branch",
@@ -24,6 +26,33 @@ const GitCommand = {
     BranchCheckout: "git_branch_checkout",
     BranchMerge: "git_branch_merge",
     CheckoutHash: "git_checkout_hash",
+    Undo: "undo_last_operation",
+    Conflicts: "git_conflicts",
+    ResolveConflict: "git_resolve_conflict",
+    MergeContinue: "git_merge_continue",
+    MergeAbort: "git_merge_abort",
+    Rebase: "git_rebase",
+    RebaseState: "git_rebase_state",
+    RebaseContinue: "git_rebase_continue",
+    RebaseSkip: "git_rebase_skip",
+    RebaseAbort: "git_rebase_abort",
+    RebasePlan: "git_rebase_plan",
+    RebaseInteractive: "git_rebase_interactive",
+    CherryPick: "git_cherry_pick",
+    Revert: "git_revert",
+    SequencerState: "git_sequencer_state",
+    SequencerContinue: "git_sequencer_continue",
+    SequencerSkip: "git_sequencer_skip",
+    SequencerAbort: "git_sequencer_abort",
+    ResetPreview: "git_reset_preview",
+    Reset: "git_reset",
+    StashList: "git_stash_list",
+    StashPush: "git_stash_push",
+    StashApply: "git_stash_apply",
+    StashPop: "git_stash_pop",
+    StashDrop: "git_stash_drop",
+    StashShowFiles: "git_stash_show_files",
+    StashShowFileDiff: "git_stash_show_file_diff",
 } as const;
 
 enum ChangeState {
@@ -32,6 +61,64 @@ enum ChangeState {
     Staging,
     Delete,
     Add,
+    Unmodified,
+    Rename,
+    Copy,
+    TypeChange,
+    Unmerged,
+    Untracked,
+    Ignored,
+    SubmoduleChange,
+}
+
+// マージの方法
+enum MergeMode {
+    Default,
+    NoFastForward,
+    FastForwardOnly,
+    Squash,
+}
+
+enum MergeOutcomeKind {
+    UpToDate,
+    FastForward,
+    MergeCommit,
+    Squashed,
+    Conflicts,
+}
+
+// 対話的リベースでの各コミットの扱い
+enum TodoAction {
+    Pick,
+    Reword,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+// cherry-pick・revert
+enum SequencerCommand {
+    CherryPick,
+    Revert,
+}
+
+enum PickOutcomeKind {
+    Applied,
+    Conflicts,
+    Empty,
+}
+
+// リセットの方法
+enum ResetMode {
+    Soft,
+    Mixed,
+    Hard,
+}
+
+// 衝突の解消に使う側
+enum ConflictSide {
+    Ours,
+    Theirs,
 }
 
 enum BranchState {
@@ -47,11 +134,140 @@ enum ViewMode {
     Log,
 }
 
+interface DiffResult {
+    raw: string;
+    files: object[];
+}
+
+interface RecentRepository {
+    path: string;
+    display_name: string;
+    pinned: boolean;
+    last_opened: number;
+    missing: boolean;
+}
+
 interface EmitMessage<T> {
     is_ok: boolean;
     result: T;
 }
 
+// fetch/pull/push/cloneの進捗
+interface GitProgress {
+    phase: string;
+    is_remote: boolean;
+    percent: number | null;
+    current: number | null;
+    total: number | null;
+    transferred: string | null;
+    rate: string | null;
+    done: boolean;
+}
+
+// マージで衝突しているファイル (各ステージのblobのハッシュ)
+interface ConflictEntry {
+    path: string;
+    kind: number | null;
+    base: string | null;
+    ours: string | null;
+    theirs: string | null;
+}
+
+interface MergeOutcome {
+    kind: number;
+    head: string | null;
+    conflicts: string[];
+}
+
+// リベース中の状態
+interface RebaseState {
+    in_progress: boolean;
+    branch_name: string | null;
+    onto: string | null;
+    stopped_at: string | null;
+    stopped_subject: string | null;
+    current_step: number | null;
+    total_steps: number | null;
+    conflicts: string[];
+}
+
+// 対話的リベースのtodoの1行
+interface TodoEntry {
+    action: number;
+    hash: string;
+    short_hash: string;
+    subject: string;
+    message: string | null;
+}
+
+// 対話的リベースの計画 (baseより後のコミットを並べ替え・まとめる)
+interface RebasePlan {
+    base: string;
+    entries: TodoEntry[];
+}
+
+interface PickOptions {
+    record_origin: boolean;
+    mainline: number | null;
+}
+
+// cherry-pick・revertの途中の状態 (止まっていない場合はcommandがnull)
+interface SequencerState {
+    command: number | null;
+    stopped_at: string | null;
+    stopped_subject: string | null;
+    remaining: number;
+    conflicts: string[];
+}
+
+interface PickOutcome {
+    kind: number;
+    created: string[];
+    state: SequencerState;
+}
+
+// 実行中のcherry-pick・revert (マージコミットの場合にmainlineを指定してやり直すため)
+interface PickRequest {
+    cmd: string;
+    hashes: string[];
+    options: PickOptions;
+}
+
+interface ResetCommit {
+    hash: string;
+    subject: string;
+}
+
+// リセットする前に確認する内容
+interface ResetPreview {
+    mode: number;
+    target: string;
+    target_subject: string;
+    branch_name: string | null;
+    unreachable_commits: ResetCommit[];
+    lost_files: string[];
+}
+
+interface StashEntry {
+    name: string;
+    hash: string;
+    message: string;
+    branch: string | null;
+    date: string;
+}
+
+interface StashApplyOutcome {
+    dropped: boolean;
+    conflicts: string[];
+}
+
+interface ProgressMessage {
+    job_id: number;
+    git_command_label: string;
+    window_label: string;
+    progress: GitProgress;
+}
+
 class StatusInfo {
     constructor() {
         this.change_state = ChangeState.Unknown;
@@ -60,6 +276,21 @@ class StatusInfo {
 
     change_state: number;
     filename: string;
+    index_state?: number;
+    worktree_state?: number;
+    orig_filename?: string | null;
+    conflict?: number | null;
+    is_submodule?: boolean;
+}
+
+class StatusResult {
+    constructor() {
+        this.staged = [];
+        this.unstaged = [];
+    }
+
+    staged: StatusInfo[];
+    unstaged: StatusInfo[];
 }
 
 class PullPushCountInfo {
@@ -147,6 +378,9 @@ let cancelCmds: string[] = [];
 let cancelNoBlockCmds: string[] = [];
 let g_currentLogViewBranch: BranchInfo = new BranchInfo();
 let g_isShowFirstParentBranch: boolean = false;
+let g_pendingCommitMessage: string | null = null; // stagingが完了したらコミットするメッセージ
+let g_rebasePlanBase: string = ""; // 取得中の対話的リベースの計画のbase
+let g_pickRequest: PickRequest | null = null; // 実行中のcherry-pick・revert
 
 function App() {
     const [version, setVersion] = useState<string>("");
@@ -163,6 +397,23 @@ function App() {
     const [contextMenu_log, setContextMenu_log] = useState<ContextMenuInfo_log>(new ContextMenuInfo_log());
     const [pullPushCount, setPullPushCount] = useState<PullPushCountInfo>(new PullPushCountInfo());
     const [gitFolderPath, setGitFolderPath] = useState("");
+    const [recentRepositories, setRecentRepositories] = useState<RecentRepository[]>([]);
+    const [conflicts, setConflicts] = useState<ConflictEntry[]>([]);
+    const [isMerging, setIsMerging] = useState(false);
+    const [rebaseState, setRebaseState] = useState<RebaseState | null>(null);
+    const [rebasePlan, setRebasePlan] = useState<RebasePlan | null>(null);
+    const [sequencerState, setSequencerState] = useState<SequencerState | null>(null);
+    const [selectedCommits, setSelectedCommits] = useState<string[]>([]);
+    const [isStashPanelVisible, setIsStashPanelVisible] = useState(false);
+    const [stashes, setStashes] = useState<StashEntry[]>([]);
+    const [selectedStash, setSelectedStash] = useState<StashEntry | null>(null);
+    const [stashFiles, setStashFiles] = useState<StatusInfo[]>([]);
+    const [stashDiff, setStashDiff] = useState("");
+    const [stashMessage, setStashMessage] = useState("");
+    const [stashIncludeUntracked, setStashIncludeUntracked] = useState(false);
+    const [stashKeepIndex, setStashKeepIndex] = useState(false);
+    const [gitProgress, setGitProgress] = useState<GitProgress | null>(null);
+    const [cloneUrl, setCloneUrl] = useState("");
     const [viewMode, setViewMode] = useState<ViewMode>();
     const [commits, setCommits] = useState<CommitInfo[]>([]);
     const [isVisibleoverlayCancelButton, setIsVisibleoverlayCancelButton] = useState(true);
@@ -200,7 +451,7 @@ function App() {
         window.addEventListener('focus', onFocus);
 
         // git結果受信イベント
-        const statusResultEvent = listen<EmitMessage<StatusInfo[]>>('post-git-status-result', (event) => {
+        const statusResultEvent = listen<EmitMessage<StatusResult>>('post-git-status-result', (event) => {
             recieveStatusResult(event);
         });
         const pushResultEvent = listen<EmitMessage<string>>('post-git-push-result', (event) => {
@@ -212,10 +463,16 @@ function App() {
         const fetchResultEvent = listen<EmitMessage<string>>('post-git-fetch-result', (event) => {
             recieveFetchResult(event);
         });
+        const cloneResultEvent = listen<EmitMessage<string>>('post-git-clone-result', (event) => {
+            recieveCloneResult(event);
+        });
+        const progressEvent = listen<ProgressMessage>('git-progress', (event) => {
+            setGitProgress(event.payload.progress);
+        });
         const getPullPushCountResultEvent = listen<EmitMessage<PullPushCountInfo>>('post-get-pull-push-count', (event) => {
             recievePullPushCountResult(event);
         });
-        const diffResultEvent = listen<EmitMessage<string>>('post-git-diff-result', (event) => {
+        const diffResultEvent = listen<EmitMessage<DiffResult>>('post-git-diff-result', (event) => {
             recieveDiffResult(event);
         });
         const discardChangesAddsResultEvent = listen<EmitMessage<string>>('post-git-discard-changes-adds-result', (_) => {
@@ -224,6 +481,9 @@ function App() {
         const discardChangesOthersResultEvent = listen<EmitMessage<string>>('post-git-discard-changes-others-result', (_) => {
             recieveDiscardChangesOthersResult();
         });
+        const stageResultEvent = listen<EmitMessage<string>>('post-git-stage-result', (event) => {
+            recieveStageResult(event);
+        });
         const commitResultEvent = listen<EmitMessage<string>>('post-git-commit-result', (event) => {
             recieveCommitResult(event);
         });
@@ -242,18 +502,105 @@ function App() {
         const branchCheckoutResultEvent = listen<EmitMessage<string>>('post-git-branch-checkout-result', (event) => {
             recieveBranchCheckoutResult(event);
         });
-        const branchMergeResultEvent = listen<EmitMessage<string>>('post-git-branch-merge-result', (event) => {
+        const branchMergeResultEvent = listen<EmitMessage<MergeOutcome | string>>('post-git-branch-merge-result', (event) => {
             recieveBranchMergeResult(event);
         });
         const checkoutHashResultEvent = listen<EmitMessage<string>>('post-git-checkout-hash-result', (event) => {
             recieveCheckoutHashResult(event);
         });
+        const resetPreviewResultEvent = listen<EmitMessage<ResetPreview | string>>('post-git-reset-preview-result', (event) => {
+            recieveResetPreviewResult(event);
+        });
+        const resetResultEvent = listen<EmitMessage<string>>('post-git-reset-result', (event) => {
+            recieveResetResult(event);
+        });
+        const stashListResultEvent = listen<EmitMessage<StashEntry[]>>('post-git-stash-list-result', (event) => {
+            recieveStashListResult(event);
+        });
+        const stashPushResultEvent = listen<EmitMessage<StashEntry | string>>('post-git-stash-push-result', (event) => {
+            recieveStashPushResult(event);
+        });
+        const stashApplyResultEvent = listen<EmitMessage<StashApplyOutcome | string>>('post-git-stash-apply-result', (event) => {
+            recieveStashApplyResult(GitCommand.StashApply, event);
+        });
+        const stashPopResultEvent = listen<EmitMessage<StashApplyOutcome | string>>('post-git-stash-pop-result', (event) => {
+            recieveStashApplyResult(GitCommand.StashPop, event);
+        });
+        const stashDropResultEvent = listen<EmitMessage<string>>('post-git-stash-drop-result', (event) => {
+            recieveStashDropResult(event);
+        });
+        const stashShowFilesResultEvent = listen<EmitMessage<StatusInfo[]>>('post-git-stash-show-files-result', (event) => {
+            recieveStashShowFilesResult(event);
+        });
+        const stashShowFileDiffResultEvent = listen<EmitMessage<DiffResult>>('post-git-stash-show-file-diff-result', (event) => {
+            recieveStashShowFileDiffResult(event);
+        });
+        const undoResultEvent = listen<EmitMessage<string>>('post-undo-last-operation-result', (event) => {
+            recieveUndoResult(event);
+        });
+        const conflictsResultEvent = listen<EmitMessage<ConflictEntry[]>>('post-git-conflicts-result', (event) => {
+            recieveConflictsResult(event);
+        });
+        const resolveConflictResultEvent = listen<EmitMessage<string>>('post-git-resolve-conflict-result', (event) => {
+            recieveResolveConflictResult(event);
+        });
+        const mergeContinueResultEvent = listen<EmitMessage<string>>('post-git-merge-continue-result', (event) => {
+            recieveMergeFinishResult(GitCommand.MergeContinue, event);
+        });
+        const mergeAbortResultEvent = listen<EmitMessage<string>>('post-git-merge-abort-result', (event) => {
+            recieveMergeFinishResult(GitCommand.MergeAbort, event);
+        });
+        const rebaseStateResultEvent = listen<EmitMessage<RebaseState>>('post-git-rebase-state-result', (event) => {
+            recieveRebaseStateResult(event);
+        });
+        const rebaseResultEvent = listen<EmitMessage<RebaseState | string>>('post-git-rebase-result', (event) => {
+            recieveRebaseResult(GitCommand.Rebase, event);
+        });
+        const rebaseContinueResultEvent = listen<EmitMessage<RebaseState | string>>('post-git-rebase-continue-result', (event) => {
+            recieveRebaseResult(GitCommand.RebaseContinue, event);
+        });
+        const rebaseSkipResultEvent = listen<EmitMessage<RebaseState | string>>('post-git-rebase-skip-result', (event) => {
+            recieveRebaseResult(GitCommand.RebaseSkip, event);
+        });
+        const rebaseAbortResultEvent = listen<EmitMessage<RebaseState | string>>('post-git-rebase-abort-result', (event) => {
+            recieveRebaseResult(GitCommand.RebaseAbort, event);
+        });
+        const rebasePlanResultEvent = listen<EmitMessage<TodoEntry[] | string>>('post-git-rebase-plan-result', (event) => {
+            recieveRebasePlanResult(event);
+        });
+        const rebaseInteractiveResultEvent = listen<EmitMessage<RebaseState | string>>('post-git-rebase-interactive-result', (event) => {
+            // 実行できた場合 (途中で止まった場合を含む) は計画を閉じる
+            if (event.payload.is_ok) {
+                setRebasePlan(null);
+            }
+            recieveRebaseResult(GitCommand.RebaseInteractive, event);
+        });
+        const sequencerStateResultEvent = listen<EmitMessage<SequencerState>>('post-git-sequencer-state-result', (event) => {
+            recieveSequencerStateResult(event);
+        });
+        const cherryPickResultEvent = listen<EmitMessage<PickOutcome | string>>('post-git-cherry-pick-result', (event) => {
+            recievePickResult(GitCommand.CherryPick, event);
+        });
+        const revertResultEvent = listen<EmitMessage<PickOutcome | string>>('post-git-revert-result', (event) => {
+            recievePickResult(GitCommand.Revert, event);
+        });
+        const sequencerContinueResultEvent = listen<EmitMessage<PickOutcome | string>>('post-git-sequencer-continue-result', (event) => {
+            recievePickResult(GitCommand.SequencerContinue, event);
+        });
+        const sequencerSkipResultEvent = listen<EmitMessage<PickOutcome | string>>('post-git-sequencer-skip-result', (event) => {
+            recievePickResult(GitCommand.SequencerSkip, event);
+        });
+        const sequencerAbortResultEvent = listen<EmitMessage<SequencerState | string>>('post-git-sequencer-abort-result', (event) => {
+            recieveSequencerAbortResult(event);
+        });
 
         await Promise.all([
             // バージョン情報取得
             fetchVersion(),
             // 現在開いているフォルダパスを取得
             getGitFolder(),
+            // 最近開いたリポジトリを取得
+            getRecentRepositories(),
             // fetchしておく
             fetchStatus(),
             // current branch情報がほしいのでbranchも取得しておく
@@ -271,10 +618,13 @@ function App() {
             pushResultEvent.then(f => f());
             pullResultEvent.then(f => f());
             fetchResultEvent.then(f => f());
+            cloneResultEvent.then(f => f());
+            progressEvent.then(f => f());
             getPullPushCountResultEvent.then(f => f());
             diffResultEvent.then(f => f());
             discardChangesAddsResultEvent.then(f => f());
             discardChangesOthersResultEvent.then(f => f());
+            stageResultEvent.then(f => f());
             commitResultEvent.then(f => f());
             logResultEvent.then(f => f());
             branchResultEvent.then(f => f());
@@ -283,6 +633,33 @@ function App() {
             branchCheckoutResultEvent.then(f => f());
             branchMergeResultEvent.then(f => f());
             checkoutHashResultEvent.then(f => f());
+            resetPreviewResultEvent.then(f => f());
+            resetResultEvent.then(f => f());
+            stashListResultEvent.then(f => f());
+            stashPushResultEvent.then(f => f());
+            stashApplyResultEvent.then(f => f());
+            stashPopResultEvent.then(f => f());
+            stashDropResultEvent.then(f => f());
+            stashShowFilesResultEvent.then(f => f());
+            stashShowFileDiffResultEvent.then(f => f());
+            undoResultEvent.then(f => f());
+            conflictsResultEvent.then(f => f());
+            resolveConflictResultEvent.then(f => f());
+            mergeContinueResultEvent.then(f => f());
+            mergeAbortResultEvent.then(f => f());
+            rebaseStateResultEvent.then(f => f());
+            rebaseResultEvent.then(f => f());
+            rebaseContinueResultEvent.then(f => f());
+            rebaseSkipResultEvent.then(f => f());
+            rebaseAbortResultEvent.then(f => f());
+            rebasePlanResultEvent.then(f => f());
+            rebaseInteractiveResultEvent.then(f => f());
+            sequencerStateResultEvent.then(f => f());
+            cherryPickResultEvent.then(f => f());
+            revertResultEvent.then(f => f());
+            sequencerContinueResultEvent.then(f => f());
+            sequencerSkipResultEvent.then(f => f());
+            sequencerAbortResultEvent.then(f => f());
         };
     }
 
@@ -340,7 +717,7 @@ function App() {
         }
     };
 
-    const recieveStatusResult = async (event: event.Event<EmitMessage<StatusInfo[]>>) => {
+    const recieveStatusResult = async (event: event.Event<EmitMessage<StatusResult>>) => {
         const result = event.payload;
         if (!result.is_ok) {
             console.error(result.result);
@@ -354,7 +731,29 @@ function App() {
             changeFilesScrollRef.current.scrollTop = 0;
         }
 
-        setStatusFiles(result.result);
+        // 未staging/staging済みの両方に変更があるファイルは未staging側を表示する
+        const files = [...result.result.unstaged];
+        for (const info of result.result.staged) {
+            if (!files.some(v => v.filename === info.filename)) {
+                files.push(info);
+            }
+        }
+        setStatusFiles(files);
+
+        // リベース中かどうか (衝突していなくても、編集などのために止まっている場合がある)
+        await gitRebaseState();
+        // cherry-pick・revert中かどうか
+        await gitSequencerState();
+
+        // 衝突しているファイルがある場合は解消用の一覧を取得する
+        if (files.some(v => v.change_state === ChangeState.Unmerged)) {
+            await gitConflicts();
+        } else {
+            setConflicts([]);
+        }
+
+        // staging済みのファイルはチェック済みにしておく
+        setCheckedFiles(prev => new Set([...prev, ...result.result.staged.map(v => v.filename)]));
 
         setLastClickedFile("");
         setDiffResult("");
@@ -383,11 +782,11 @@ function App() {
         hideNoBlockOverlay(GitCommand.GetPullPushCount);
     }
 
-    const updateDiff = async (file: string) => {
+    const updateDiff = async (file: string, staged: boolean = false) => {
         await gitDiffCancel();
 
         if (!file) return;
-        await getDiff(file);
+        await getDiff(file, staged);
     };
 
     const gitDiffCancel = async () => {
@@ -400,16 +799,16 @@ function App() {
         }
     };
 
-    const getDiff = async (file: string) => {
+    const getDiff = async (file: string, staged: boolean) => {
         try {
             showNoBlockOverlay(GitCommand.Diff);
-            await invoke(GitCommand.Diff, {windowLabel: getCurrent().label, file});
+            await invoke(GitCommand.Diff, {windowLabel: getCurrent().label, file, staged});
         } catch (error) {
             console.error("Failed to fetch git diff:", error);
         }
     };
 
-    const recieveDiffResult = async (event: event.Event<EmitMessage<string>>) => {
+    const recieveDiffResult = async (event: event.Event<EmitMessage<DiffResult>>) => {
         const result = event.payload;
         if (!result.is_ok) {
             console.error(result.result);
@@ -421,7 +820,7 @@ function App() {
             diffScrollRef.current.scrollTop = 0;
         }
 
-        let diff = result.result;
+        let diff = result.result.raw;
         if (diff) {
             setDiffResult(diff);
         } else {
@@ -462,7 +861,7 @@ function App() {
         }
 
         setLastClickedFile(fileInfo.filename);
-        await updateDiff(fileInfo.filename);
+        await updateDiff(fileInfo.filename, fileInfo.worktree_state === ChangeState.Unmodified);
     };
 
     // Handle branch click
@@ -510,6 +909,7 @@ function App() {
         let currentBranch = branches.find(value => value.branch_state === BranchState.Current);
         if (currentBranch) {
             let isOnBranch = await invoke("is_on_branch", {
+                windowLabel: getCurrent().label,
                 branchName: currentBranch.branch_name,
                 branchState: currentBranch.branch_state
             });
@@ -538,12 +938,42 @@ function App() {
             }
         }
 
+        // チェックしたファイルをstagingしてからコミットする (recieveStageResultでコミット)
         try {
             showOverlay(GitCommand.Commit, true);
+            g_pendingCommitMessage = commitMessage;
+            await invoke(GitCommand.Stage, {
+                windowLabel: getCurrent().label,
+                infos: statusFiles.filter(v => checkedFiles.has(v.filename)),
+            });
+        } catch (error) {
+            g_pendingCommitMessage = null;
+            hideOverlay(GitCommand.Commit);
+            console.error("Failed to stage:", error);
+            alert("ERROR: git add\n" + error);
+        }
+    };
+
+    const recieveStageResult = async (event: event.Event<EmitMessage<string>>) => {
+        const message = g_pendingCommitMessage;
+        g_pendingCommitMessage = null;
+
+        const result = event.payload;
+        if (!result.is_ok) {
+            console.error(result.result);
+            hideOverlay(GitCommand.Commit);
+            return;
+        }
+
+        if (message === null) {
+            await fetchStatus();
+            return;
+        }
+
+        try {
             await invoke(GitCommand.Commit, {
                 windowLabel: getCurrent().label,
-                files: filesToCommit,
-                message: commitMessage
+                message: message
             });
         } catch (error) {
             console.error("Failed to commit:", error);
@@ -1032,185 +1462,637 @@ function App() {
     const openFileLocation = async (filename: string) => {
         try {
             hideContextMenu();
-            await invoke("open_file_location", {file: filename});
+            await invoke("open_file_location", {windowLabel: getCurrent().label, file: filename});
         } catch (error) {
             console.error("Failed to open file location:", error);
         }
     };
 
-    const gitPush = async () => {
+    const undoLastOperation = async () => {
+        const userResponse = await ask("直前の操作を元に戻しますか？", 'Undo');
+        if (!userResponse) return;
+
         try {
-            showOverlay(GitCommand.Push, true);
-            await invoke(GitCommand.Push, {windowLabel: getCurrent().label});
+            showOverlay(GitCommand.Undo, false);
+            await invoke(GitCommand.Undo, {windowLabel: getCurrent().label});
         } catch (error) {
-            console.error("Failed to push:", error);
-            alert("ERROR: git push\n" + error);
+            hideOverlay(GitCommand.Undo);
+            console.error("Failed to undo:", error);
+            alert("ERROR: undo\n" + error);
         }
     };
 
-    const recievePushResult = async (event: event.Event<EmitMessage<string>>) => {
+    const recieveUndoResult = async (event: event.Event<EmitMessage<string>>) => {
         const result = event.payload;
-        if (!result.is_ok) {
-            alert(result.result);
-            return;
+        if (result.is_ok) {
+            await Promise.all([
+                fetchStatus(),
+                gitLog(),
+                gitBranch(),
+                getPullPushCount(),
+            ]);
         }
 
-        await Promise.all([
-            gitLog(),
-            gitBranch(),
-            getPullPushCount(),
-        ]);
-
-        hideOverlay(GitCommand.Push);
+        hideOverlay(GitCommand.Undo);
+        // 戻せなかった場合は理由を表示する
         alert(result.result);
     }
 
-    const gitPull = async () => {
+    const gitConflicts = async () => {
         try {
-            showOverlay(GitCommand.Pull, true);
-            await invoke(GitCommand.Pull, {windowLabel: getCurrent().label});
+            await invoke(GitCommand.Conflicts, {windowLabel: getCurrent().label});
         } catch (error) {
-            console.error("Failed to pull:", error);
-            alert("ERROR: git pull\n" + error);
+            console.error("Failed to get conflicts:", error);
         }
     };
 
-    const recievePullResult = async (event: event.Event<EmitMessage<string>>) => {
+    const recieveConflictsResult = async (event: event.Event<EmitMessage<ConflictEntry[]>>) => {
         const result = event.payload;
         if (!result.is_ok) {
-            alert(result.result);
+            console.error(result.result);
             return;
         }
 
-        await Promise.all([
-            gitLog(),
-            gitBranch(),
-            fetchStatus(),
-        ]);
+        setConflicts(result.result);
+        if (result.result.length > 0) {
+            setIsMerging(true);
+        }
+    }
 
-        hideOverlay(GitCommand.Pull);
-        alert(result.result);
+    const resolveConflict = async (path: string, side: ConflictSide) => {
+        try {
+            showOverlay(GitCommand.ResolveConflict, false);
+            await invoke(GitCommand.ResolveConflict, {windowLabel: getCurrent().label, path: path, side: side});
+        } catch (error) {
+            hideOverlay(GitCommand.ResolveConflict);
+            console.error("Failed to resolve conflict:", error);
+        }
+    };
+
+    const recieveResolveConflictResult = async (event: event.Event<EmitMessage<string>>) => {
+        const result = event.payload;
+        hideOverlay(GitCommand.ResolveConflict);
+        if (!result.is_ok) {
+            alert(result.result);
+        }
+        await fetchStatus();
     }
 
-    const gitFetch = async () => {
+    const finishMerge = async (cmd: string) => {
+        if (cmd === GitCommand.MergeAbort) {
+            const userResponse = await ask("マージを中止して、マージ前の状態に戻しますか？", 'Abort merge');
+            if (!userResponse) return;
+        }
+
         try {
-            showOverlay(GitCommand.Fetch, true);
-            await invoke(GitCommand.Fetch, {windowLabel: getCurrent().label});
+            showOverlay(cmd, false);
+            await invoke(cmd, {windowLabel: getCurrent().label});
         } catch (error) {
-            console.error("Failed to pull:", error);
-            alert("ERROR: git pull\n" + error);
+            hideOverlay(cmd);
+            console.error("Failed to finish merge:", error);
         }
     };
 
-    const recieveFetchResult = async (event: event.Event<EmitMessage<string>>) => {
+    const recieveMergeFinishResult = async (cmd: string, event: event.Event<EmitMessage<string>>) => {
         const result = event.payload;
+        hideOverlay(cmd);
         if (!result.is_ok) {
             alert(result.result);
             return;
         }
 
+        setIsMerging(false);
         await Promise.all([
+            fetchStatus(),
             gitLog(),
-            gitBranch(),
             getPullPushCount(),
         ]);
-
-        hideOverlay(GitCommand.Fetch);
     }
 
-    const selectGitFolder = async () => {
+    const gitRebaseState = async () => {
         try {
-            const path = await invoke<string>("select_git_folder");
-            setGitFolderPath(path);
-            setViewMode(ViewMode.Commit);
+            await invoke(GitCommand.RebaseState, {windowLabel: getCurrent().label});
+        } catch (error) {
+            console.error("Failed to get rebase state:", error);
+        }
+    };
+
+    const recieveRebaseStateResult = async (event: event.Event<EmitMessage<RebaseState>>) => {
+        const result = event.payload;
+        if (!result.is_ok) {
+            console.error(result.result);
+            return;
+        }
+        setRebaseState(result.result);
+    }
 
-            refresh();
+    // 現在のブランチをtarget (ブランチ名またはコミット) の上に付け替える
+    const gitRebase = async (target: string) => {
+        const userResponse = await ask("現在のブランチを " + target + " の上にリベースしますか？", 'Rebase');
+        if (!userResponse) return;
 
-            await Promise.all([
-                fetchStatus(),
-                gitBranch(), // current branch情報がほしいのでbranchも取得しておく
-            ]);
+        try {
+            showOverlay(GitCommand.Rebase, true);
+            await invoke(GitCommand.Rebase, {windowLabel: getCurrent().label, target: target});
         } catch (error) {
-            if (error) { // cancelボタンを押して閉じた際はエラーメッセージなし
-                alert(error);
-                console.error("Failed to select git folder:", error);
-            }
+            hideOverlay(GitCommand.Rebase);
+            console.error("Failed to git rebase:", error);
         }
     };
 
-    const getGitFolder = async () => {
+    // baseより後のコミットを対話的リベースの計画として取得する
+    const gitRebasePlan = async (base: string) => {
         try {
-            const path = await invoke<string>("get_git_folder");
-            setGitFolderPath(path);
+            g_rebasePlanBase = base;
+            showOverlay(GitCommand.RebasePlan, true);
+            await invoke(GitCommand.RebasePlan, {windowLabel: getCurrent().label, base: base});
         } catch (error) {
-            console.error("Failed to get git folder:", error);
+            hideOverlay(GitCommand.RebasePlan);
+            console.error("Failed to get rebase plan:", error);
         }
     };
 
-    const gitLog = async () => {
-        try {
-            showNoBlockOverlay(GitCommand.Log);
+    const recieveRebasePlanResult = async (event: event.Event<EmitMessage<TodoEntry[] | string>>) => {
+        const result = event.payload;
+        hideOverlay(GitCommand.RebasePlan);
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
 
-            let isOnBranch = await invoke("is_on_branch", {
-                branchName: g_currentLogViewBranch.branch_name,
-                branchState: g_currentLogViewBranch.branch_state
-            });
+        const entries = result.result as TodoEntry[];
+        if (entries.length === 0) {
+            alert("このコミットより後にコミットがありません。");
+            return;
+        }
+        setRebasePlan({base: g_rebasePlanBase, entries: entries});
+    }
 
-            let branchName = isOnBranch ?
-                g_currentLogViewBranch.branch_name :
-                ""; // 特定のコミットハッシュにチェックアウトしている場合
+    // 計画のindex番目の行を変更する
+    const updateTodoEntry = (index: number, change: Partial<TodoEntry>) => {
+        setRebasePlan(prev => prev && {
+            ...prev,
+            entries: prev.entries.map((v, i) => i === index ? {...v, ...change} : v),
+        });
+    }
 
-            await invoke(GitCommand.Log, {
+    // 計画のindex番目の行を上下に移動する
+    const moveTodoEntry = (index: number, offset: number) => {
+        setRebasePlan(prev => {
+            if (!prev || index + offset < 0 || index + offset >= prev.entries.length) return prev;
+            const entries = [...prev.entries];
+            [entries[index], entries[index + offset]] = [entries[index + offset], entries[index]];
+            return {...prev, entries: entries};
+        });
+    }
+
+    const gitRebaseInteractive = async () => {
+        if (!rebasePlan) return;
+
+        try {
+            showOverlay(GitCommand.RebaseInteractive, true);
+            await invoke(GitCommand.RebaseInteractive, {
                 windowLabel: getCurrent().label,
-                isShowAll: g_currentLogViewBranch.branch_state === BranchState.All,
-                branchName: branchName,
-                isFirstParent: g_isShowFirstParentBranch,
+                base: rebasePlan.base,
+                entries: rebasePlan.entries,
             });
         } catch (error) {
-            console.error("Failed to git log:", error);
+            hideOverlay(GitCommand.RebaseInteractive);
+            console.error("Failed to git rebase interactive:", error);
         }
     };
 
-    const gitLogCancel = async () => {
+    // continue/skip/abort
+    const stepRebase = async (cmd: string) => {
+        if (cmd === GitCommand.RebaseAbort) {
+            const userResponse = await ask("リベースを中止して、元のブランチに戻しますか？", 'Abort rebase');
+            if (!userResponse) return;
+        }
+
         try {
-            await invoke("git_log_cancel", {windowLabel: getCurrent().label});
-            hideNoBlockOverlay(GitCommand.Log);
+            showOverlay(cmd, true);
+            await invoke(cmd, {windowLabel: getCurrent().label});
         } catch (error) {
-            console.error("Failed to cancel:", error);
-            alert("ERROR: Failed to cancel\n" + error);
+            hideOverlay(cmd);
+            console.error("Failed to " + cmd + ":", error);
         }
     };
 
-    const recieveLogResult = async (event: event.Event<EmitMessage<CommitInfo[]>>) => {
+    const recieveRebaseResult = async (cmd: string, event: event.Event<EmitMessage<RebaseState | string>>) => {
         const result = event.payload;
+        hideOverlay(cmd);
         if (!result.is_ok) {
-            console.error(result.result);
+            alert(result.result);
             return;
         }
 
-        setCommits(result.result);
+        const state = result.result as RebaseState;
+        setRebaseState(state);
+        if (!state.in_progress) {
+            // リベース中の衝突をマージの衝突として表示しないようにする
+            setIsMerging(false);
+        } else {
+            alert("リベースが " + (state.stopped_at ? state.stopped_at.substring(0, 7) + " " + (state.stopped_subject ?? "") : "途中") + " で止まりました。" +
+                (state.conflicts.length > 0 ? "\n衝突を解消してから続けてください。\n" + state.conflicts.join("\n") : ""));
+        }
 
-        hideNoBlockOverlay(GitCommand.Log);
+        await Promise.all([
+            fetchStatus(),
+            gitBranch(),
+            gitLog(),
+            getPullPushCount(),
+        ]);
     }
 
-    const gitBranch = async () => {
+    const gitSequencerState = async () => {
         try {
-            showNoBlockOverlay(GitCommand.Branch);
-            await invoke(GitCommand.Branch, {windowLabel: getCurrent().label});
+            await invoke(GitCommand.SequencerState, {windowLabel: getCurrent().label});
         } catch (error) {
-            console.error("Failed to git branch:", error);
+            console.error("Failed to get sequencer state:", error);
         }
     };
 
-    const recieveBranchResult = async (event: event.Event<EmitMessage<BranchInfo[]>>) => {
+    const recieveSequencerStateResult = async (event: event.Event<EmitMessage<SequencerState>>) => {
         const result = event.payload;
         if (!result.is_ok) {
             console.error(result.result);
             return;
         }
+        setSequencerState(result.result);
+    }
 
-        setBranches(result.result);
-        setCurrentLogViewBranch(result.result.find(value => value.branch_state === BranchState.Current) ?? new BranchInfo());
+    // 右クリックしたコミットが選択中のコミットに含まれていれば選択中の全て、そうでなければそのコミットだけを対象にする
+    const getPickTargets = (hash: string) => {
+        return selectedCommits.includes(hash) ? selectedCommits : [hash];
+    }
+
+    // ctrl (macはcmd) を押しながらクリックしたコミットを選択・選択解除する
+    const toggleSelectedCommit = (event: React.MouseEvent, hash: string) => {
+        if (!hash || !(event.ctrlKey || event.metaKey)) return;
+        setSelectedCommits(prev => prev.includes(hash) ? prev.filter(v => v !== hash) : [...prev, hash]);
+    }
+
+    const pickCommits = async (request: PickRequest) => {
+        try {
+            g_pickRequest = request;
+            showOverlay(request.cmd, true);
+            await invoke(request.cmd, {
+                windowLabel: getCurrent().label,
+                hashes: request.hashes,
+                options: request.options,
+            });
+        } catch (error) {
+            hideOverlay(request.cmd);
+            console.error("Failed to " + request.cmd + ":", error);
+        }
+    };
+
+    const gitCherryPick = async (hashes: string[], recordOrigin: boolean) => {
+        const userResponse = await ask(hashes.length + " 個のコミットを現在のブランチにcherry-pickしますか？", 'Cherry-pick');
+        if (!userResponse) return;
+        await pickCommits({cmd: GitCommand.CherryPick, hashes: hashes, options: {record_origin: recordOrigin, mainline: null}});
+    };
+
+    const gitRevert = async (hashes: string[]) => {
+        const userResponse = await ask(hashes.length + " 個のコミットを打ち消すコミットを作成しますか？", 'Revert');
+        if (!userResponse) return;
+        await pickCommits({cmd: GitCommand.Revert, hashes: hashes, options: {record_origin: false, mainline: null}});
+    };
+
+    // continue/skip/abort
+    const stepSequencer = async (cmd: string) => {
+        if (cmd === GitCommand.SequencerAbort) {
+            const userResponse = await ask("中止して、始める前の状態に戻しますか？", 'Abort');
+            if (!userResponse) return;
+        }
+
+        try {
+            showOverlay(cmd, true);
+            await invoke(cmd, {windowLabel: getCurrent().label});
+        } catch (error) {
+            hideOverlay(cmd);
+            console.error("Failed to " + cmd + ":", error);
+        }
+    };
+
+    const recievePickResult = async (cmd: string, event: event.Event<EmitMessage<PickOutcome | string>>) => {
+        const result = event.payload;
+        hideOverlay(cmd);
+        const request = g_pickRequest;
+        g_pickRequest = null;
+        if (!result.is_ok) {
+            const message = result.result as string;
+            // マージコミットはどの親との差分を使うか指定が必要 (通常はマージ先の最初の親)
+            if (request && request.options.mainline === null && message.includes("is a merge commit. Choose the parent to use as the mainline.")) {
+                const userResponse = await ask("マージコミットが含まれています。最初の親との差分を使って続けますか？", 'Merge commit');
+                if (userResponse) {
+                    await pickCommits({...request, options: {...request.options, mainline: 1}});
+                }
+                return;
+            }
+            alert(message);
+            return;
+        }
+
+        const outcome = result.result as PickOutcome;
+        setSequencerState(outcome.state);
+        switch (outcome.kind) {
+            case PickOutcomeKind.Applied:
+                setSelectedCommits([]);
+                break;
+            case PickOutcomeKind.Conflicts:
+                // 止まったままなので、衝突しているファイルを解消してから続けてもらう
+                alert((outcome.state.stopped_at ?? "").substring(0, 7) + " " + (outcome.state.stopped_subject ?? "") + " で衝突しました。\n" +
+                    "解消してから続けてください。\n" + outcome.state.conflicts.join("\n"));
+                break;
+            case PickOutcomeKind.Empty:
+                alert((outcome.state.stopped_at ?? "").substring(0, 7) + " " + (outcome.state.stopped_subject ?? "") + " の変更は既に含まれています。\nSkipで飛ばしてください。");
+                break;
+        }
+
+        await Promise.all([
+            fetchStatus(),
+            gitLog(),
+            getPullPushCount(),
+        ]);
+    }
+
+    const recieveSequencerAbortResult = async (event: event.Event<EmitMessage<SequencerState | string>>) => {
+        const result = event.payload;
+        hideOverlay(GitCommand.SequencerAbort);
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        setSequencerState(result.result as SequencerState);
+        setIsMerging(false);
+        await Promise.all([
+            fetchStatus(),
+            gitLog(),
+            getPullPushCount(),
+        ]);
+    }
+
+    const gitPush = async () => {
+        try {
+            showOverlay(GitCommand.Push, true);
+            await invoke(GitCommand.Push, {windowLabel: getCurrent().label});
+        } catch (error) {
+            console.error("Failed to push:", error);
+            alert("ERROR: git push\n" + error);
+        }
+    };
+
+    const recievePushResult = async (event: event.Event<EmitMessage<string>>) => {
+        const result = event.payload;
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        await Promise.all([
+            gitLog(),
+            gitBranch(),
+            getPullPushCount(),
+        ]);
+
+        hideOverlay(GitCommand.Push);
+        alert(result.result);
+    }
+
+    const gitPull = async () => {
+        try {
+            showOverlay(GitCommand.Pull, true);
+            await invoke(GitCommand.Pull, {windowLabel: getCurrent().label});
+        } catch (error) {
+            console.error("Failed to pull:", error);
+            alert("ERROR: git pull\n" + error);
+        }
+    };
+
+    const recievePullResult = async (event: event.Event<EmitMessage<string>>) => {
+        const result = event.payload;
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        await Promise.all([
+            gitLog(),
+            gitBranch(),
+            fetchStatus(),
+        ]);
+
+        hideOverlay(GitCommand.Pull);
+        alert(result.result);
+    }
+
+    const gitFetch = async () => {
+        try {
+            showOverlay(GitCommand.Fetch, true);
+            await invoke(GitCommand.Fetch, {windowLabel: getCurrent().label});
+        } catch (error) {
+            console.error("Failed to pull:", error);
+            alert("ERROR: git pull\n" + error);
+        }
+    };
+
+    const recieveFetchResult = async (event: event.Event<EmitMessage<string>>) => {
+        const result = event.payload;
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        await Promise.all([
+            gitLog(),
+            gitBranch(),
+            getPullPushCount(),
+        ]);
+
+        hideOverlay(GitCommand.Fetch);
+    }
+
+    const gitClone = async () => {
+        if (!cloneUrl) {
+            alert("Please enter the URL of the repository to clone.");
+            return;
+        }
+
+        try {
+            const parentDir = await invoke<string>("select_clone_destination");
+            showOverlay(GitCommand.Clone, true);
+            await invoke(GitCommand.Clone, {windowLabel: getCurrent().label, url: cloneUrl, parentDir: parentDir});
+        } catch (error) {
+            hideOverlay(GitCommand.Clone);
+            if (error) { // cancelボタンを押して閉じた際はエラーメッセージなし
+                console.error("Failed to clone:", error);
+                alert("ERROR: git clone\n" + error);
+            }
+        }
+    };
+
+    const recieveCloneResult = async (event: event.Event<EmitMessage<string>>) => {
+        hideOverlay(GitCommand.Clone);
+
+        const result = event.payload;
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        setCloneUrl("");
+        await openedRepository(result.result);
+    }
+
+    const selectGitFolder = async () => {
+        try {
+            const path = await invoke<string>("select_git_folder", {windowLabel: getCurrent().label});
+            await openedRepository(path);
+        } catch (error) {
+            if (error) { // cancelボタンを押して閉じた際はエラーメッセージなし
+                alert(error);
+                console.error("Failed to select git folder:", error);
+            }
+        }
+    };
+
+    const openRecentRepository = async (path: string) => {
+        try {
+            const openedPath = await invoke<string>("open_recent_repository", {windowLabel: getCurrent().label, path});
+            await openedRepository(openedPath);
+        } catch (error) {
+            alert(error);
+            console.error("Failed to open recent repository:", error);
+            await getRecentRepositories();
+        }
+    };
+
+    // リポジトリを開いた後の画面の更新
+    const openedRepository = async (path: string) => {
+        setGitFolderPath(path);
+        setViewMode(ViewMode.Commit);
+
+        refresh();
+
+        await Promise.all([
+            getRecentRepositories(),
+            fetchStatus(),
+            gitBranch(), // current branch情報がほしいのでbranchも取得しておく
+        ]);
+    };
+
+    const getRecentRepositories = async () => {
+        try {
+            setRecentRepositories(await invoke<RecentRepository[]>("get_recent_repositories"));
+        } catch (error) {
+            console.error("Failed to get recent repositories:", error);
+        }
+    };
+
+    const togglePinRepository = async () => {
+        const current = recentRepositories.find(v => v.path === gitFolderPath);
+        if (!current) {
+            return;
+        }
+        try {
+            setRecentRepositories(await invoke<RecentRepository[]>("pin_recent_repository", {
+                path: current.path,
+                pinned: !current.pinned
+            }));
+        } catch (error) {
+            console.error("Failed to pin repository:", error);
+        }
+    };
+
+    // 別のリポジトリを新しいウィンドウで開く
+    const openRepositoryWindow = async () => {
+        try {
+            const path = await invoke<string>("select_repository_for_new_window");
+            await invoke("open_repository_window", {path});
+        } catch (error) {
+            if (error) { // cancelボタンを押して閉じた際はエラーメッセージなし
+                alert(error);
+                console.error("Failed to open repository window:", error);
+            }
+        }
+    };
+
+    const getGitFolder = async () => {
+        try {
+            const path = await invoke<string>("get_git_folder", {windowLabel: getCurrent().label});
+            setGitFolderPath(path);
+        } catch (error) {
+            console.error("Failed to get git folder:", error);
+        }
+    };
+
+    const gitLog = async () => {
+        try {
+            showNoBlockOverlay(GitCommand.Log);
+
+            let isOnBranch = await invoke("is_on_branch", {
+                windowLabel: getCurrent().label,
+                branchName: g_currentLogViewBranch.branch_name,
+                branchState: g_currentLogViewBranch.branch_state
+            });
+
+            let branchName = isOnBranch ?
+                g_currentLogViewBranch.branch_name :
+                ""; // 特定のコミットハッシュにチェックアウトしている場合
+
+            await invoke(GitCommand.Log, {
+                windowLabel: getCurrent().label,
+                isShowAll: g_currentLogViewBranch.branch_state === BranchState.All,
+                branchName: branchName,
+                isFirstParent: g_isShowFirstParentBranch,
+            });
+        } catch (error) {
+            console.error("Failed to git log:", error);
+        }
+    };
+
+    const gitLogCancel = async () => {
+        try {
+            await invoke("git_log_cancel", {windowLabel: getCurrent().label});
+            hideNoBlockOverlay(GitCommand.Log);
+        } catch (error) {
+            console.error("Failed to cancel:", error);
+            alert("ERROR: Failed to cancel\n" + error);
+        }
+    };
+
+    const recieveLogResult = async (event: event.Event<EmitMessage<CommitInfo[]>>) => {
+        const result = event.payload;
+        if (!result.is_ok) {
+            console.error(result.result);
+            return;
+        }
+
+        setCommits(result.result);
+
+        hideNoBlockOverlay(GitCommand.Log);
+    }
+
+    const gitBranch = async () => {
+        try {
+            showNoBlockOverlay(GitCommand.Branch);
+            await invoke(GitCommand.Branch, {windowLabel: getCurrent().label});
+        } catch (error) {
+            console.error("Failed to git branch:", error);
+        }
+    };
+
+    const recieveBranchResult = async (event: event.Event<EmitMessage<BranchInfo[]>>) => {
+        const result = event.payload;
+        if (!result.is_ok) {
+            console.error(result.result);
+            return;
+        }
+
+        setBranches(result.result);
+        setCurrentLogViewBranch(result.result.find(value => value.branch_state === BranchState.Current) ?? new BranchInfo());
 
         hideNoBlockOverlay(GitCommand.Branch);
     }
@@ -1329,71 +2211,302 @@ function App() {
             getPullPushCount(),
         ])
 
-        hideOverlay(GitCommand.BranchCheckout);
+        hideOverlay(GitCommand.BranchCheckout);
+    }
+
+    const gitBranchMerge = async (branchName: string, mode: MergeMode = MergeMode.Default) => {
+        try {
+            showOverlay(GitCommand.BranchMerge, true);
+            await invoke(GitCommand.BranchMerge, {
+                windowLabel: getCurrent().label,
+                mergeBranchName: branchName,
+                options: {mode: mode, message: ""},
+            });
+        } catch (error) {
+            hideOverlay(GitCommand.BranchMerge);
+            console.error("Failed to git branch merge:", error);
+        }
+    };
+
+    const recieveBranchMergeResult = async (event: event.Event<EmitMessage<MergeOutcome | string>>) => {
+        const result = event.payload;
+        hideOverlay(GitCommand.BranchMerge);
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        const outcome = result.result as MergeOutcome;
+        switch (outcome.kind) {
+            case MergeOutcomeKind.UpToDate:
+                alert("Already up to date.");
+                break;
+            case MergeOutcomeKind.Squashed:
+                alert("変更をstagingしました。内容を確認してコミットしてください。");
+                break;
+            case MergeOutcomeKind.Conflicts:
+                // マージ中のままなので、衝突しているファイルを解消してもらう
+                alert("衝突しています。解消してからマージを続けてください。\n" + outcome.conflicts.join("\n"));
+                break;
+        }
+
+        await Promise.all([
+            fetchStatus(),
+            gitBranch(),
+            gitLog(),
+            getPullPushCount(),
+        ])
+    }
+
+    const gitCheckoutHash = async (commitHash: string) => {
+        try {
+            showOverlay(GitCommand.CheckoutHash, true);
+            await invoke(GitCommand.CheckoutHash, {
+                windowLabel: getCurrent().label,
+                commitHash: commitHash,
+            });
+        } catch (error) {
+            console.error("Failed to git checkout hash:", error);
+        }
+    };
+
+    const recieveCheckoutHashResult = async (event: event.Event<EmitMessage<string>>) => {
+        const result = event.payload;
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        if (result.result) {
+            alert(result.result);
+        }
+
+        await Promise.all([
+            gitBranch(),
+            gitLog(),
+            getPullPushCount(),
+        ])
+
+        hideOverlay(GitCommand.CheckoutHash);
+    }
+
+    // リセットする前に、何が起きるかを取得する (結果を確認してからリセットする)
+    const gitResetPreview = async (target: string, mode: ResetMode) => {
+        try {
+            showOverlay(GitCommand.ResetPreview, true);
+            await invoke(GitCommand.ResetPreview, {windowLabel: getCurrent().label, target: target, mode: mode});
+        } catch (error) {
+            hideOverlay(GitCommand.ResetPreview);
+            console.error("Failed to get reset preview:", error);
+        }
+    };
+
+    const recieveResetPreviewResult = async (event: event.Event<EmitMessage<ResetPreview | string>>) => {
+        const result = event.payload;
+        hideOverlay(GitCommand.ResetPreview);
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        const preview = result.result as ResetPreview;
+        const modeName = ["soft", "mixed", "hard"][preview.mode];
+        const lines = [(preview.branch_name ?? "HEAD") + " を " + preview.target.substring(0, 7) + " " + preview.target_subject + " に " + modeName + " リセットしますか？"];
+        if (preview.unreachable_commits.length > 0) {
+            lines.push("", preview.unreachable_commits.length + " 個のコミットがどのブランチからも辿れなくなります:");
+            lines.push(...preview.unreachable_commits.slice(0, 10).map(v => "  " + v.hash.substring(0, 7) + " " + v.subject));
+            if (preview.unreachable_commits.length > 10) lines.push("  ...");
+        }
+        if (preview.lost_files.length > 0) {
+            lines.push("", "コミットしていない次のファイルの変更が失われます:");
+            lines.push(...preview.lost_files.slice(0, 10).map(v => "  " + v));
+            if (preview.lost_files.length > 10) lines.push("  ...");
+        }
+        const userResponse = await ask(lines.join("\n"), 'Reset');
+        if (!userResponse) return;
+
+        try {
+            showOverlay(GitCommand.Reset, true);
+            await invoke(GitCommand.Reset, {windowLabel: getCurrent().label, target: preview.target, mode: preview.mode});
+        } catch (error) {
+            hideOverlay(GitCommand.Reset);
+            console.error("Failed to git reset:", error);
+        }
+    }
+
+    const recieveResetResult = async (event: event.Event<EmitMessage<string>>) => {
+        const result = event.payload;
+        hideOverlay(GitCommand.Reset);
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        await Promise.all([
+            fetchStatus(),
+            gitBranch(),
+            gitLog(),
+            getPullPushCount(),
+        ]);
+    }
+
+    const openStashPanel = async () => {
+        setIsStashPanelVisible(true);
+        setSelectedStash(null);
+        setStashFiles([]);
+        setStashDiff("");
+        await gitStashList();
+    }
+
+    const gitStashList = async () => {
+        try {
+            await invoke(GitCommand.StashList, {windowLabel: getCurrent().label});
+        } catch (error) {
+            console.error("Failed to get stash list:", error);
+        }
+    };
+
+    const recieveStashListResult = async (event: event.Event<EmitMessage<StashEntry[]>>) => {
+        const result = event.payload;
+        if (!result.is_ok) {
+            console.error(result.result);
+            return;
+        }
+        setStashes(result.result);
+    }
+
+    // チェックしたファイルがあればそのファイルだけ、なければ全ての変更をstashする
+    const gitStashPush = async () => {
+        const files = statusFiles.filter(v => checkedFiles.has(v.filename));
+        try {
+            showOverlay(GitCommand.StashPush, true);
+            await invoke(GitCommand.StashPush, {
+                windowLabel: getCurrent().label,
+                options: {
+                    message: stashMessage,
+                    include_untracked: stashIncludeUntracked,
+                    keep_index: stashKeepIndex,
+                    files: files,
+                },
+            });
+        } catch (error) {
+            hideOverlay(GitCommand.StashPush);
+            console.error("Failed to git stash push:", error);
+        }
+    };
+
+    const recieveStashPushResult = async (event: event.Event<EmitMessage<StashEntry | string>>) => {
+        const result = event.payload;
+        hideOverlay(GitCommand.StashPush);
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        setStashMessage("");
+        setCheckedFiles(new Set());
+        await Promise.all([
+            fetchStatus(),
+            gitStashList(),
+        ]);
     }
 
-    const gitBranchMerge = async (branchName: string) => {
+    // apply/pop/drop
+    const stepStash = async (cmd: string, stash: StashEntry) => {
+        if (cmd === GitCommand.StashDrop) {
+            const userResponse = await ask(stash.name + " " + stash.message + " を削除しますか？", 'Drop stash');
+            if (!userResponse) return;
+        }
+
         try {
-            showOverlay(GitCommand.BranchMerge, true);
-            await invoke(GitCommand.BranchMerge, {
-                windowLabel: getCurrent().label,
-                mergeBranchName: branchName,
-            });
+            showOverlay(cmd, true);
+            await invoke(cmd, {windowLabel: getCurrent().label, hash: stash.hash});
         } catch (error) {
-            console.error("Failed to git branch merge:", error);
+            hideOverlay(cmd);
+            console.error("Failed to " + cmd + ":", error);
         }
     };
 
-    const recieveBranchMergeResult = async (event: event.Event<EmitMessage<string>>) => {
+    const recieveStashApplyResult = async (cmd: string, event: event.Event<EmitMessage<StashApplyOutcome | string>>) => {
         const result = event.payload;
+        hideOverlay(cmd);
         if (!result.is_ok) {
             alert(result.result);
             return;
         }
 
-        if (result.result) {
-            alert(result.result);
+        const outcome = result.result as StashApplyOutcome;
+        if (outcome.conflicts.length > 0) {
+            // stashは残っているので、衝突を解消した後に必要なら削除してもらう
+            alert("衝突しています。解消してください。" + (cmd === GitCommand.StashPop ? "\nstashは削除せずに残しています。" : "") + "\n" + outcome.conflicts.join("\n"));
+        }
+        if (outcome.dropped) {
+            setSelectedStash(null);
+            setStashFiles([]);
+            setStashDiff("");
         }
 
         await Promise.all([
-            gitBranch(),
-            gitLog(),
-            getPullPushCount(),
-        ])
+            fetchStatus(),
+            gitStashList(),
+        ]);
+    }
 
-        hideOverlay(GitCommand.BranchMerge);
+    const recieveStashDropResult = async (event: event.Event<EmitMessage<string>>) => {
+        const result = event.payload;
+        hideOverlay(GitCommand.StashDrop);
+        if (!result.is_ok) {
+            alert(result.result);
+            return;
+        }
+
+        setSelectedStash(null);
+        setStashFiles([]);
+        setStashDiff("");
+        await gitStashList();
     }
 
-    const gitCheckoutHash = async (commitHash: string) => {
+    const selectStash = async (stash: StashEntry) => {
+        setSelectedStash(stash);
+        setStashDiff("");
         try {
-            showOverlay(GitCommand.CheckoutHash, true);
-            await invoke(GitCommand.CheckoutHash, {
-                windowLabel: getCurrent().label,
-                commitHash: commitHash,
-            });
+            showOverlay(GitCommand.StashShowFiles, false);
+            await invoke(GitCommand.StashShowFiles, {windowLabel: getCurrent().label, hash: stash.hash});
         } catch (error) {
-            console.error("Failed to git checkout hash:", error);
+            hideOverlay(GitCommand.StashShowFiles);
+            console.error("Failed to git stash show files:", error);
         }
     };
 
-    const recieveCheckoutHashResult = async (event: event.Event<EmitMessage<string>>) => {
+    const recieveStashShowFilesResult = async (event: event.Event<EmitMessage<StatusInfo[]>>) => {
         const result = event.payload;
+        hideOverlay(GitCommand.StashShowFiles);
         if (!result.is_ok) {
-            alert(result.result);
+            console.error(result.result);
             return;
         }
+        setStashFiles(result.result);
+    }
 
-        if (result.result) {
-            alert(result.result);
+    const gitStashShowFileDiff = async (hash: string, file: string) => {
+        try {
+            await invoke("git_stash_show_file_diff_cancel", {windowLabel: getCurrent().label});
+            showNoBlockOverlay(GitCommand.StashShowFileDiff);
+            await invoke(GitCommand.StashShowFileDiff, {windowLabel: getCurrent().label, hash: hash, file: file});
+        } catch (error) {
+            hideNoBlockOverlay(GitCommand.StashShowFileDiff);
+            console.error("Failed to git stash show file diff:", error);
         }
+    };
 
-        await Promise.all([
-            gitBranch(),
-            gitLog(),
-            getPullPushCount(),
-        ])
-
-        hideOverlay(GitCommand.CheckoutHash);
+    const recieveStashShowFileDiffResult = async (event: event.Event<EmitMessage<DiffResult>>) => {
+        const result = event.payload;
+        hideNoBlockOverlay(GitCommand.StashShowFileDiff);
+        if (!result.is_ok) {
+            console.error(result.result);
+            return;
+        }
+        setStashDiff(result.result.raw ?? "");
     }
 
     const minimize = () => {
@@ -1456,7 +2569,7 @@ function App() {
             const windowPosition = await appWindow.outerPosition();
             const absoluteX = windowPosition.x + x;
             const absoluteY = windowPosition.y + y;
-            await invoke('open_new_window', {hash: hash, x: absoluteX, y: absoluteY});
+            await invoke('open_new_window', {windowLabel: getCurrent().label, hash: hash, x: absoluteX, y: absoluteY});
         } catch (error) {
             console.error("Failed to openNewWindow:", error);
         }
@@ -1488,6 +2601,7 @@ function App() {
 
         if (cancelCmds.length > 0) return;
 
+        setGitProgress(null);
         let overlay = document.getElementById('overlay');
         if (overlay) {
             overlay.style.display = 'none';
@@ -1529,6 +2643,21 @@ function App() {
             case ChangeState.Add:
                 res = "A : " + res;
                 break;
+            case ChangeState.Rename:
+                res = "R : " + (info.orig_filename ? info.orig_filename + " → " : "") + res;
+                break;
+            case ChangeState.Copy:
+                res = "C : " + (info.orig_filename ? info.orig_filename + " → " : "") + res;
+                break;
+            case ChangeState.TypeChange:
+                res = "T : " + res;
+                break;
+            case ChangeState.Unmerged:
+                res = "U : " + res;
+                break;
+            case ChangeState.SubmoduleChange:
+                res = "S : " + res;
+                break;
         }
         return res;
     }
@@ -1543,6 +2672,23 @@ function App() {
         return res;
     }
 
+    // 衝突しているファイルと、どちらかの内容で解消するボタン
+    const getConflictList = () => {
+        return conflicts.map((entry) => (
+            <div key={entry.path} style={{display: 'flex', columnGap: '10px', paddingTop: '5px'}}>
+                <div style={{flex: 1, overflow: 'hidden', textOverflow: 'ellipsis'}} title={entry.path}>
+                    {entry.path}
+                </div>
+                <button onClick={() => resolveConflict(entry.path, ConflictSide.Ours)}>
+                    {entry.ours === null ? "Delete (ours)" : "Ours"}
+                </button>
+                <button onClick={() => resolveConflict(entry.path, ConflictSide.Theirs)}>
+                    {entry.theirs === null ? "Delete (theirs)" : "Theirs"}
+                </button>
+            </div>
+        ));
+    }
+
     const getCommitPanel = () => {
         return (
             <div style={{display: 'flex', flex: 1, overflow: 'hidden'}}>
@@ -1597,6 +2743,64 @@ function App() {
                             ))}
                         </ul>
                     </div>
+                    {rebaseState?.in_progress && (
+                        <div style={{padding: '10px', borderTop: '1px solid #ddd'}}>
+                            <div style={{display: 'flex', columnGap: '10px', alignItems: 'center'}}>
+                                <div style={{flex: 1, overflow: 'hidden', textOverflow: 'ellipsis'}}>
+                                    {"Rebasing " + (rebaseState.branch_name ?? "HEAD") +
+                                        (rebaseState.current_step && rebaseState.total_steps ? " (" + rebaseState.current_step + "/" + rebaseState.total_steps + ")" : "") +
+                                        (rebaseState.stopped_at ? ": " + rebaseState.stopped_at.substring(0, 7) + " " + (rebaseState.stopped_subject ?? "") : "")}
+                                </div>
+                                <button onClick={() => stepRebase(GitCommand.RebaseContinue)} disabled={conflicts.length > 0}>
+                                    Continue
+                                </button>
+                                <button onClick={() => stepRebase(GitCommand.RebaseSkip)}>
+                                    Skip
+                                </button>
+                                <button onClick={() => stepRebase(GitCommand.RebaseAbort)}>
+                                    Abort
+                                </button>
+                            </div>
+                            {getConflictList()}
+                        </div>
+                    )}
+                    {sequencerState?.command != null && (
+                        <div style={{padding: '10px', borderTop: '1px solid #ddd'}}>
+                            <div style={{display: 'flex', columnGap: '10px', alignItems: 'center'}}>
+                                <div style={{flex: 1, overflow: 'hidden', textOverflow: 'ellipsis'}}>
+                                    {(sequencerState.command === SequencerCommand.Revert ? "Reverting" : "Cherry-picking") +
+                                        (sequencerState.stopped_at ? ": " + sequencerState.stopped_at.substring(0, 7) + " " + (sequencerState.stopped_subject ?? "") : "") +
+                                        (sequencerState.remaining > 0 ? " (残り " + sequencerState.remaining + ")" : "")}
+                                </div>
+                                <button onClick={() => stepSequencer(GitCommand.SequencerContinue)} disabled={conflicts.length > 0}>
+                                    Continue
+                                </button>
+                                <button onClick={() => stepSequencer(GitCommand.SequencerSkip)}>
+                                    Skip
+                                </button>
+                                <button onClick={() => stepSequencer(GitCommand.SequencerAbort)}>
+                                    Abort
+                                </button>
+                            </div>
+                            {getConflictList()}
+                        </div>
+                    )}
+                    {isMerging && !rebaseState?.in_progress && sequencerState?.command == null && (
+                        <div style={{padding: '10px', borderTop: '1px solid #ddd'}}>
+                            <div style={{display: 'flex', columnGap: '10px', alignItems: 'center'}}>
+                                <div style={{flex: 1}}>
+                                    {conflicts.length > 0 ? "Conflicts: " + conflicts.length : "All conflicts resolved"}
+                                </div>
+                                <button onClick={() => finishMerge(GitCommand.MergeContinue)} disabled={conflicts.length > 0}>
+                                    Continue merge
+                                </button>
+                                <button onClick={() => finishMerge(GitCommand.MergeAbort)}>
+                                    Abort merge
+                                </button>
+                            </div>
+                            {getConflictList()}
+                        </div>
+                    )}
                     <div style={{
                         display: 'flex',
                         padding: '10px',
@@ -1612,6 +2816,9 @@ function App() {
                         <button onClick={handleCommit} style={{marginLeft: '10px'}}>
                             Commit
                         </button>
+                        <button onClick={openStashPanel}>
+                            Stash
+                        </button>
                     </div>
                 </div>
 
@@ -1770,8 +2977,9 @@ function App() {
                         <div style={{overflowX: 'auto', overflowY: 'hidden'}}>
                             {commits.map((v, index) => (
                                 <pre key={`date-${index}`}
+                                     onClick={(event) => toggleSelectedCommit(event, v.hash)}
                                      onContextMenu={(event) => handleContextMenu_log(event, v)}
-                                     style={{height: '10px'}}>
+                                     style={{height: '10px', backgroundColor: selectedCommits.includes(v.hash) ? 'rgba(0,0,0,0.1)' : undefined}}>
                                     {v.date}
                                 </pre>
                             ))}
@@ -1799,8 +3007,9 @@ function App() {
                         }}>
                             {commits.map((v, index) => (
                                 <pre key={`message-${index}`}
+                                     onClick={(event) => toggleSelectedCommit(event, v.hash)}
                                      onContextMenu={(event) => handleContextMenu_log(event, v)}
-                                     style={{height: '10px'}}>
+                                     style={{height: '10px', backgroundColor: selectedCommits.includes(v.hash) ? 'rgba(0,0,0,0.1)' : undefined}}>
                                 {<p>{parse(ansiToHtml(v.branch))} {v.message}</p>}
                             </pre>
                             ))}
@@ -1985,6 +3194,32 @@ function App() {
                 <button onClick={selectGitFolder} style={{minHeight: '40px', minWidth: '50px'}}>
                     Open
                 </button>
+                <button onClick={openRepositoryWindow} style={{minHeight: '40px', minWidth: '50px'}}>
+                    New Window
+                </button>
+                <input value={cloneUrl}
+                       onChange={(e) => setCloneUrl(e.target.value)}
+                       placeholder={"Repository URL"}
+                       style={{minHeight: '34px', width: '160px'}}/>
+                <button onClick={gitClone} style={{minHeight: '40px', minWidth: '50px'}}>
+                    Clone
+                </button>
+                <select value={gitFolderPath}
+                        onChange={(e) => openRecentRepository(e.target.value)}
+                        style={{minHeight: '40px', maxWidth: '200px'}}>
+                    {!recentRepositories.some(v => v.path === gitFolderPath) && <option value={gitFolderPath}></option>}
+                    {recentRepositories.map(v => (
+                        <option key={v.path} value={v.path} title={v.path}>
+                            {(v.pinned ? "★ " : "") + v.display_name + (v.missing ? " (not found)" : "")}
+                        </option>
+                    ))}
+                </select>
+                <button onClick={togglePinRepository} style={{minHeight: '40px', minWidth: '50px'}}>
+                    {recentRepositories.find(v => v.path === gitFolderPath)?.pinned ? "Unpin" : "Pin"}
+                </button>
+                <button onClick={undoLastOperation} style={{minHeight: '40px', minWidth: '50px'}}>
+                    Undo
+                </button>
                 <button onClick={gitFetch} style={{minHeight: '40px', minWidth: '50px'}}>
                     Fetch
                 </button>
@@ -2019,6 +3254,175 @@ function App() {
             </div>
 
 
+            {/*対話的リベースの計画*/}
+            {rebasePlan &&
+                <div style={{
+                    position: 'fixed',
+                    inset: 0,
+                    backgroundColor: 'rgba(0,0,0,0.4)',
+                    zIndex: 999, // 実行中のオーバーレイ(1000)より下
+                    display: 'flex',
+                    alignItems: 'center',
+                    justifyContent: 'center',
+                }}>
+                    <div className={"card middle"} style={{
+                        flexDirection: 'column',
+                        width: '80%',
+                        maxHeight: '80%',
+                        overflow: 'auto',
+                        padding: '10px',
+                        backgroundColor: '#EBECF0',
+                    }}>
+                        <div style={{paddingBottom: '10px'}}>
+                            {"Interactive rebase onto " + rebasePlan.base.substring(0, 7) + " (上のコミットから順に適用します)"}
+                        </div>
+                        {rebasePlan.entries.map((entry, index) => (
+                            <div key={entry.hash} style={{display: 'flex', flexDirection: 'column', paddingBottom: '5px'}}>
+                                <div style={{display: 'flex', columnGap: '10px', alignItems: 'center'}}>
+                                    <select value={entry.action}
+                                            onChange={(e) => updateTodoEntry(index, {action: Number(e.target.value)})}>
+                                        <option value={TodoAction.Pick}>pick</option>
+                                        <option value={TodoAction.Reword}>reword</option>
+                                        <option value={TodoAction.Squash}>squash</option>
+                                        <option value={TodoAction.Fixup}>fixup</option>
+                                        <option value={TodoAction.Drop}>drop</option>
+                                    </select>
+                                    <div style={{
+                                        flex: 1,
+                                        overflow: 'hidden',
+                                        textOverflow: 'ellipsis',
+                                        whiteSpace: 'nowrap',
+                                        textDecoration: entry.action === TodoAction.Drop ? 'line-through' : 'none',
+                                    }}>
+                                        {entry.short_hash + " " + entry.subject}
+                                    </div>
+                                    <button onClick={() => moveTodoEntry(index, -1)} disabled={index === 0}>↑</button>
+                                    <button onClick={() => moveTodoEntry(index, 1)} disabled={index === rebasePlan.entries.length - 1}>↓</button>
+                                </div>
+                                {(entry.action === TodoAction.Reword || entry.action === TodoAction.Squash) &&
+                                    <textarea
+                                        placeholder={entry.action === TodoAction.Squash ? "まとめた後のメッセージ (空の場合は各コミットのメッセージを並べたもの)" : "新しいメッセージ"}
+                                        value={entry.message ?? ""}
+                                        onChange={(e) => updateTodoEntry(index, {message: e.target.value === "" ? null : e.target.value})}
+                                        style={{marginTop: '5px'}}
+                                    />
+                                }
+                            </div>
+                        ))}
+                        <div style={{display: 'flex', columnGap: '10px', justifyContent: 'flex-end', paddingTop: '10px'}}>
+                            <button onClick={() => setRebasePlan(null)}>
+                                Cancel
+                            </button>
+                            <button onClick={gitRebaseInteractive}>
+                                Rebase
+                            </button>
+                        </div>
+                    </div>
+                </div>
+            }
+
+            {isStashPanelVisible &&
+                <div style={{
+                    position: 'fixed',
+                    inset: 0,
+                    backgroundColor: 'rgba(0,0,0,0.4)',
+                    zIndex: 999, // 実行中のオーバーレイ(1000)より下
+                    display: 'flex',
+                    alignItems: 'center',
+                    justifyContent: 'center',
+                }}>
+                    <div className={"card middle"} style={{
+                        flexDirection: 'column',
+                        width: '80%',
+                        height: '80%',
+                        overflow: 'hidden',
+                        padding: '10px',
+                        backgroundColor: '#EBECF0',
+                    }}>
+                        {/* 作成 */}
+                        <div style={{display: 'flex', columnGap: '10px', alignItems: 'center', paddingBottom: '10px'}}>
+                            <input
+                                placeholder="Stash message"
+                                value={stashMessage}
+                                onChange={(e) => setStashMessage(e.target.value)}
+                                style={{flex: 1}}
+                            />
+                            <label>
+                                <input type="checkbox" checked={stashIncludeUntracked}
+                                       onChange={(e) => setStashIncludeUntracked(e.target.checked)}/>
+                                Include untracked
+                            </label>
+                            <label>
+                                <input type="checkbox" checked={stashKeepIndex}
+                                       onChange={(e) => setStashKeepIndex(e.target.checked)}/>
+                                Keep index
+                            </label>
+                            <button onClick={gitStashPush}>
+                                {checkedFiles.size > 0 ? "Stash " + checkedFiles.size + " files" : "Stash all changes"}
+                            </button>
+                        </div>
+                        <div style={{display: 'flex', flex: 1, columnGap: '10px', overflow: 'hidden'}}>
+                            {/* 一覧 */}
+                            <ul style={{width: '40%', overflowY: 'auto', margin: 0, padding: 0}}>
+                                {stashes.map(stash => (
+                                    <li key={stash.hash}
+                                        onClick={() => selectStash(stash)}
+                                        style={{
+                                            listStyle: 'none',
+                                            cursor: 'pointer',
+                                            padding: '5px',
+                                            backgroundColor: selectedStash?.hash === stash.hash ? 'rgba(0,0,0,0.1)' : undefined,
+                                        }}>
+                                        <div style={{overflow: 'hidden', textOverflow: 'ellipsis', whiteSpace: 'nowrap'}}>
+                                            {stash.name + " " + stash.message}
+                                        </div>
+                                        <div style={{display: 'flex', columnGap: '5px', alignItems: 'center'}}>
+                                            <div style={{flex: 1, fontSize: 'small'}}>
+                                                {(stash.branch ?? "(no branch)") + " " + stash.date}
+                                            </div>
+                                            <button onClick={(e) => { e.stopPropagation(); stepStash(GitCommand.StashApply, stash); }}>Apply</button>
+                                            <button onClick={(e) => { e.stopPropagation(); stepStash(GitCommand.StashPop, stash); }}>Pop</button>
+                                            <button onClick={(e) => { e.stopPropagation(); stepStash(GitCommand.StashDrop, stash); }}>Drop</button>
+                                        </div>
+                                    </li>
+                                ))}
+                                {stashes.length === 0 && <li style={{listStyle: 'none', padding: '5px'}}>No stashes</li>}
+                            </ul>
+                            {/* 選択したstashの内容 */}
+                            <div style={{flex: 1, display: 'flex', flexDirection: 'column', overflow: 'hidden'}}>
+                                <ul style={{maxHeight: '30%', overflowY: 'auto', margin: 0, padding: 0}}>
+                                    {selectedStash && stashFiles.map(info => (
+                                        <li key={info.filename}
+                                            onClick={() => gitStashShowFileDiff(selectedStash.hash, info.filename)}
+                                            style={{listStyle: 'none', cursor: 'pointer', padding: '2px 5px'}}>
+                                            {getFileNameWithChangeState(info)}
+                                        </li>
+                                    ))}
+                                </ul>
+                                <div className={"card shallow-inset"}
+                                     style={{
+                                         flex: 1,
+                                         overflowY: 'auto',
+                                         padding: '0px 18px',
+                                         margin: '10px 0px 0px 0px',
+                                         flexDirection: 'column',
+                                         userSelect: 'text',
+                                         WebkitUserSelect: 'text',
+                                         cursor: 'auto',
+                                     }}>
+                                    {renderDiff(stashDiff)}
+                                </div>
+                            </div>
+                        </div>
+                        <div style={{display: 'flex', justifyContent: 'flex-end', paddingTop: '10px'}}>
+                            <button onClick={() => setIsStashPanelVisible(false)}>
+                                Close
+                            </button>
+                        </div>
+                    </div>
+                </div>
+            }
+
             {/*オーバーレイ*/}
             <div id={"overlay"}>
                 <div style={{
@@ -2044,6 +3448,29 @@ function App() {
                             <div className={"dot-spinner__dot"}></div>
                         </div>
                     </div>
+                    {gitProgress &&
+                        <div style={{
+                            position: 'absolute',
+                            top: '60%',
+                            left: '50%',
+                            transform: 'translate(-50%,-50%)',
+                            width: '300px',
+                            color: '#fff',
+                            textAlign: 'center',
+                        }}>
+                            <div>
+                                {(gitProgress.is_remote ? "remote: " : "") + gitProgress.phase}
+                                {gitProgress.current !== null && gitProgress.total !== null ? ` (${gitProgress.current}/${gitProgress.total})` : ""}
+                                {gitProgress.current !== null && gitProgress.total === null ? ` (${gitProgress.current})` : ""}
+                            </div>
+                            {gitProgress.percent !== null &&
+                                <progress value={gitProgress.percent} max={100} style={{width: '100%'}}/>
+                            }
+                            <div style={{fontSize: '12px'}}>
+                                {[gitProgress.transferred, gitProgress.rate].filter(v => v).join(" | ")}
+                            </div>
+                        </div>
+                    }
                     {isVisibleoverlayCancelButton &&
                         <button
                             className={"cancel-button"}
@@ -2191,6 +3618,54 @@ function App() {
                         >
                             現在のブランチにこのブランチをマージ
                         </li>
+                        <li
+                            onClick={() => gitBranchMerge(contextMenu_branch.branchInfo.branch_name, MergeMode.NoFastForward)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            マージコミットを作成してマージ (--no-ff)
+                        </li>
+                        <li
+                            onClick={() => gitBranchMerge(contextMenu_branch.branchInfo.branch_name, MergeMode.FastForwardOnly)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            fast-forwardできる場合のみマージ (--ff-only)
+                        </li>
+                        <li
+                            onClick={() => gitBranchMerge(contextMenu_branch.branchInfo.branch_name, MergeMode.Squash)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            変更をまとめて取り込む (--squash)
+                        </li>
+                        <li
+                            onClick={() => gitRebase(contextMenu_branch.branchInfo.branch_name)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            現在のブランチをこのブランチにリベース
+                        </li>
                         {
                             contextMenu_branch.branchInfo.branch_state !== BranchState.Remote &&
                             <li
@@ -2238,6 +3713,102 @@ function App() {
                         >
                             このコミットにチェックアウト
                         </li>
+                        <li
+                            onClick={() => gitRebase(contextMenu_log.commitInfo.hash)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            現在のブランチをこのコミットにリベース
+                        </li>
+                        <li
+                            onClick={() => gitRebasePlan(contextMenu_log.commitInfo.hash)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            このコミットより後のコミットを整理 (interactive rebase)
+                        </li>
+                        <li
+                            onClick={() => gitCherryPick(getPickTargets(contextMenu_log.commitInfo.hash), false)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            {"cherry-pick (" + getPickTargets(contextMenu_log.commitInfo.hash).length + ")"}
+                        </li>
+                        <li
+                            onClick={() => gitCherryPick(getPickTargets(contextMenu_log.commitInfo.hash), true)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            {"cherry-pick -x (" + getPickTargets(contextMenu_log.commitInfo.hash).length + ")"}
+                        </li>
+                        <li
+                            onClick={() => gitRevert(getPickTargets(contextMenu_log.commitInfo.hash))}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            {"revert (" + getPickTargets(contextMenu_log.commitInfo.hash).length + ")"}
+                        </li>
+                        <li
+                            onClick={() => gitResetPreview(contextMenu_log.commitInfo.hash, ResetMode.Soft)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            現在のブランチをこのコミットにリセット (soft)
+                        </li>
+                        <li
+                            onClick={() => gitResetPreview(contextMenu_log.commitInfo.hash, ResetMode.Mixed)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            現在のブランチをこのコミットにリセット (mixed)
+                        </li>
+                        <li
+                            onClick={() => gitResetPreview(contextMenu_log.commitInfo.hash, ResetMode.Hard)}
+                            style={{
+                                cursor: 'pointer',
+                                padding: '5px',
+                                backgroundColor: 'rgba(0,0,0,0.4)'
+                            }}
+                            onMouseEnter={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.6)'}
+                            onMouseLeave={(e) => e.currentTarget.style.backgroundColor = 'rgba(0,0,0,0.4)'}
+                        >
+                            現在のブランチをこのコミットにリセット (hard)
+                        </li>
                     </ul>
                 )
             }
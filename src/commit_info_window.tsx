@@ -21,6 +21,14 @@ enum ChangeState {
     Staging,
     Delete,
     Add,
+    Unmodified,
+    Rename,
+    Copy,
+    TypeChange,
+    Unmerged,
+    Untracked,
+    Ignored,
+    SubmoduleChange,
 }
 
 class StatusInfo {
@@ -31,6 +39,11 @@ class StatusInfo {
 
     change_state: number;
     filename: string;
+    index_state?: number;
+    worktree_state?: number;
+    orig_filename?: string | null;
+    conflict?: number | null;
+    is_submodule?: boolean;
 }
 
 class ShowInfo {
@@ -47,6 +60,11 @@ class ShowInfo {
     message: string;
 }
 
+interface DiffResult {
+    raw: string;
+    files: object[];
+}
+
 interface EmitMessage<T> {
     is_ok: boolean;
     result: T;
@@ -106,7 +124,7 @@ function NewWindow() {
         const showFilesResult = listen<EmitMessage<StatusInfo[]>>('post-git-show-files-result', (event) => {
             recieveShowFilesResult(event);
         });
-        const showFileDiffResultEvent = listen<EmitMessage<string>>('post-git-show-file-diff-result', (event) => {
+        const showFileDiffResultEvent = listen<EmitMessage<DiffResult>>('post-git-show-file-diff-result', (event) => {
             recieveShowFileDiffResult(event);
         });
 
@@ -222,7 +240,7 @@ function NewWindow() {
         }
     };
 
-    const recieveShowFileDiffResult = async (event: event.Event<EmitMessage<string>>) => {
+    const recieveShowFileDiffResult = async (event: event.Event<EmitMessage<DiffResult>>) => {
         const result = event.payload;
         if (!result.is_ok) {
             console.error(result.result);
@@ -234,8 +252,8 @@ function NewWindow() {
             diffScrollRef.current.scrollTop = 0;
         }
 
-        if (result.result) {
-            setDiffResult(result.result);
+        if (result.result.raw) {
+            setDiffResult(result.result.raw);
         } else {
             setDiffResult("");
         }
@@ -305,6 +323,21 @@ function NewWindow() {
             case ChangeState.Add:
                 res = "A : " + res;
                 break;
+            case ChangeState.Rename:
+                res = "R : " + (info.orig_filename ? info.orig_filename + " → " : "") + res;
+                break;
+            case ChangeState.Copy:
+                res = "C : " + (info.orig_filename ? info.orig_filename + " → " : "") + res;
+                break;
+            case ChangeState.TypeChange:
+                res = "T : " + res;
+                break;
+            case ChangeState.Unmerged:
+                res = "U : " + res;
+                break;
+            case ChangeState.SubmoduleChange:
+                res = "S : " + res;
+                break;
         }
         return res;
     }